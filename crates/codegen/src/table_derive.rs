@@ -11,6 +11,7 @@ pub fn impl_to_table_name(ast: &syn::MacroInput) -> quote::Tokens {
                 rustorm_dao::TableName{
                     name: stringify!(#name).to_lowercase().into(),
                     schema: None,
+                    catalog: None,
                     alias: None,
                 }
             }
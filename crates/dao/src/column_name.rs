@@ -1,4 +1,15 @@
-use crate::common;
+use crate::{
+    common,
+    error::NameParseError,
+    parse::{
+        self,
+        QualifiedName,
+    },
+    quote::{
+        Dialect,
+        UnsafeSql,
+    },
+};
 use serde_derive::{
     Deserialize,
     Serialize,
@@ -12,29 +23,39 @@ pub struct ColumnName {
 }
 
 impl ColumnName {
-    /// create table with name
+    /// create column with name, which can be qualified with the table.
+    /// Quoted parts may contain a `.`, ie: `film."rental.rate"`.
+    /// A name which can not be split is used as is
     pub fn from(arg: &str) -> Self {
-        if arg.contains('.') {
-            let splinters = arg.split('.').collect::<Vec<&str>>();
-            assert!(
-                splinters.len() == 2,
-                "There should only be 2 parts, trying to split `.` {}",
-                arg
-            );
-            let table = splinters[0].to_owned();
-            let name = splinters[1].to_owned();
-            ColumnName {
-                name,
-                table: Some(table),
-                alias: None,
-            }
-        } else {
-            ColumnName {
-                name: arg.to_owned(),
-                table: None,
-                alias: None,
-            }
+        parse::parse_name(arg, None)
+            .and_then(|parsed| Self::from_parsed(arg, parsed))
+            .unwrap_or_else(|_| {
+                ColumnName {
+                    name: arg.to_owned(),
+                    table: None,
+                    alias: None,
+                }
+            })
+    }
+
+    /// parse the column name as it would be written in the sql of the dialect,
+    /// ie: `f."Title" AS title`.
+    /// Unquoted parts are folded to lowercase in postgresql
+    pub fn parse(arg: &str, dialect: Dialect) -> Result<Self, NameParseError> {
+        Self::from_parsed(arg, parse::parse_name(arg, Some(dialect))?)
+    }
+
+    fn from_parsed(arg: &str, parsed: QualifiedName) -> Result<Self, NameParseError> {
+        let QualifiedName { mut parts, alias } = parsed;
+        if parts.len() > 2 {
+            return Err(NameParseError::TooManyParts(arg.to_string(), 2));
         }
+        let name = parts.pop().expect("must have at least 1 part");
+        Ok(ColumnName {
+            name,
+            table: parts.pop(),
+            alias,
+        })
     }
 
     /// return the long name of the table using schema.table_name
@@ -47,8 +68,33 @@ impl ColumnName {
 
     pub fn safe_complete_name(&self) -> String {
         match self.table {
-            Some(ref table) => format!("{}.{}", common::keywords_safe(table), self.name),
-            None => self.name.to_owned(),
+            Some(ref table) => {
+                format!(
+                    "{}.{}",
+                    common::keywords_safe(table),
+                    common::keywords_safe(&self.name)
+                )
+            }
+            None => common::keywords_safe(&self.name),
+        }
+    }
+
+    /// the name of the column quoted for the dialect when needed
+    pub fn quoted_name(&self, dialect: Dialect) -> Result<String, UnsafeSql> {
+        dialect.safe_identifier(&self.name)
+    }
+
+    /// the table qualified name of the column quoted for the dialect when needed
+    pub fn quoted_complete_name(&self, dialect: Dialect) -> Result<String, UnsafeSql> {
+        match self.table {
+            Some(ref table) => {
+                Ok(format!(
+                    "{}.{}",
+                    dialect.safe_identifier(table)?,
+                    self.quoted_name(dialect)?
+                ))
+            }
+            None => self.quoted_name(dialect),
         }
     }
 }
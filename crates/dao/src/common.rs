@@ -1,11 +1,13 @@
-fn is_keyword(s: &str) -> bool {
-    let keywords = ["user", "role"];
-    keywords.contains(&s)
-}
+use crate::quote::{
+    self,
+    Dialect,
+};
 
+/// quote the identifier with double quotes when it is a reserved word
+/// or it is not a plain identifier
 pub fn keywords_safe(s: &str) -> String {
-    if is_keyword(s) {
-        format!("\"{}\"", s)
+    if Dialect::Postgres.needs_quoting(s) {
+        quote::quote_unchecked(s, '"')
     } else {
         s.to_string()
     }
@@ -12,7 +12,13 @@ use serde::{
     Deserialize,
     Deserializer,
 };
-use std::collections::BTreeMap;
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    hash::BuildHasher,
+};
 
 #[derive(Debug, PartialEq, Clone, Default)]
 pub struct Dao(pub BTreeMap<String, Value>);
@@ -101,6 +107,20 @@ pub trait ToDao {
     fn to_dao(&self) -> Dao;
 }
 
+impl ToDao for Dao {
+    fn to_dao(&self) -> Dao { self.clone() }
+}
+
+impl ToDao for BTreeMap<String, Value> {
+    fn to_dao(&self) -> Dao { Dao(self.clone()) }
+}
+
+impl<S: BuildHasher> ToDao for HashMap<String, Value, S> {
+    fn to_dao(&self) -> Dao {
+        Dao(self.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
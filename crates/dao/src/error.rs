@@ -14,3 +14,15 @@ pub enum DaoError {
     #[error("No such value {0}")]
     NoSuchValueError(String),
 }
+
+#[derive(Error, Debug, PartialEq)]
+pub enum NameParseError {
+    #[error("Empty identifier in name {0:?}")]
+    EmptyPart(String),
+    #[error("Unterminated quoted identifier in name {0:?}")]
+    UnterminatedQuote(String),
+    #[error("Unexpected character {1:?} in name {0:?}")]
+    UnexpectedCharacter(String, char),
+    #[error("Name {0:?} has too many parts, at most {1} are allowed")]
+    TooManyParts(String, usize),
+}
@@ -13,8 +13,13 @@ pub use dao::{
 pub use error::{
     ConvertError,
     DaoError,
+    NameParseError,
 };
 pub use interval::Interval;
+pub use quote::{
+    Dialect,
+    UnsafeSql,
+};
 pub use rows::Rows;
 pub use table_name::{
     TableName,
@@ -32,6 +37,8 @@ mod common;
 mod dao;
 mod error;
 mod interval;
+mod parse;
+pub mod quote;
 mod rows;
 mod table_name;
 pub mod value;
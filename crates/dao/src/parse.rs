@@ -0,0 +1,197 @@
+//! Parsing of the possibly quoted and qualified table and column names
+use crate::{
+    error::NameParseError,
+    quote::Dialect,
+};
+
+/// The parts of a qualified name and its alias, ie: `schema.table AS t`
+#[derive(Debug, PartialEq)]
+pub(crate) struct QualifiedName {
+    pub parts: Vec<String>,
+    pub alias: Option<String>,
+}
+
+/// parse the name using the quoting and case folding rules of the dialect.
+/// Without a dialect, any of the quote characters are accepted, unquoted parts are taken as is
+/// up to the next `.` and no alias is expected
+pub(crate) fn parse_name(
+    arg: &str,
+    dialect: Option<Dialect>,
+) -> Result<QualifiedName, NameParseError> {
+    let chars: Vec<char> = arg.chars().collect();
+    let mut i = 0;
+    let mut parts = vec![];
+    loop {
+        if dialect.is_some() {
+            skip_whitespace(&chars, &mut i);
+        }
+        let (part, _quoted) = identifier(arg, &chars, &mut i, dialect)?;
+        parts.push(part);
+        if dialect.is_some() {
+            skip_whitespace(&chars, &mut i);
+        }
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    let mut alias = None;
+    if i < chars.len() && dialect.is_some() {
+        let (word, quoted) = identifier(arg, &chars, &mut i, dialect)?;
+        if !quoted && word.eq_ignore_ascii_case("as") {
+            skip_whitespace(&chars, &mut i);
+            alias = Some(identifier(arg, &chars, &mut i, dialect)?.0);
+        } else {
+            alias = Some(word);
+        }
+        skip_whitespace(&chars, &mut i);
+    }
+    match chars.get(i) {
+        Some(c) => Err(NameParseError::UnexpectedCharacter(arg.to_string(), *c)),
+        None => Ok(QualifiedName { parts, alias }),
+    }
+}
+
+fn skip_whitespace(chars: &[char], i: &mut usize) {
+    while chars.get(*i).map(|c| c.is_whitespace()).unwrap_or(false) {
+        *i += 1;
+    }
+}
+
+/// the closing quote of the quoted identifier started with this character
+fn closing_quote(open: char, dialect: Option<Dialect>) -> Option<char> {
+    match (open, dialect) {
+        ('"', Some(Dialect::Postgres)) | ('"', Some(Dialect::Sqlite)) | ('"', None) => Some('"'),
+        ('`', Some(Dialect::Mysql)) | ('`', Some(Dialect::Sqlite)) | ('`', None) => Some('`'),
+        ('[', Some(Dialect::Sqlite)) | ('[', None) => Some(']'),
+        _ => None,
+    }
+}
+
+/// read the quoted or unquoted identifier at `i`, returning it and whether it was quoted.
+/// Unquoted identifiers are folded to lowercase in postgresql
+fn identifier(
+    arg: &str,
+    chars: &[char],
+    i: &mut usize,
+    dialect: Option<Dialect>,
+) -> Result<(String, bool), NameParseError> {
+    let first = match chars.get(*i) {
+        Some(c) => *c,
+        None => return Err(NameParseError::EmptyPart(arg.to_string())),
+    };
+    if let Some(close) = closing_quote(first, dialect) {
+        let mut ident = String::new();
+        *i += 1;
+        loop {
+            match chars.get(*i) {
+                // a doubled closing quote is an escaped quote
+                Some(c) if *c == close && chars.get(*i + 1) == Some(&close) && close != ']' => {
+                    ident.push(close);
+                    *i += 2;
+                }
+                Some(c) if *c == close => {
+                    *i += 1;
+                    break;
+                }
+                Some(c) => {
+                    ident.push(*c);
+                    *i += 1;
+                }
+                None => return Err(NameParseError::UnterminatedQuote(arg.to_string())),
+            }
+        }
+        if ident.is_empty() {
+            return Err(NameParseError::EmptyPart(arg.to_string()));
+        }
+        return Ok((ident, true));
+    }
+    let start = *i;
+    while let Some(c) = chars.get(*i) {
+        if *c == '.' {
+            break;
+        }
+        if dialect.is_some() {
+            if c.is_whitespace() {
+                break;
+            }
+            if !(c.is_alphanumeric() || *c == '_' || *c == '$') {
+                return Err(NameParseError::UnexpectedCharacter(arg.to_string(), *c));
+            }
+        }
+        *i += 1;
+    }
+    if start == *i {
+        return Err(NameParseError::EmptyPart(arg.to_string()));
+    }
+    let ident: String = chars[start..*i].iter().collect();
+    match dialect {
+        Some(Dialect::Postgres) => Ok((ident.to_lowercase(), false)),
+        _ => Ok((ident, false)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parsed(parts: &[&str], alias: Option<&str>) -> QualifiedName {
+        QualifiedName {
+            parts: parts.iter().map(|p| p.to_string()).collect(),
+            alias: alias.map(|a| a.to_string()),
+        }
+    }
+
+    #[test]
+    fn quoted_and_folded() {
+        assert_eq!(
+            parse_name("Public.\"Sales.2020\" AS s", Some(Dialect::Postgres)),
+            Ok(parsed(&["public", "Sales.2020"], Some("s")))
+        );
+        assert_eq!(
+            parse_name("db . `Order``s` o", Some(Dialect::Mysql)),
+            Ok(parsed(&["db", "Order`s"], Some("o")))
+        );
+        assert_eq!(
+            parse_name("main.[my table]", Some(Dialect::Sqlite)),
+            Ok(parsed(&["main", "my table"], None))
+        );
+        assert_eq!(
+            parse_name("sakila.public.Actor", Some(Dialect::Sqlite)),
+            Ok(parsed(&["sakila", "public", "Actor"], None))
+        );
+    }
+
+    #[test]
+    fn lenient_without_dialect() {
+        assert_eq!(
+            parse_name("public.first name", None),
+            Ok(parsed(&["public", "first name"], None))
+        );
+        assert_eq!(
+            parse_name("\"a.b\".c", None),
+            Ok(parsed(&["a.b", "c"], None))
+        );
+    }
+
+    #[test]
+    fn invalid_names() {
+        assert_eq!(
+            parse_name("a..b", None),
+            Err(NameParseError::EmptyPart("a..b".to_string()))
+        );
+        assert_eq!(
+            parse_name("\"abc", Some(Dialect::Postgres)),
+            Err(NameParseError::UnterminatedQuote("\"abc".to_string()))
+        );
+        assert_eq!(
+            parse_name("film-actor", Some(Dialect::Postgres)),
+            Err(NameParseError::UnexpectedCharacter("film-actor".to_string(), '-'))
+        );
+        assert_eq!(
+            parse_name("film AS f g", Some(Dialect::Postgres)),
+            Err(NameParseError::UnexpectedCharacter("film AS f g".to_string(), 'g'))
+        );
+    }
+}
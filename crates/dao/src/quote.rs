@@ -0,0 +1,706 @@
+//! Quoting of identifiers and string literals for each sql dialect
+use thiserror::Error;
+
+/// The sql dialect which determines the quote character and the reserved words
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Dialect {
+    /// identifiers are quoted with `"`
+    Postgres,
+    /// identifiers are quoted with `"`
+    Sqlite,
+    /// identifiers are quoted with backticks
+    Mysql,
+}
+
+/// An identifier or literal which can not be quoted safely,
+/// such as an empty identifier or one containing a NUL character
+#[derive(Error, Debug, PartialEq)]
+#[error("Unsafe identifier or literal {0:?}")]
+pub struct UnsafeSql(pub String);
+
+impl Dialect {
+    pub fn quote_char(&self) -> char {
+        match *self {
+            Dialect::Postgres | Dialect::Sqlite => '"',
+            Dialect::Mysql => '`',
+        }
+    }
+
+    /// the word can not be used as an identifier without quoting it, case insensitive
+    pub fn is_reserved(&self, word: &str) -> bool {
+        let keywords = match *self {
+            Dialect::Postgres => POSTGRES_RESERVED,
+            Dialect::Sqlite => SQLITE_RESERVED,
+            Dialect::Mysql => MYSQL_RESERVED,
+        };
+        keywords
+            .binary_search(&word.to_ascii_lowercase().as_str())
+            .is_ok()
+    }
+
+    /// always quote the identifier, the quote character is escaped by doubling it
+    pub fn quote_identifier(&self, ident: &str) -> Result<String, UnsafeSql> {
+        if ident.is_empty() || ident.contains('\0') {
+            return Err(UnsafeSql(ident.to_string()));
+        }
+        Ok(quote_unchecked(ident, self.quote_char()))
+    }
+
+    /// quote the identifier only when it is a reserved word or it is not a plain identifier
+    /// made of letters, digits and underscores, or it has uppercase letters in postgresql
+    pub fn safe_identifier(&self, ident: &str) -> Result<String, UnsafeSql> {
+        if self.needs_quoting(ident) {
+            self.quote_identifier(ident)
+        } else {
+            Ok(ident.to_string())
+        }
+    }
+
+    /// quote the string literal, single quotes are escaped by doubling them.
+    /// Backslashes are escaped as well in mysql, where they are escape characters by default
+    pub fn quote_literal(&self, literal: &str) -> Result<String, UnsafeSql> {
+        if literal.contains('\0') {
+            return Err(UnsafeSql(literal.to_string()));
+        }
+        let escaped = match *self {
+            Dialect::Mysql => literal.replace('\\', "\\\\").replace('\'', "''"),
+            Dialect::Postgres | Dialect::Sqlite => literal.replace('\'', "''"),
+        };
+        Ok(format!("'{}'", escaped))
+    }
+
+    pub(crate) fn needs_quoting(&self, ident: &str) -> bool {
+        let mut chars = ident.chars();
+        let plain = match chars.next() {
+            Some(first) => {
+                (first.is_ascii_alphabetic() || first == '_')
+                    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }
+            None => false,
+        };
+        // unquoted identifiers are folded to lowercase in postgresql
+        let folded = *self == Dialect::Postgres && ident.chars().any(|c| c.is_uppercase());
+        !plain || folded || self.is_reserved(ident)
+    }
+}
+
+/// quote the identifier without checking it
+pub(crate) fn quote_unchecked(ident: &str, quote: char) -> String {
+    let doubled = format!("{}{}", quote, quote);
+    format!("{}{}{}", quote, ident.replace(quote, &doubled), quote)
+}
+
+/// the reserved key words and the key words which can not be a function or type name,
+/// from the Key Words appendix of the postgresql documentation, sorted
+const POSTGRES_RESERVED: &[&str] = &[
+    "all",
+    "analyse",
+    "analyze",
+    "and",
+    "any",
+    "array",
+    "as",
+    "asc",
+    "asymmetric",
+    "authorization",
+    "between",
+    "bigint",
+    "binary",
+    "bit",
+    "boolean",
+    "both",
+    "case",
+    "cast",
+    "char",
+    "character",
+    "check",
+    "coalesce",
+    "collate",
+    "collation",
+    "column",
+    "concurrently",
+    "constraint",
+    "create",
+    "cross",
+    "current_catalog",
+    "current_date",
+    "current_role",
+    "current_schema",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "dec",
+    "decimal",
+    "default",
+    "deferrable",
+    "desc",
+    "distinct",
+    "do",
+    "else",
+    "end",
+    "except",
+    "exists",
+    "extract",
+    "false",
+    "fetch",
+    "float",
+    "for",
+    "foreign",
+    "freeze",
+    "from",
+    "full",
+    "grant",
+    "greatest",
+    "group",
+    "grouping",
+    "having",
+    "ilike",
+    "in",
+    "initially",
+    "inner",
+    "inout",
+    "int",
+    "integer",
+    "intersect",
+    "interval",
+    "into",
+    "is",
+    "isnull",
+    "join",
+    "lateral",
+    "leading",
+    "least",
+    "left",
+    "like",
+    "limit",
+    "localtime",
+    "localtimestamp",
+    "national",
+    "natural",
+    "nchar",
+    "none",
+    "not",
+    "notnull",
+    "null",
+    "nullif",
+    "numeric",
+    "offset",
+    "on",
+    "only",
+    "or",
+    "order",
+    "out",
+    "outer",
+    "overlaps",
+    "overlay",
+    "placing",
+    "position",
+    "precision",
+    "primary",
+    "real",
+    "references",
+    "returning",
+    "right",
+    "row",
+    "select",
+    "session_user",
+    "setof",
+    "similar",
+    "smallint",
+    "some",
+    "substring",
+    "symmetric",
+    "table",
+    "tablesample",
+    "then",
+    "time",
+    "timestamp",
+    "to",
+    "trailing",
+    "treat",
+    "trim",
+    "true",
+    "union",
+    "unique",
+    "user",
+    "using",
+    "values",
+    "varchar",
+    "variadic",
+    "verbose",
+    "when",
+    "where",
+    "window",
+    "with",
+    "xmlattributes",
+    "xmlconcat",
+    "xmlelement",
+    "xmlexists",
+    "xmlforest",
+    "xmlnamespaces",
+    "xmlparse",
+    "xmlpi",
+    "xmlroot",
+    "xmlserialize",
+    "xmltable",
+];
+
+/// the key words from the SQL Keywords page of the sqlite documentation, sorted
+const SQLITE_RESERVED: &[&str] = &[
+    "abort",
+    "action",
+    "add",
+    "after",
+    "all",
+    "alter",
+    "always",
+    "analyze",
+    "and",
+    "as",
+    "asc",
+    "attach",
+    "autoincrement",
+    "before",
+    "begin",
+    "between",
+    "by",
+    "cascade",
+    "case",
+    "cast",
+    "check",
+    "collate",
+    "column",
+    "commit",
+    "conflict",
+    "constraint",
+    "create",
+    "cross",
+    "current",
+    "current_date",
+    "current_time",
+    "current_timestamp",
+    "database",
+    "default",
+    "deferrable",
+    "deferred",
+    "delete",
+    "desc",
+    "detach",
+    "distinct",
+    "do",
+    "drop",
+    "each",
+    "else",
+    "end",
+    "escape",
+    "except",
+    "exclude",
+    "exclusive",
+    "exists",
+    "explain",
+    "fail",
+    "filter",
+    "first",
+    "following",
+    "for",
+    "foreign",
+    "from",
+    "full",
+    "generated",
+    "glob",
+    "group",
+    "groups",
+    "having",
+    "if",
+    "ignore",
+    "immediate",
+    "in",
+    "index",
+    "indexed",
+    "initially",
+    "inner",
+    "insert",
+    "instead",
+    "intersect",
+    "into",
+    "is",
+    "isnull",
+    "join",
+    "key",
+    "last",
+    "left",
+    "like",
+    "limit",
+    "match",
+    "materialized",
+    "natural",
+    "no",
+    "not",
+    "nothing",
+    "notnull",
+    "null",
+    "nulls",
+    "of",
+    "offset",
+    "on",
+    "or",
+    "order",
+    "others",
+    "outer",
+    "over",
+    "partition",
+    "plan",
+    "pragma",
+    "preceding",
+    "primary",
+    "query",
+    "raise",
+    "range",
+    "recursive",
+    "references",
+    "regexp",
+    "reindex",
+    "release",
+    "rename",
+    "replace",
+    "restrict",
+    "returning",
+    "right",
+    "rollback",
+    "row",
+    "rows",
+    "savepoint",
+    "select",
+    "set",
+    "table",
+    "temp",
+    "temporary",
+    "then",
+    "ties",
+    "to",
+    "transaction",
+    "trigger",
+    "unbounded",
+    "union",
+    "unique",
+    "update",
+    "using",
+    "vacuum",
+    "values",
+    "view",
+    "virtual",
+    "when",
+    "where",
+    "window",
+    "with",
+    "without",
+];
+
+/// the reserved words from the Keywords and Reserved Words page of the mysql 8.0
+/// documentation, sorted
+const MYSQL_RESERVED: &[&str] = &[
+    "accessible",
+    "add",
+    "all",
+    "alter",
+    "analyze",
+    "and",
+    "as",
+    "asc",
+    "asensitive",
+    "before",
+    "between",
+    "bigint",
+    "binary",
+    "blob",
+    "both",
+    "by",
+    "call",
+    "cascade",
+    "case",
+    "change",
+    "char",
+    "character",
+    "check",
+    "collate",
+    "column",
+    "condition",
+    "constraint",
+    "continue",
+    "convert",
+    "create",
+    "cross",
+    "cube",
+    "cume_dist",
+    "current_date",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "cursor",
+    "database",
+    "databases",
+    "day_hour",
+    "day_microsecond",
+    "day_minute",
+    "day_second",
+    "dec",
+    "decimal",
+    "declare",
+    "default",
+    "delayed",
+    "delete",
+    "dense_rank",
+    "desc",
+    "describe",
+    "deterministic",
+    "distinct",
+    "distinctrow",
+    "div",
+    "double",
+    "drop",
+    "dual",
+    "each",
+    "else",
+    "elseif",
+    "empty",
+    "enclosed",
+    "escaped",
+    "except",
+    "exists",
+    "exit",
+    "explain",
+    "false",
+    "fetch",
+    "first_value",
+    "float",
+    "float4",
+    "float8",
+    "for",
+    "force",
+    "foreign",
+    "from",
+    "fulltext",
+    "function",
+    "generated",
+    "get",
+    "grant",
+    "group",
+    "grouping",
+    "groups",
+    "having",
+    "high_priority",
+    "hour_microsecond",
+    "hour_minute",
+    "hour_second",
+    "if",
+    "ignore",
+    "in",
+    "index",
+    "infile",
+    "inner",
+    "inout",
+    "insensitive",
+    "insert",
+    "int",
+    "int1",
+    "int2",
+    "int3",
+    "int4",
+    "int8",
+    "integer",
+    "intersect",
+    "interval",
+    "into",
+    "io_after_gtids",
+    "io_before_gtids",
+    "is",
+    "iterate",
+    "join",
+    "json_table",
+    "key",
+    "keys",
+    "kill",
+    "lag",
+    "last_value",
+    "lateral",
+    "lead",
+    "leading",
+    "leave",
+    "left",
+    "like",
+    "limit",
+    "linear",
+    "lines",
+    "load",
+    "localtime",
+    "localtimestamp",
+    "lock",
+    "long",
+    "longblob",
+    "longtext",
+    "loop",
+    "low_priority",
+    "master_bind",
+    "master_ssl_verify_server_cert",
+    "match",
+    "maxvalue",
+    "mediumblob",
+    "mediumint",
+    "mediumtext",
+    "middleint",
+    "minute_microsecond",
+    "minute_second",
+    "mod",
+    "modifies",
+    "natural",
+    "no_write_to_binlog",
+    "not",
+    "nth_value",
+    "ntile",
+    "null",
+    "numeric",
+    "of",
+    "on",
+    "optimize",
+    "optimizer_costs",
+    "option",
+    "optionally",
+    "or",
+    "order",
+    "out",
+    "outer",
+    "outfile",
+    "over",
+    "partition",
+    "percent_rank",
+    "precision",
+    "primary",
+    "procedure",
+    "purge",
+    "range",
+    "rank",
+    "read",
+    "read_write",
+    "reads",
+    "real",
+    "recursive",
+    "references",
+    "regexp",
+    "release",
+    "rename",
+    "repeat",
+    "replace",
+    "require",
+    "resignal",
+    "restrict",
+    "return",
+    "revoke",
+    "right",
+    "rlike",
+    "row",
+    "row_number",
+    "rows",
+    "schema",
+    "schemas",
+    "second_microsecond",
+    "select",
+    "sensitive",
+    "separator",
+    "set",
+    "show",
+    "signal",
+    "smallint",
+    "spatial",
+    "specific",
+    "sql",
+    "sql_big_result",
+    "sql_calc_found_rows",
+    "sql_small_result",
+    "sqlexception",
+    "sqlstate",
+    "sqlwarning",
+    "ssl",
+    "starting",
+    "stored",
+    "straight_join",
+    "system",
+    "table",
+    "terminated",
+    "then",
+    "tinyblob",
+    "tinyint",
+    "tinytext",
+    "to",
+    "trailing",
+    "trigger",
+    "true",
+    "undo",
+    "union",
+    "unique",
+    "unlock",
+    "unsigned",
+    "update",
+    "usage",
+    "use",
+    "using",
+    "utc_date",
+    "utc_time",
+    "utc_timestamp",
+    "values",
+    "varbinary",
+    "varchar",
+    "varcharacter",
+    "varying",
+    "virtual",
+    "when",
+    "where",
+    "while",
+    "window",
+    "with",
+    "write",
+    "xor",
+    "year_month",
+    "zerofill",
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keywords_are_sorted() {
+        for keywords in &[POSTGRES_RESERVED, SQLITE_RESERVED, MYSQL_RESERVED] {
+            assert!(keywords.windows(2).all(|w| w[0] < w[1]));
+        }
+    }
+
+    #[test]
+    fn quote_per_dialect() {
+        assert_eq!(Dialect::Postgres.safe_identifier("actor"), Ok("actor".to_string()));
+        assert_eq!(Dialect::Postgres.safe_identifier("USER"), Ok("\"USER\"".to_string()));
+        assert_eq!(
+            Dialect::Sqlite.safe_identifier("my \"table\""),
+            Ok("\"my \"\"table\"\"\"".to_string())
+        );
+        assert_eq!(Dialect::Mysql.safe_identifier("key"), Ok("`key`".to_string()));
+        assert_eq!(Dialect::Mysql.safe_identifier("a`b"), Ok("`a``b`".to_string()));
+        assert_eq!(Dialect::Postgres.safe_identifier("key"), Ok("key".to_string()));
+        assert_eq!(Dialect::Postgres.safe_identifier("Actor"), Ok("\"Actor\"".to_string()));
+        assert_eq!(Dialect::Sqlite.safe_identifier("Actor"), Ok("Actor".to_string()));
+        assert!(Dialect::Postgres.quote_identifier("").is_err());
+        assert!(Dialect::Mysql.quote_identifier("a\0b").is_err());
+    }
+
+    #[test]
+    fn quote_literals() {
+        assert_eq!(
+            Dialect::Postgres.quote_literal("it's \\"),
+            Ok("'it''s \\'".to_string())
+        );
+        assert_eq!(
+            Dialect::Mysql.quote_literal("it's \\"),
+            Ok("'it''s \\\\'".to_string())
+        );
+        assert!(Dialect::Sqlite.quote_literal("\0").is_err());
+    }
+}
@@ -1,4 +1,15 @@
-use crate::common;
+use crate::{
+    common,
+    error::NameParseError,
+    parse::{
+        self,
+        QualifiedName,
+    },
+    quote::{
+        Dialect,
+        UnsafeSql,
+    },
+};
 use serde_derive::{
     Deserialize,
     Serialize,
@@ -12,55 +23,108 @@ use std::hash::{
 pub struct TableName {
     pub name: String,
     pub schema: Option<String>,
+    /// the database of the table in a `catalog.schema.table` name
+    #[serde(default)]
+    pub catalog: Option<String>,
     pub alias: Option<String>,
 }
 
 impl Eq for TableName {}
 
 impl TableName {
-    /// create table with name
+    /// create table with name, which can be qualified with the schema and the catalog.
+    /// Quoted parts may contain a `.`, ie: `public."film.2020"`.
+    /// A name which can not be split is used as is
     pub fn from(arg: &str) -> Self {
-        if arg.contains('.') {
-            let splinters = arg.split('.').collect::<Vec<&str>>();
-            assert!(splinters.len() == 2, "There should only be 2 parts");
-            let schema = splinters[0].to_owned();
-            let table = splinters[1].to_owned();
-            TableName {
-                schema: Some(schema),
-                name: table,
-                alias: None,
-            }
-        } else {
-            TableName {
-                schema: None,
-                name: arg.to_owned(),
-                alias: None,
-            }
+        parse::parse_name(arg, None)
+            .and_then(|parsed| Self::from_parsed(arg, parsed, 3))
+            .unwrap_or_else(|_| {
+                TableName {
+                    name: arg.to_owned(),
+                    schema: None,
+                    catalog: None,
+                    alias: None,
+                }
+            })
+    }
+
+    /// parse the table name as it would be written in the sql of the dialect,
+    /// ie: `"Sales".orders AS o`.
+    /// Unquoted parts are folded to lowercase in postgresql, and a `catalog.schema.table` name
+    /// is only allowed in postgresql
+    pub fn parse(arg: &str, dialect: Dialect) -> Result<Self, NameParseError> {
+        let max_parts = match dialect {
+            Dialect::Postgres => 3,
+            Dialect::Sqlite | Dialect::Mysql => 2,
+        };
+        Self::from_parsed(arg, parse::parse_name(arg, Some(dialect))?, max_parts)
+    }
+
+    fn from_parsed(
+        arg: &str,
+        parsed: QualifiedName,
+        max_parts: usize,
+    ) -> Result<Self, NameParseError> {
+        let QualifiedName { mut parts, alias } = parsed;
+        if parts.len() > max_parts {
+            return Err(NameParseError::TooManyParts(arg.to_string(), max_parts));
         }
+        let name = parts.pop().expect("must have at least 1 part");
+        let schema = parts.pop();
+        let catalog = parts.pop();
+        Ok(TableName {
+            name,
+            schema,
+            catalog,
+            alias,
+        })
     }
 
     pub fn name(&self) -> String { self.name.to_owned() }
 
     pub fn safe_name(&self) -> String { common::keywords_safe(&self.name) }
 
-    /// return the long name of the table using schema.table_name
+    /// return the long name of the table using catalog.schema.table_name
     pub fn complete_name(&self) -> String {
-        match self.schema {
-            Some(ref schema) => format!("{}.{}", schema, self.name),
-            None => self.name.to_owned(),
-        }
+        self.qualifiers()
+            .chain(Some(&self.name))
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(".")
     }
 
     pub fn safe_complete_name(&self) -> String {
-        match self.schema {
-            Some(ref schema) => format!("{}.{}", schema, self.safe_name()),
-            None => self.name.to_owned(),
-        }
+        self.qualifiers()
+            .chain(Some(&self.name))
+            .map(|part| common::keywords_safe(part))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// the name of the table quoted for the dialect when needed
+    pub fn quoted_name(&self, dialect: Dialect) -> Result<String, UnsafeSql> {
+        dialect.safe_identifier(&self.name)
+    }
+
+    /// the qualified name of the table quoted for the dialect when needed
+    pub fn quoted_complete_name(&self, dialect: Dialect) -> Result<String, UnsafeSql> {
+        let parts = self
+            .qualifiers()
+            .chain(Some(&self.name))
+            .map(|part| dialect.safe_identifier(part))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(parts.join("."))
+    }
+
+    /// the catalog and the schema of the table, when present
+    fn qualifiers(&self) -> impl Iterator<Item = &String> {
+        self.catalog.iter().chain(self.schema.iter())
     }
 }
 
 impl Hash for TableName {
     fn hash<H: Hasher>(&self, state: &mut H) {
+        self.catalog.hash(state);
         self.schema.hash(state);
         self.name.hash(state);
     }
@@ -70,3 +134,33 @@ pub trait ToTableName {
     /// extract the table name from a struct
     fn to_table_name() -> TableName;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_qualified_names() {
+        let table = TableName::parse("Sakila.public.\"Film.2020\" AS f", Dialect::Postgres)
+            .expect("must parse");
+        assert_eq!(table.catalog, Some("sakila".to_string()));
+        assert_eq!(table.schema, Some("public".to_string()));
+        assert_eq!(table.name, "Film.2020");
+        assert_eq!(table.alias, Some("f".to_string()));
+        assert_eq!(
+            table.quoted_complete_name(Dialect::Postgres),
+            Ok("sakila.public.\"Film.2020\"".to_string())
+        );
+        assert_eq!(
+            TableName::parse("a.b.c", Dialect::Mysql),
+            Err(NameParseError::TooManyParts("a.b.c".to_string(), 2))
+        );
+    }
+
+    #[test]
+    fn from_does_not_panic() {
+        assert_eq!(TableName::from("public.film").complete_name(), "public.film");
+        assert_eq!(TableName::from("\"my.db\".film").schema, Some("my.db".to_string()));
+        assert_eq!(TableName::from("a..b").name, "a..b");
+    }
+}
@@ -39,12 +39,26 @@ pub enum Value {
     Point(Point<f64>),
 
     Array(Array),
+
+    /// the fields of a composite type value, in the order they are declared in the type
+    Composite(Vec<(String, Value)>),
 }
 
 impl Value {
     pub fn is_nil(&self) -> bool {
         *self == Value::Nil
     }
+
+    /// the value of the field of a composite value
+    pub fn get_field(&self, name: &str) -> Option<&Value> {
+        match self {
+            Value::Composite(fields) => fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Value {
@@ -68,6 +82,17 @@ impl fmt::Display for Value {
             Value::DateTime(v) => write!(f, "{}", v.format("%Y-%m-%d %H:%M:%S").to_string()),
             Value::Timestamp(v) => write!(f, "{}", v.to_rfc3339()),
             Value::Array(array) => array.fmt(f),
+            Value::Composite(fields) => {
+                // the same row literal format used by postgresql, ie: (1,"a b",)
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(_, value)| match value {
+                        Value::Nil => String::new(),
+                        _ => quote_composite_field(&value.to_string()),
+                    })
+                    .collect();
+                write!(f, "({})", fields.join(","))
+            }
             Value::Blob(v) => {
                 let encoded = base64::encode_config(&v, base64::MIME);
                 write!(f, "{}", encoded)
@@ -77,6 +102,19 @@ impl fmt::Display for Value {
     }
 }
 
+/// quote the field of a row literal when it is empty or contains a delimiter
+fn quote_composite_field(field: &str) -> String {
+    let needs_quote = field.is_empty()
+        || field.chars().any(|c| {
+            c == ',' || c == '(' || c == ')' || c == '"' || c == '\\' || c.is_whitespace()
+        });
+    if needs_quote {
+        format!("\"{}\"", field.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        field.to_string()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum Array {
     /*
@@ -395,4 +433,17 @@ mod tests {
         assert!(ts.is_ok());
         DateTime::<Utc>::from_utc(ts.unwrap(), Utc);
     }
+
+    #[test]
+    fn composite_display() {
+        let address = Value::Composite(vec![
+            ("street".to_string(), Value::Text("Main st, 1".to_string())),
+            ("number".to_string(), Value::Int(12)),
+            ("unit".to_string(), Value::Nil),
+            ("note".to_string(), Value::Text("say \"hi\"".to_string())),
+        ]);
+        assert_eq!(address.to_string(), r#"("Main st, 1",12,,"say \"hi\"")"#);
+        assert_eq!(address.get_field("number"), Some(&Value::Int(12)));
+        assert_eq!(address.get_field("zip"), None);
+    }
 }
@@ -32,7 +32,7 @@ fn main() {
         )";
 
     let db_url = "sqlite:///tmp/sqlite.db";
-    let mut pool = Pool::new();
+    let pool = Pool::new();
     let mut em = pool.em(db_url).unwrap();
     let ret = em.db().execute_sql_with_return(create_sql, &[]);
     println!("ret: {:?}", ret);
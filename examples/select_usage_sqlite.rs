@@ -8,7 +8,7 @@ struct Actor {
 
 fn main() {
     let db_url = "sqlite://sakila.db";
-    let mut pool = Pool::new();
+    let pool = Pool::new();
     let mut em = pool.em(db_url).unwrap();
     let sql = "SELECT * FROM actor LIMIT 10";
     let actors: Result<Vec<Actor>, DbError> = em.execute_sql_with_return(sql, &[]);
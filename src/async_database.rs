@@ -0,0 +1,119 @@
+//! The async counterpart of `Database` and `DBPlatform`.
+//!
+//! Only the execution of sql statements and the transactions are async,
+//! the values are still converted into `Value`, `Dao` and `Rows`.
+use crate::{placeholder, DbError, Dialect, Rows, Value};
+use async_trait::async_trait;
+use cfg_if::cfg_if;
+use std::ops::{Deref, DerefMut};
+
+cfg_if! {if #[cfg(feature = "with-postgres-async")]{
+    use crate::pg::async_pg::AsyncPostgresDB;
+}}
+
+cfg_if! {if #[cfg(feature = "with-sqlite-async")]{
+    use crate::sqlite::async_sqlite::AsyncSqliteDB;
+}}
+
+cfg_if! {if #[cfg(feature = "with-mysql-async")]{
+    use crate::my::async_my::AsyncMysqlDB;
+}}
+
+#[async_trait]
+pub trait AsyncDatabase: Send {
+    async fn begin_transaction(&mut self) -> Result<(), DbError>;
+
+    async fn commit_transaction(&mut self) -> Result<(), DbError>;
+
+    async fn rollback_transaction(&mut self) -> Result<(), DbError>;
+
+    async fn execute_sql_with_return(
+        &mut self,
+        sql: &str,
+        param: &[&Value],
+    ) -> Result<Rows, DbError>;
+}
+
+pub enum AsyncDBPlatform {
+    #[cfg(feature = "with-postgres-async")]
+    Postgres(Box<AsyncPostgresDB>),
+    #[cfg(feature = "with-sqlite-async")]
+    Sqlite(Box<AsyncSqliteDB>),
+    #[cfg(feature = "with-mysql-async")]
+    Mysql(Box<AsyncMysqlDB>),
+}
+
+impl AsyncDBPlatform {
+    /// the parameter placeholder for the parameter at `index` (1-based) in the sql statement
+    pub(crate) fn placeholder(&self, index: usize) -> String {
+        placeholder::placeholder(self.dialect(), index)
+    }
+
+    /// the sql dialect used for quoting identifiers and literals
+    pub fn dialect(&self) -> Dialect {
+        match *self {
+            #[cfg(feature = "with-postgres-async")]
+            AsyncDBPlatform::Postgres(_) => Dialect::Postgres,
+            #[cfg(feature = "with-sqlite-async")]
+            AsyncDBPlatform::Sqlite(_) => Dialect::Sqlite,
+            #[cfg(feature = "with-mysql-async")]
+            AsyncDBPlatform::Mysql(_) => Dialect::Mysql,
+        }
+    }
+
+    /// the maximum number of parameters which can be bound to a single statement
+    pub fn max_params(&self) -> usize {
+        match *self {
+            #[cfg(feature = "with-postgres-async")]
+            AsyncDBPlatform::Postgres(_) => 65535,
+            #[cfg(feature = "with-sqlite-async")]
+            AsyncDBPlatform::Sqlite(ref sq) => sq.max_params(),
+            #[cfg(feature = "with-mysql-async")]
+            AsyncDBPlatform::Mysql(_) => 65535,
+        }
+    }
+
+    /// execute the sql after rewriting its `$n` or `?` placeholders to the style of this platform,
+    /// so the same sql can be used on every platform
+    pub(crate) async fn execute_portable_sql(
+        &mut self,
+        sql: &str,
+        params: &[&Value],
+    ) -> Result<Rows, DbError> {
+        match placeholder::rewrite_positional(sql, self.dialect(), params.len())? {
+            Some((sql, order)) => {
+                let params: Vec<&Value> = order.into_iter().map(|i| params[i]).collect();
+                self.execute_sql_with_return(&sql, &params).await
+            }
+            None => self.execute_sql_with_return(sql, params).await,
+        }
+    }
+}
+
+impl Deref for AsyncDBPlatform {
+    type Target = dyn AsyncDatabase;
+
+    fn deref(&self) -> &Self::Target {
+        match *self {
+            #[cfg(feature = "with-postgres-async")]
+            AsyncDBPlatform::Postgres(ref pg) => pg.deref(),
+            #[cfg(feature = "with-sqlite-async")]
+            AsyncDBPlatform::Sqlite(ref sq) => sq.deref(),
+            #[cfg(feature = "with-mysql-async")]
+            AsyncDBPlatform::Mysql(ref my) => my.deref(),
+        }
+    }
+}
+
+impl DerefMut for AsyncDBPlatform {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match *self {
+            #[cfg(feature = "with-postgres-async")]
+            AsyncDBPlatform::Postgres(ref mut pg) => pg.deref_mut(),
+            #[cfg(feature = "with-sqlite-async")]
+            AsyncDBPlatform::Sqlite(ref mut sq) => sq.deref_mut(),
+            #[cfg(feature = "with-mysql-async")]
+            AsyncDBPlatform::Mysql(ref mut my) => my.deref_mut(),
+        }
+    }
+}
@@ -0,0 +1,384 @@
+#[cfg(feature = "with-mysql-async")]
+use crate::my::async_my::AsyncMysqlDB;
+#[cfg(feature = "with-sqlite-async")]
+use crate::sqlite;
+use crate::{
+    entity, placeholder, AsyncDBPlatform, AsyncDatabase, DataError, DbError, ToValue, Value,
+};
+#[cfg(feature = "with-mysql-async")]
+use rustorm_dao::ColumnName;
+use rustorm_dao::{FromDao, TableName, ToColumnNames, ToDao, ToTableName};
+
+/// The async counterpart of `EntityManager`, the records are converted the same way
+/// using `FromDao` and `ToDao`.
+///
+/// The table definitions are not introspected, use the `EntityManager` of the synchronous api
+/// for that.
+/// The last field is whether a transaction was started with `begin_transaction` and is still open
+pub struct AsyncEntityManager(pub AsyncDBPlatform, bool);
+
+impl AsyncEntityManager {
+    pub fn new(db: AsyncDBPlatform) -> Self {
+        AsyncEntityManager(db, false)
+    }
+
+    pub async fn begin_transaction(&mut self) -> Result<(), DbError> {
+        self.0.begin_transaction().await?;
+        self.1 = true;
+        Ok(())
+    }
+
+    pub async fn commit_transaction(&mut self) -> Result<(), DbError> {
+        self.1 = false;
+        self.0.commit_transaction().await
+    }
+
+    pub async fn rollback_transaction(&mut self) -> Result<(), DbError> {
+        self.1 = false;
+        self.0.rollback_transaction().await
+    }
+
+    pub fn db(&mut self) -> &mut dyn AsyncDatabase {
+        &mut *self.0
+    }
+
+    /// start a transaction, or a savepoint when a transaction was started with
+    /// `begin_transaction`, so several statements are written as one. Returns whether it is a
+    /// savepoint
+    async fn begin_atomic(&mut self, savepoint: &str) -> Result<bool, DbError> {
+        if self.1 {
+            let sql = format!("SAVEPOINT {}", savepoint);
+            self.0.execute_sql_with_return(&sql, &[]).await?;
+            Ok(true)
+        } else {
+            self.begin_transaction().await?;
+            Ok(false)
+        }
+    }
+
+    /// commit the transaction or release the savepoint started with `begin_atomic`
+    async fn commit_atomic(&mut self, savepoint: &str, nested: bool) -> Result<(), DbError> {
+        if nested {
+            let sql = format!("RELEASE SAVEPOINT {}", savepoint);
+            self.0.execute_sql_with_return(&sql, &[]).await?;
+            Ok(())
+        } else {
+            self.commit_transaction().await
+        }
+    }
+
+    /// roll back the transaction or the savepoint started with `begin_atomic`
+    async fn rollback_atomic(&mut self, savepoint: &str, nested: bool) -> Result<(), DbError> {
+        if nested {
+            let sql = format!("ROLLBACK TO SAVEPOINT {}", savepoint);
+            self.0.execute_sql_with_return(&sql, &[]).await?;
+            Ok(())
+        } else {
+            self.rollback_transaction().await
+        }
+    }
+
+    /// get all the records of this table
+    pub async fn get_all<T>(&mut self) -> Result<Vec<T>, DbError>
+    where
+        T: ToTableName + ToColumnNames + FromDao,
+    {
+        let sql = entity::select_all_sql::<T>(self.0.dialect())?;
+        let rows = self.0.execute_sql_with_return(&sql, &[]).await?;
+        Ok(rows.iter().map(|dao| T::from_dao(&dao)).collect())
+    }
+
+    /// Get the total count of records
+    pub async fn get_total_records(&mut self, table_name: &TableName) -> Result<usize, DbError> {
+        #[derive(crate::FromDao)]
+        struct Count {
+            count: i64,
+        }
+        let sql = entity::total_records_sql(table_name, self.0.dialect())?;
+        let count: Count = self.execute_sql_with_one_return(&sql, &[]).await?;
+        Ok(count.count as usize)
+    }
+
+    /// insert the entities and return the inserted records, see `insert_with_progress`
+    pub async fn insert<T, R>(&mut self, entities: &[&T]) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+    {
+        self.insert_with_progress(entities, |_, _| ()).await
+    }
+
+    /// insert the entities in chunks which keep the number of parameters of each statement under
+    /// the limit of the platform, all the chunks are inserted in one transaction.
+    /// `progress` is called after each chunk with the number of entities inserted so far and the
+    /// total number of entities.
+    ///
+    /// Inside a transaction started with `begin_transaction` the chunks are inserted in a
+    /// savepoint instead, which is rolled back on error and the transaction is left open
+    pub async fn insert_with_progress<T, R, F>(
+        &mut self,
+        entities: &[&T],
+        mut progress: F,
+    ) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+        F: FnMut(usize, usize),
+    {
+        let chunk_size = entity::insert_chunk_size(T::to_column_names().len(), self.0.max_params());
+        if entities.len() <= chunk_size {
+            let retrieved_entities = self.insert_chunk(entities).await?;
+            progress(entities.len(), entities.len());
+            return Ok(retrieved_entities);
+        }
+        let nested = self.begin_atomic(entity::INSERT_SAVEPOINT).await?;
+        let mut retrieved_entities = Vec::with_capacity(entities.len());
+        let mut inserted = 0;
+        for chunk in entities.chunks(chunk_size) {
+            match self.insert_chunk(chunk).await {
+                Ok(retrieved) => retrieved_entities.extend(retrieved),
+                Err(e) => {
+                    let _ = self.rollback_atomic(entity::INSERT_SAVEPOINT, nested).await;
+                    return Err(e);
+                }
+            }
+            inserted += chunk.len();
+            progress(inserted, entities.len());
+        }
+        self.commit_atomic(entity::INSERT_SAVEPOINT, nested).await?;
+        Ok(retrieved_entities)
+    }
+
+    /// insert the entities which fit in a single statement
+    async fn insert_chunk<T, R>(&mut self, entities: &[&T]) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+    {
+        match self.0 {
+            #[cfg(feature = "with-sqlite-async")]
+            AsyncDBPlatform::Sqlite(_) if sqlite::supports_returning() => {
+                self.insert_bulk_with_returning_support(entities).await
+            }
+            #[cfg(feature = "with-sqlite-async")]
+            AsyncDBPlatform::Sqlite(_) => self.insert_simple(entities).await,
+            #[cfg(feature = "with-postgres-async")]
+            AsyncDBPlatform::Postgres(_) => self.insert_bulk_with_returning_support(entities).await,
+            #[cfg(feature = "with-mysql-async")]
+            AsyncDBPlatform::Mysql(_) => self.insert_mysql(entities).await,
+        }
+    }
+
+    /// the mysql database, only called when the platform is mysql
+    #[cfg(feature = "with-mysql-async")]
+    #[allow(unreachable_patterns)]
+    fn mysql_db(&mut self) -> &mut AsyncMysqlDB {
+        match self.0 {
+            AsyncDBPlatform::Mysql(ref mut db) => db,
+            _ => unreachable!("the platform is mysql"),
+        }
+    }
+
+    /// the async counterpart of `EntityManager::insert_mysql`, the generated keys are derived
+    /// from `LAST_INSERT_ID` and the records are selected back by their primary key.
+    /// MariaDB 10.5 and later use `INSERT ... RETURNING` instead
+    #[cfg(feature = "with-mysql-async")]
+    async fn insert_mysql<T, R>(&mut self, entities: &[&T]) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+    {
+        if self.mysql_db().server_supports_returning().await? {
+            return self.insert_bulk_with_returning_support(entities).await;
+        }
+        let table_name = T::to_table_name();
+        let primary = self.mysql_db().primary_key_columns(&table_name).await?;
+        let (supplied_keys, generated) =
+            entity::mysql_supplied_keys(&table_name, &primary, entities)?;
+        let increment = if generated > 0 {
+            self.mysql_db().auto_increment_increment().await?
+        } else {
+            1
+        };
+        // the generated keys of a statement are only known to be consecutive when all of its rows
+        // are generated, so the rows are inserted one at a time when the keys are mixed
+        if generated == 0 || generated == entities.len() {
+            let chunk_size = entities.len().max(1);
+            return self
+                .insert_mysql_rows(entities, &primary, &supplied_keys, chunk_size, increment)
+                .await;
+        }
+        // the rows inserted one at a time are all inserted or none like a single statement
+        let nested = self.begin_atomic(entity::MYSQL_ROWS_SAVEPOINT).await?;
+        match self
+            .insert_mysql_rows(entities, &primary, &supplied_keys, 1, increment)
+            .await
+        {
+            Ok(retrieved_entities) => {
+                self.commit_atomic(entity::MYSQL_ROWS_SAVEPOINT, nested)
+                    .await?;
+                Ok(retrieved_entities)
+            }
+            Err(e) => {
+                let _ = self
+                    .rollback_atomic(entity::MYSQL_ROWS_SAVEPOINT, nested)
+                    .await;
+                Err(e)
+            }
+        }
+    }
+
+    /// insert the entities into mysql with statements of `chunk_size` rows, then select them back
+    /// by the supplied keys and the keys generated from `LAST_INSERT_ID`
+    #[cfg(feature = "with-mysql-async")]
+    async fn insert_mysql_rows<T, R>(
+        &mut self,
+        entities: &[&T],
+        primary: &[ColumnName],
+        supplied_keys: &[Option<Vec<Value>>],
+        chunk_size: usize,
+        increment: u64,
+    ) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+    {
+        let table_name = T::to_table_name();
+        let dialect = self.0.dialect();
+        let mut keys: Vec<Vec<Value>> = Vec::with_capacity(entities.len());
+        for (chunk, chunk_keys) in entities
+            .chunks(chunk_size)
+            .zip(supplied_keys.chunks(chunk_size))
+        {
+            let sql = entity::insert_clause(chunk, dialect)?;
+            let values = entity::insert_values(chunk);
+            let bvalues: Vec<&Value> = values.iter().collect();
+            let (first_id, inserted) = self.mysql_db().execute_insert(&sql, &bvalues).await?;
+            entity::push_mysql_keys(&mut keys, chunk_keys, first_id, inserted, increment);
+        }
+        entity::check_record_count(&table_name, entities.len(), keys.len())?;
+        let mut retrieved_entities = Vec::with_capacity(keys.len());
+        let chunk_size = entity::insert_chunk_size(primary.len(), self.0.max_params());
+        for chunk in keys.chunks(chunk_size) {
+            let sql = entity::select_by_keys_sql::<R>(&table_name, primary, chunk.len(), dialect)?;
+            let values: Vec<&Value> = chunk.iter().flatten().collect();
+            let rows = self.0.execute_sql_with_return(&sql, &values).await?;
+            retrieved_entities.extend(entity::in_key_order(&rows, primary, chunk));
+        }
+        entity::check_record_count(&table_name, keys.len(), retrieved_entities.len())?;
+        Ok(retrieved_entities)
+    }
+
+    /// called when the platform used is postgresql, sqlite 3.35.0 and later or MariaDB 10.5 and
+    /// later
+    pub async fn insert_bulk_with_returning_support<T, R>(
+        &mut self,
+        entities: &[&T],
+    ) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+    {
+        let dialect = self.0.dialect();
+        let mut sql = entity::insert_clause(entities, dialect)?;
+        sql += &entity::returning_clause(&R::to_column_names(), dialect)?;
+        let values = entity::insert_values(entities);
+        let bvalues: Vec<&Value> = values.iter().collect();
+        let rows = self.0.execute_sql_with_return(&sql, &bvalues).await?;
+        Ok(rows.iter().map(|dao| R::from_dao(&dao)).collect())
+    }
+
+    /// called multiple times when using database platform that doesn;t support multiple value
+    /// insert such as sqlite
+    pub async fn single_insert<T>(&mut self, entity: &T) -> Result<(), DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+    {
+        let sql = entity::insert_clause(&[entity], self.0.dialect())?;
+        let values = entity::insert_values(&[entity]);
+        let bvalues: Vec<&Value> = values.iter().collect();
+        self.0.execute_sql_with_return(&sql, &bvalues).await?;
+        Ok(())
+    }
+
+    /// insert the entities one at a time and retrieve each of them with `LAST_INSERT_ROWID`, used
+    /// with the sqlite versions older than 3.35.0 which don't support `RETURNING`
+    pub async fn insert_simple<T, R>(&mut self, entities: &[&T]) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+    {
+        let last_insert_sql = entity::last_insert_sql::<T, R>(self.0.dialect())?;
+        let mut retrieved_entities = vec![];
+        for entity in entities {
+            self.single_insert(*entity).await?;
+            let retrieved = self.execute_sql_with_return(&last_insert_sql, &[]).await?;
+            retrieved_entities.extend(retrieved);
+        }
+        Ok(retrieved_entities)
+    }
+
+    /// execute the sql, the `$n` or `?` placeholders are rewritten to the style of the platform
+    pub async fn execute_sql_with_return<'a, R>(
+        &mut self,
+        sql: &str,
+        params: &[&'a (dyn ToValue + Sync)],
+    ) -> Result<Vec<R>, DbError>
+    where
+        R: FromDao,
+    {
+        let values: Vec<Value> = params.iter().map(|p| p.to_value()).collect();
+        let bvalues: Vec<&Value> = values.iter().collect();
+        let rows = self.0.execute_portable_sql(sql, &bvalues).await?;
+        Ok(rows.iter().map(|dao| R::from_dao(&dao)).collect::<Vec<R>>())
+    }
+
+    /// execute the sql with named parameters, ie: `:actor_id`, which are bound to the values of
+    /// the dao, map or struct with the same name
+    pub async fn execute_sql_with_named_params<R, P>(
+        &mut self,
+        sql: &str,
+        params: &P,
+    ) -> Result<Vec<R>, DbError>
+    where
+        R: FromDao,
+        P: ToDao,
+    {
+        let (sql, values) = placeholder::bind_named(sql, self.0.dialect(), &params.to_dao())?;
+        let bvalues: Vec<&Value> = values.iter().collect();
+        let rows = self.0.execute_sql_with_return(&sql, &bvalues).await?;
+        Ok(rows.iter().map(|dao| R::from_dao(&dao)).collect::<Vec<R>>())
+    }
+
+    pub async fn execute_sql_with_one_return<'a, R>(
+        &mut self,
+        sql: &str,
+        params: &[&'a (dyn ToValue + Sync)],
+    ) -> Result<R, DbError>
+    where
+        R: FromDao,
+    {
+        let mut result: Vec<R> = self.execute_sql_with_return(sql, params).await?;
+        match result.len() {
+            0 => Err(DbError::DataError(DataError::ZeroRecordReturned)),
+            1 => Ok(result.remove(0)),
+            _ => Err(DbError::DataError(DataError::MoreThan1RecordReturned)),
+        }
+    }
+
+    pub async fn execute_sql_with_maybe_one_return<'a, R>(
+        &mut self,
+        sql: &str,
+        params: &[&'a (dyn ToValue + Sync)],
+    ) -> Result<Option<R>, DbError>
+    where
+        R: FromDao,
+    {
+        let mut result: Vec<R> = self.execute_sql_with_return(sql, params).await?;
+        match result.len() {
+            0 => Ok(None),
+            1 => Ok(Some(result.remove(0))),
+            _ => Err(DbError::DataError(DataError::MoreThan1RecordReturned)),
+        }
+    }
+}
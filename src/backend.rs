@@ -0,0 +1,137 @@
+//! The registry of the database backends keyed by the scheme of their db_url.
+//!
+//! The built-in postgresql, sqlite, mysql and duckdb backends are registered when their feature is
+//! enabled. Other crates can add their own `Database` implementation with `register_backend`,
+//! after which `Pool::em` and `Pool::dm` accept the db_url of that scheme.
+//!
+//! ```rust,ignore
+//! struct MyBackend;
+//!
+//! impl Backend for MyBackend {
+//!     fn init_pool(&self, db_url: &str) -> Result<Arc<dyn BackendPool>, DbError> {
+//!         Ok(Arc::new(MyPool::new(db_url)?))
+//!     }
+//! }
+//!
+//! impl BackendPool for MyPool {
+//!     fn connect(&self) -> Result<DBPlatform, DbError> {
+//!         Ok(DBPlatform::Other(Box::new(self.get_connection()?)))
+//!     }
+//! }
+//!
+//! register_backend("mydb", MyBackend);
+//! let mut em = Pool::new().em("mydb://localhost/sakila")?;
+//! ```
+use crate::{error::ConnectError, DBPlatform, DbError};
+use lazy_static::lazy_static;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+/// A database backend which creates the connection pools of its db_urls
+pub trait Backend: Send + Sync {
+    /// create the connection pool for this db_url
+    fn init_pool(&self, db_url: &str) -> Result<Arc<dyn BackendPool>, DbError>;
+
+    /// check that a connection can be made to this db_url, defaults to creating a pool
+    /// and getting a connection from it
+    fn test_connection(&self, db_url: &str) -> Result<(), DbError> {
+        self.init_pool(db_url)?.connect().map(|_| ())
+    }
+}
+
+/// The connection pool of a db_url, shared by the clones of the `Pool`
+pub trait BackendPool: Send + Sync {
+    /// get a database instance with a connection from this pool
+    fn connect(&self) -> Result<DBPlatform, DbError>;
+}
+
+lazy_static! {
+    static ref BACKENDS: RwLock<BTreeMap<String, Arc<dyn Backend>>> =
+        RwLock::new(builtin_backends());
+}
+
+fn builtin_backends() -> BTreeMap<String, Arc<dyn Backend>> {
+    #[allow(unused_mut)]
+    let mut backends: BTreeMap<String, Arc<dyn Backend>> = BTreeMap::new();
+    #[cfg(feature = "with-postgres")]
+    backends.insert("postgres".to_string(), Arc::new(crate::pg::PostgresBackend));
+    #[cfg(feature = "with-sqlite")]
+    backends.insert("sqlite".to_string(), Arc::new(crate::sqlite::SqliteBackend));
+    #[cfg(feature = "with-mysql")]
+    backends.insert("mysql".to_string(), Arc::new(crate::my::MysqlBackend));
+    #[cfg(feature = "with-duckdb")]
+    backends.insert("duckdb".to_string(), Arc::new(crate::duck::DuckdbBackend));
+    backends
+}
+
+/// register the backend used for the db_urls of this scheme, ie: `mydb` of `mydb://localhost/db`
+///
+/// Note: this replaces the backend already registered to this scheme, including the built-in ones.
+/// The pools already created by a `Pool` are kept.
+pub fn register_backend<B>(scheme: &str, backend: B)
+where
+    B: Backend + 'static,
+{
+    BACKENDS
+        .write()
+        .expect("backends lock is poisoned")
+        .insert(scheme.to_string(), Arc::new(backend));
+}
+
+/// the backend registered to the scheme of this db_url
+pub(crate) fn get_backend(db_url: &str) -> Result<Arc<dyn Backend>, DbError> {
+    let url = url::Url::parse(db_url).map_err(|e| ConnectError::ParseError(e.into()))?;
+    let scheme = url.scheme();
+    BACKENDS
+        .read()
+        .expect("backends lock is poisoned")
+        .get(scheme)
+        .cloned()
+        .ok_or_else(|| DbError::ConnectError(ConnectError::UnsupportedDb(scheme.to_string())))
+}
+
+#[cfg(test)]
+#[cfg(feature = "with-sqlite")]
+mod test {
+    use super::*;
+    use crate::{
+        sqlite::{self, SqliteDB},
+        Dialect, Pool, TableName,
+    };
+    use r2d2_sqlite::SqliteConnectionManager;
+
+    /// a third party backend, using the sqlite database as a `DBPlatform::Other`
+    struct ForwardBackend;
+
+    struct ForwardPool(r2d2::Pool<SqliteConnectionManager>);
+
+    impl Backend for ForwardBackend {
+        fn init_pool(&self, db_url: &str) -> Result<Arc<dyn BackendPool>, DbError> {
+            let db_file = db_url.trim_start_matches("forward://");
+            Ok(Arc::new(ForwardPool(sqlite::init_pool(db_file)?)))
+        }
+    }
+
+    impl BackendPool for ForwardPool {
+        fn connect(&self) -> Result<DBPlatform, DbError> {
+            let conn = self.0.get().map_err(ConnectError::R2d2Error)?;
+            Ok(DBPlatform::Other(Box::new(SqliteDB(conn))))
+        }
+    }
+
+    #[test]
+    fn registered_scheme() {
+        let db_url = "forward://sakila.db";
+        let pool = Pool::new();
+        assert!(pool.em(db_url).is_err());
+        register_backend("forward", ForwardBackend);
+        let mut em = pool.em(db_url).expect("must be ok");
+        assert_eq!(em.0.dialect(), Dialect::Sqlite);
+        let count = em
+            .get_total_records(&TableName::from("actor"))
+            .expect("must count");
+        assert!(count > 0);
+    }
+}
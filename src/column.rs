@@ -1,4 +1,4 @@
-use crate::{types::SqlType, ColumnName, FromDao, TableName};
+use crate::{types::SqlType, ColumnName, TableName};
 use uuid::Uuid;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -133,12 +133,44 @@ pub enum Literal {
     ArrayString(Vec<String>),
 }
 
-/// column stat, derive from pg_stats
-#[derive(Debug, PartialEq, FromDao, Clone)]
+/// column stat, derive from pg_stats, sqlite_stat1 and sqlite_stat4 or mysql column_statistics
+#[derive(Debug, PartialEq, Clone)]
 pub struct ColumnStat {
-    pub avg_width: i32, /* average width of the column, (the number of characters) */
-    //most_common_values: Value,//top 5 most common values
-    pub n_distinct: f32, // the number of distinct values of these column
+    pub avg_width: Option<i32>, /* average width of the column, (the number of characters) */
+    /// the number of distinct values of these column,
+    /// a negative value is the number of distinct values divided by the number of rows,
+    /// ie: -1 means all the values are unique
+    pub n_distinct: f32,
+    /// the fraction of the records where this column is null
+    pub null_fraction: Option<f32>,
+    /// the most common values, the most common first
+    pub most_common_values: Vec<MostCommonValue>,
+    /// the bounds which divides the values into groups of about the same number of records
+    pub histogram_bounds: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MostCommonValue {
+    pub value: String,
+    /// the fraction of the records with this value
+    pub frequency: f32,
+}
+
+impl ColumnStat {
+    /// the estimated number of distinct values when the table has this number of rows
+    pub fn distinct_count(&self, row_count: f64) -> f64 {
+        if self.n_distinct < 0.0 {
+            -self.n_distinct as f64 * row_count
+        } else {
+            self.n_distinct as f64
+        }
+    }
+
+    /// the column only has a few distinct values regardless of the number of rows,
+    /// such as a status or category column which can be displayed as a dropdown list
+    pub fn is_low_cardinality(&self, max_distinct: f32) -> bool {
+        self.n_distinct > 0.0 && self.n_distinct <= max_distinct
+    }
 }
 
 impl From<i64> for Literal {
@@ -1,10 +1,19 @@
-use crate::{column::Capacity, types::SqlType, Value};
+use crate::{column::Capacity, types::SqlType, ColumnName, DbError, Dialect, Value};
 use bigdecimal::BigDecimal;
 use chrono::NaiveDateTime;
 use log::*;
 use num_traits::ToPrimitive;
 use std::str::FromStr;
 
+/// the comma separated names of the columns, quoted for the dialect when needed
+pub fn quoted_column_names(columns: &[ColumnName], dialect: Dialect) -> Result<String, DbError> {
+    let names = columns
+        .iter()
+        .map(|column| column.quoted_name(dialect))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(names.join(", "))
+}
+
 pub fn extract_datatype_with_capacity(data_type: &str) -> (String, Option<Capacity>) {
     let start = data_type.find('(');
     let end = data_type.find(')');
@@ -61,6 +70,8 @@ pub fn extract_datatype_with_capacity(data_type: &str) -> (String, Option<Capaci
 pub fn cast_type(value: &Value, required_type: &SqlType) -> Value {
     if *value == Value::Nil || required_type.same_type(value) {
         value.to_owned()
+    } else if let SqlType::Domain(_, ref base) = *required_type {
+        cast_type(value, base)
     } else {
         match *value {
             Value::Smallint(v) => match *required_type {
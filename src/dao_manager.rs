@@ -1,10 +1,24 @@
-use crate::{DBPlatform, Dao, DataError, DbError, Rows, Value};
+use crate::{
+    common, placeholder, table::TableDef, ColumnDef, ColumnName, DBPlatform, Dao, DataError,
+    Database, DbError, Rows, SchemaCache, TableName, Value,
+};
+use rustorm_dao::ToDao;
 
 /// an interface executing sql statement and getting the results as generic DAO values
 /// without any further conversion.
-pub struct DaoManager(pub DBPlatform);
+/// The schema cache is shared with the pool this dao manager is created from, if enabled
+pub struct DaoManager(pub(crate) DBPlatform, pub(crate) Option<SchemaCache>);
 
 impl DaoManager {
+    /// a dao manager of the database connection, without a schema cache
+    pub fn new(db: DBPlatform) -> Self {
+        DaoManager(db, None)
+    }
+
+    pub fn db(&mut self) -> &mut dyn Database {
+        &mut *self.0
+    }
+
     pub fn begin_transaction(&mut self) -> Result<(), DbError> {
         self.0.begin_transaction()
     }
@@ -17,21 +31,40 @@ impl DaoManager {
         self.0.rollback_transaction()
     }
 
+    /// execute the sql, the `$n` or `?` placeholders are rewritten to the style of the platform
     pub fn execute_sql_with_return(
         &mut self,
         sql: &str,
         params: &[&Value],
     ) -> Result<Rows, DbError> {
-        let rows = self.0.execute_sql_with_return(sql, params)?;
+        let rows = self.0.execute_portable_sql(sql, params)?;
+        if let Some(ref cache) = self.1 {
+            cache.invalidate_if_ddl(sql);
+        }
         Ok(rows)
     }
 
+    /// execute the sql with named parameters, ie: `:actor_id`, which are bound to the values of
+    /// the dao, map or struct with the same name
+    pub fn execute_sql_with_named_params<P>(
+        &mut self,
+        sql: &str,
+        params: &P,
+    ) -> Result<Rows, DbError>
+    where
+        P: ToDao,
+    {
+        let (sql, values) = placeholder::bind_named(sql, self.0.dialect(), &params.to_dao())?;
+        let bvalues: Vec<&Value> = values.iter().collect();
+        self.execute_sql_with_return(&sql, &bvalues)
+    }
+
     pub fn execute_sql_with_records_return(
         &mut self,
         sql: &str,
         params: &[&Value],
     ) -> Result<Vec<Dao>, DbError> {
-        let rows = self.0.execute_sql_with_return(sql, params)?;
+        let rows = self.execute_sql_with_return(sql, params)?;
         let daos: Vec<Dao> = rows.iter().collect();
         Ok(daos)
     }
@@ -67,4 +100,322 @@ impl DaoManager {
             Err(e) => Err(e),
         }
     }
+
+    /// insert the records into the table, returning the stored records
+    /// which includes the values generated by the database such as the autoincrement primary key
+    /// and the column defaults.
+    /// Values in the dao which are not a column of the table are ignored.
+    pub fn insert_dao(
+        &mut self,
+        table_name: &TableName,
+        daos: &[Dao],
+    ) -> Result<Vec<Dao>, DbError> {
+        let table = self.get_existing_table(table_name)?;
+        let dialect = self.0.dialect();
+        let table_name = table.quoted_complete_name(dialect)?;
+        let mut inserted = vec![];
+        for dao in daos {
+            let (columns, values) = column_values(&table, dao, |_| true);
+            let sql = if columns.is_empty() {
+                format!("INSERT INTO {} DEFAULT VALUES", table_name)
+            } else {
+                let column_names: Vec<ColumnName> =
+                    columns.iter().map(|column| column.name.clone()).collect();
+                format!(
+                    "INSERT INTO {} ({}) VALUES ({})",
+                    table_name,
+                    common::quoted_column_names(&column_names, dialect)?,
+                    (1..=values.len())
+                        .map(|i| self.0.placeholder(i))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            let bvalues: Vec<&Value> = values.iter().collect();
+            if self.0.supports_returning() {
+                let sql = format!("{} RETURNING *", sql);
+                let record = self.execute_sql_with_one_return(&sql, &bvalues)?;
+                inserted.push(record);
+            } else {
+                self.0.execute_sql_with_return(&sql, &bvalues)?;
+                let record = self.get_last_inserted(&table, dao)?;
+                inserted.push(record.unwrap_or_else(|| to_dao(&columns, values)));
+            }
+        }
+        Ok(inserted)
+    }
+
+    /// update the records matching the primary key values in the dao,
+    /// setting the rest of the column values in the dao.
+    /// Returns the updated records, records which no longer exist are not included.
+    pub fn update_dao(
+        &mut self,
+        table_name: &TableName,
+        daos: &[Dao],
+    ) -> Result<Vec<Dao>, DbError> {
+        let table = self.get_existing_table(table_name)?;
+        let mut updated = vec![];
+        for dao in daos {
+            let (columns, mut values) =
+                column_values(&table, dao, |column| !table.is_primary_column(column));
+            if columns.is_empty() {
+                if let Some(record) = self.fetch_by_primary_key(&table, dao)? {
+                    updated.push(record);
+                }
+                continue;
+            }
+            let set_clause = columns
+                .iter()
+                .enumerate()
+                .map(|(i, column)| {
+                    Ok(format!(
+                        "{} = {}",
+                        column.name.quoted_name(self.0.dialect())?,
+                        self.0.placeholder(i + 1)
+                    ))
+                })
+                .collect::<Result<Vec<_>, DbError>>()?
+                .join(", ");
+            let (where_clause, pk_values) = self.primary_key_filter(&table, dao, values.len())?;
+            values.extend(pk_values);
+            let sql = format!(
+                "UPDATE {} SET {} WHERE {}",
+                table.quoted_complete_name(self.0.dialect())?,
+                set_clause,
+                where_clause
+            );
+            let bvalues: Vec<&Value> = values.iter().collect();
+            if self.0.supports_returning() {
+                let sql = format!("{} RETURNING *", sql);
+                let record = self.execute_sql_with_maybe_one_return(&sql, &bvalues)?;
+                updated.extend(record);
+            } else {
+                self.0.execute_sql_with_return(&sql, &bvalues)?;
+                updated.extend(self.fetch_by_primary_key(&table, dao)?);
+            }
+        }
+        Ok(updated)
+    }
+
+    /// delete the records matching the primary key values in the dao,
+    /// returning the records that were deleted.
+    pub fn delete_dao(
+        &mut self,
+        table_name: &TableName,
+        daos: &[Dao],
+    ) -> Result<Vec<Dao>, DbError> {
+        let table = self.get_existing_table(table_name)?;
+        let mut deleted = vec![];
+        for dao in daos {
+            let (where_clause, values) = self.primary_key_filter(&table, dao, 0)?;
+            let sql = format!(
+                "DELETE FROM {} WHERE {}",
+                table.quoted_complete_name(self.0.dialect())?,
+                where_clause
+            );
+            let bvalues: Vec<&Value> = values.iter().collect();
+            if self.0.supports_returning() {
+                let sql = format!("{} RETURNING *", sql);
+                let record = self.execute_sql_with_maybe_one_return(&sql, &bvalues)?;
+                deleted.extend(record);
+            } else {
+                let record = self.fetch_by_primary_key(&table, dao)?;
+                self.0.execute_sql_with_return(&sql, &bvalues)?;
+                deleted.extend(record);
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// get the record of the table which matches the primary key values in the dao
+    pub fn get_by_primary_key(
+        &mut self,
+        table_name: &TableName,
+        dao: &Dao,
+    ) -> Result<Option<Dao>, DbError> {
+        let table = self.get_existing_table(table_name)?;
+        self.fetch_by_primary_key(&table, dao)
+    }
+
+    fn fetch_by_primary_key(
+        &mut self,
+        table: &TableDef,
+        dao: &Dao,
+    ) -> Result<Option<Dao>, DbError> {
+        let (where_clause, values) = self.primary_key_filter(table, dao, 0)?;
+        let sql = format!(
+            "SELECT * FROM {} WHERE {}",
+            table.quoted_complete_name(self.0.dialect())?,
+            where_clause
+        );
+        let bvalues: Vec<&Value> = values.iter().collect();
+        self.execute_sql_with_maybe_one_return(&sql, &bvalues)
+    }
+
+    fn get_existing_table(&mut self, table_name: &TableName) -> Result<TableDef, DbError> {
+        let table = match self.1 {
+            Some(ref cache) => cache.get_table(&mut *self.0, table_name)?,
+            None => self.0.get_table(table_name)?,
+        };
+        match table {
+            Some(table) => Ok(table),
+            None => Err(DbError::DataError(DataError::TableNameNotFound(
+                table_name.complete_name(),
+            ))),
+        }
+    }
+
+    /// retrieve the record that was just inserted, for platforms that don't support `RETURNING`.
+    /// The record is looked up using the primary key values when they are supplied, otherwise
+    /// using the rowid of the last inserted record.
+    fn get_last_inserted(&mut self, table: &TableDef, dao: &Dao) -> Result<Option<Dao>, DbError> {
+        let primary = table.get_primary_column_names();
+        let has_primary_values = !primary.is_empty()
+            && primary.iter().all(|pk| match dao.get_value(&pk.name) {
+                Some(Value::Nil) | None => false,
+                Some(_) => true,
+            });
+        if has_primary_values {
+            return self.fetch_by_primary_key(table, dao);
+        }
+        #[allow(unreachable_patterns)]
+        let sql: Option<String> = match self.0 {
+            #[cfg(feature = "with-sqlite")]
+            DBPlatform::Sqlite(_) => Some(format!(
+                "SELECT * FROM {} WHERE ROWID = LAST_INSERT_ROWID()",
+                table.quoted_complete_name(self.0.dialect())?
+            )),
+            #[cfg(feature = "with-mysql")]
+            DBPlatform::Mysql(_) if primary.len() == 1 => Some(format!(
+                "SELECT * FROM {} WHERE {} = LAST_INSERT_ID()",
+                table.quoted_complete_name(self.0.dialect())?,
+                primary[0].quoted_name(self.0.dialect())?
+            )),
+            _ => None,
+        };
+        match sql {
+            Some(sql) => self.execute_sql_with_maybe_one_return(&sql, &[]),
+            None => Ok(None),
+        }
+    }
+
+    /// build the where clause matching the primary key of the table,
+    /// the placeholders starts after `offset` parameters
+    fn primary_key_filter(
+        &self,
+        table: &TableDef,
+        dao: &Dao,
+        offset: usize,
+    ) -> Result<(String, Vec<Value>), DbError> {
+        let primary = table.get_primary_columns();
+        if primary.is_empty() {
+            return Err(DbError::DataError(DataError::NoPrimaryKey(
+                table.complete_name(),
+            )));
+        }
+        let mut filters = vec![];
+        let mut values = vec![];
+        for (i, column) in primary.iter().enumerate() {
+            match dao.get_value(&column.name.name) {
+                Some(value) if *value != Value::Nil => {
+                    filters.push(format!(
+                        "{} = {}",
+                        column.name.quoted_name(self.0.dialect())?,
+                        self.0.placeholder(offset + i + 1)
+                    ));
+                    values.push(common::cast_type(value, &column.specification.sql_type));
+                }
+                _ => {
+                    return Err(DbError::DataError(DataError::MissingPrimaryKeyValue(
+                        table.complete_name(),
+                        column.name.name.to_owned(),
+                    )));
+                }
+            }
+        }
+        Ok((filters.join(" AND "), values))
+    }
+}
+
+/// the columns of the table which has a value in the dao and passed the filter,
+/// together with the values casted to the column type
+fn column_values<'t, F>(
+    table: &'t TableDef,
+    dao: &Dao,
+    filter: F,
+) -> (Vec<&'t ColumnDef>, Vec<Value>)
+where
+    F: Fn(&ColumnDef) -> bool,
+{
+    table
+        .columns
+        .iter()
+        .filter(|column| filter(column))
+        .filter_map(|column| {
+            dao.get_value(&column.name.name).map(|value| {
+                (
+                    column,
+                    common::cast_type(value, &column.specification.sql_type),
+                )
+            })
+        })
+        .unzip()
+}
+
+fn to_dao(columns: &[&ColumnDef], values: Vec<Value>) -> Dao {
+    let mut dao = Dao::new();
+    for (column, value) in columns.iter().zip(values) {
+        dao.insert_value(&column.name.name, &value);
+    }
+    dao
+}
+
+#[cfg(test)]
+#[cfg(feature = "with-sqlite")]
+mod tests_sq {
+    use crate::*;
+
+    #[test]
+    fn insert_update_delete_actor_dao() {
+        let db_url = "sqlite://sakila.db";
+        let pool = Pool::new();
+        let mut dm = pool.dm(db_url).unwrap();
+        let actor = TableName::from("actor");
+        dm.begin_transaction().unwrap();
+
+        let mut dao = Dao::new();
+        dao.insert("first_name", "HURRY");
+        dao.insert("last_name", "POTTER");
+        dao.insert("not_a_column", "ignored");
+        let inserted = dm.insert_dao(&actor, &[dao]).expect("must insert");
+        assert_eq!(inserted.len(), 1);
+        let mut inserted = inserted[0].clone();
+        assert!(inserted.get_value("actor_id").is_some());
+        assert!(inserted.get_value("not_a_column").is_none());
+        assert_eq!(inserted.get::<String>("last_name").unwrap(), "POTTER");
+
+        inserted.insert("last_name", "OTTER");
+        let updated = dm
+            .update_dao(&actor, &[inserted.clone()])
+            .expect("must update");
+        assert_eq!(updated[0].get::<String>("last_name").unwrap(), "OTTER");
+
+        let deleted = dm.delete_dao(&actor, &[inserted.clone()]).unwrap();
+        assert_eq!(deleted.len(), 1);
+        let retrieved = dm.get_by_primary_key(&actor, &inserted).unwrap();
+        assert!(retrieved.is_none());
+
+        dm.rollback_transaction().unwrap();
+    }
+
+    #[test]
+    fn update_without_primary_key_value() {
+        let db_url = "sqlite://sakila.db";
+        let pool = Pool::new();
+        let mut dm = pool.dm(db_url).unwrap();
+        let mut dao = Dao::new();
+        dao.insert("last_name", "OTTER");
+        let result = dm.update_dao(&TableName::from("actor"), &[dao]);
+        assert!(result.is_err());
+    }
 }
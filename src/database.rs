@@ -1,6 +1,12 @@
 #[cfg(feature = "db-auth")]
 use crate::db_auth::{Role, User};
-use crate::{table::SchemaContent, DbError, Rows, TableDef, TableName, Value};
+use crate::{
+    routine::{Routine, RoutineResult},
+    table::{SchemaContent, TableStat},
+    trigger::Trigger,
+    view::ViewDef,
+    DbError, Dialect, Rows, TableDef, TableName, Value,
+};
 use rustorm_codegen::FromDao;
 use serde::Serialize;
 
@@ -12,6 +18,22 @@ pub struct DatabaseName {
 }
 
 pub trait Database {
+    /// the sql dialect used for quoting the identifiers and literals and the style of the
+    /// placeholders of the sql sent to this database
+    fn dialect(&self) -> Dialect;
+
+    /// the maximum number of parameters which can be bound to a single statement, the bulk
+    /// inserts are split into several statements to stay under it
+    fn max_params(&self) -> usize {
+        65535
+    }
+
+    /// the inserted, updated or deleted records can be returned by the same statement with
+    /// `RETURNING`, otherwise they are selected back after the statement
+    fn supports_returning(&self) -> bool {
+        false
+    }
+
     fn begin_transaction(&mut self) -> Result<(), DbError>;
 
     fn commit_transaction(&mut self) -> Result<(), DbError>;
@@ -41,6 +63,42 @@ pub trait Database {
 
     fn get_database_name(&mut self) -> Result<Option<DatabaseName>, DbError>;
 
+    /// get the estimated size of these tables from the statistics of the database,
+    /// tables which are not found are skipped
+    fn get_table_stats(&mut self, table_names: &[TableName]) -> Result<Vec<TableStat>, DbError>;
+
+    /// get the definition of the view or materialized view, None if there is no such view
+    fn get_view_definition(&mut self, view_name: &TableName) -> Result<Option<ViewDef>, DbError>;
+
+    /// get the triggers defined on this table or view
+    fn get_triggers(&mut self, table_name: &TableName) -> Result<Vec<Trigger>, DbError>;
+
+    /// a value which changes whenever a table, column or constraint is created, altered or dropped,
+    /// used to detect the changes made to the schema outside of rustorm
+    fn get_schema_version(&mut self) -> Result<Option<String>, DbError>;
+
+    /// recompute the content of the materialized view
+    fn refresh_materialized_view(
+        &mut self,
+        view_name: &TableName,
+        concurrently: bool,
+    ) -> Result<(), DbError>;
+
+    /// get the stored functions and procedures of the database
+    fn get_routines(&mut self) -> Result<Vec<Routine>, DbError>;
+
+    /// get the stored functions and procedures with this name,
+    /// postgresql functions can be overloaded with different arguments
+    fn get_routine(&mut self, routine_name: &TableName) -> Result<Vec<Routine>, DbError>;
+
+    /// call the stored function or procedure,
+    /// the values are bound to its IN and INOUT arguments in the order they are declared
+    fn call_routine(
+        &mut self,
+        routine: &Routine,
+        args: &[&Value],
+    ) -> Result<RoutineResult, DbError>;
+
     #[cfg(feature = "db-auth")]
     fn get_users(&mut self) -> Result<Vec<User>, DbError>;
 
@@ -0,0 +1,1329 @@
+//! The embedded DuckDB backend, ie: `duckdb://analytics.duckdb`.
+//!
+//! The table definitions are read from `information_schema` and the `duckdb_constraints()`,
+//! `duckdb_indexes()` table functions. Records can be bulk loaded with `DuckdbDB::append`
+//! which uses the appender of duckdb instead of an INSERT statement.
+#[cfg(feature = "db-auth")]
+use crate::db_auth::{Role, User};
+use crate::{
+    backend::{Backend, BackendPool},
+    column::{Capacity, ColumnConstraint, ColumnSpecification, Literal},
+    common,
+    error::{ConnectError, ConvertError},
+    platform,
+    routine::{Routine, RoutineResult},
+    table::{Check, ForeignKey, Index, IndexColumn, Key, MatchType, ReferentialAction, TableKey},
+    table::{SchemaContent, TableStat},
+    trigger::Trigger,
+    types::SqlType,
+    util::{self, extract_index_expressions, extract_view_select},
+    view::ViewDef,
+    ColumnDef, ColumnName, DBPlatform, Database, DatabaseName, DbError, Dialect, Rows, TableDef,
+    TableName, ToValue, Value,
+};
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use duckdb::{
+    params_from_iter,
+    types::{TimeUnit, Value as DkValue},
+    DuckdbConnectionManager,
+};
+use log::*;
+use rustorm_dao::Interval;
+use std::{collections::HashMap, convert::TryFrom, str::FromStr, sync::Arc};
+use thiserror::Error;
+
+/// open the database file, an empty path opens an in-memory database which is shared by all
+/// the connections of the pool
+pub fn init_pool(db_file: &str) -> Result<r2d2::Pool<DuckdbConnectionManager>, DuckdbError> {
+    info!("initializing pool: {}", db_file);
+    let manager = if db_file.is_empty() {
+        DuckdbConnectionManager::memory()?
+    } else {
+        DuckdbConnectionManager::file(db_file)?
+    };
+    let pool = r2d2::Pool::new(manager)?;
+    Ok(pool)
+}
+
+/// the built-in duckdb backend, the db_url is the path of the database file,
+/// ie: `duckdb://analytics.duckdb`, or `duckdb://` for an in-memory database
+pub struct DuckdbBackend;
+
+impl Backend for DuckdbBackend {
+    fn init_pool(&self, db_url: &str) -> Result<Arc<dyn BackendPool>, DbError> {
+        let url = url::Url::parse(db_url).map_err(|e| ConnectError::ParseError(e.into()))?;
+        Ok(Arc::new(init_pool(&platform::database_file(&url))?))
+    }
+}
+
+impl BackendPool for r2d2::Pool<DuckdbConnectionManager> {
+    fn connect(&self) -> Result<DBPlatform, DbError> {
+        let conn = self.get().map_err(ConnectError::R2d2Error)?;
+        Ok(DBPlatform::Duckdb(Box::new(DuckdbDB(conn))))
+    }
+}
+
+pub struct DuckdbDB(pub r2d2::PooledConnection<DuckdbConnectionManager>);
+
+impl DuckdbDB {
+    /// append the rows to the table using the appender of duckdb, which is a lot faster than
+    /// inserting them with INSERT statements.
+    ///
+    /// Note: the appender fills all the columns of the table, the values of each row has to be
+    /// in the same order as the columns of the table
+    pub fn append(
+        &mut self,
+        table_name: &TableName,
+        rows: &[Vec<Value>],
+    ) -> Result<usize, DbError> {
+        info!(
+            "appending {} rows to: {}",
+            rows.len(),
+            table_name.complete_name()
+        );
+        let mut appender = match table_name.schema {
+            Some(ref schema) => self.0.appender_to_db(&table_name.name, schema),
+            None => self.0.appender(&table_name.name),
+        }
+        .map_err(DuckdbError::from)?;
+        for row in rows {
+            let values = row
+                .iter()
+                .map(to_dk_value)
+                .collect::<Result<Vec<_>, DbError>>()?;
+            appender
+                .append_row(params_from_iter(values))
+                .map_err(DuckdbError::from)?;
+        }
+        appender.flush().map_err(DuckdbError::from)?;
+        Ok(rows.len())
+    }
+}
+
+/// the values which duckdb can not store, such as the points, arrays and composites, are a
+/// `ConvertError`
+fn to_dk_value(val: &Value) -> Result<DkValue, DbError> {
+    let value = match *val {
+        Value::Nil => DkValue::Null,
+        Value::Bool(v) => DkValue::Boolean(v),
+        Value::Tinyint(v) => DkValue::TinyInt(v),
+        Value::Smallint(v) => DkValue::SmallInt(v),
+        Value::Int(v) => DkValue::Int(v),
+        Value::Bigint(v) => DkValue::BigInt(v),
+        Value::Float(v) => DkValue::Float(v),
+        Value::Double(v) => DkValue::Double(v),
+        // duckdb casts the text to the decimal type of the column, without losing precision
+        Value::BigDecimal(ref v) => DkValue::Text(v.to_string()),
+        Value::Blob(ref v) => DkValue::Blob(v.clone()),
+        Value::Char(v) => DkValue::Text(v.to_string()),
+        Value::Text(ref v) => DkValue::Text(v.clone()),
+        Value::Json(ref v) => DkValue::Text(v.clone()),
+        Value::Uuid(ref v) => DkValue::Text(v.to_string()),
+        Value::Date(ref v) => {
+            let days = v.signed_duration_since(unix_epoch().date()).num_days();
+            DkValue::Date32(days as i32)
+        }
+        Value::Time(ref v) => {
+            let micros = i64::from(v.num_seconds_from_midnight()) * 1_000_000
+                + i64::from(v.nanosecond() / 1_000);
+            DkValue::Time64(TimeUnit::Microsecond, micros)
+        }
+        Value::DateTime(ref v) => DkValue::Timestamp(TimeUnit::Microsecond, timestamp_micros(v)),
+        Value::Timestamp(ref v) => {
+            DkValue::Timestamp(TimeUnit::Microsecond, timestamp_micros(&v.naive_utc()))
+        }
+        Value::Interval(ref v) => DkValue::Interval {
+            months: v.months,
+            days: v.days,
+            nanos: v.microseconds * 1_000,
+        },
+        _ => {
+            return Err(DbError::ConvertError(ConvertError::UnsupportedDataType(
+                format!("{:?}", val),
+            )))
+        }
+    };
+    Ok(value)
+}
+
+fn from_dk_value(value: DkValue) -> Result<Value, DbError> {
+    let value = match value {
+        DkValue::Null => Value::Nil,
+        DkValue::Boolean(v) => Value::Bool(v),
+        DkValue::TinyInt(v) => Value::Tinyint(v),
+        DkValue::SmallInt(v) => Value::Smallint(v),
+        DkValue::Int(v) => Value::Int(v),
+        DkValue::BigInt(v) => Value::Bigint(v),
+        DkValue::UTinyInt(v) => Value::Smallint(i16::from(v)),
+        DkValue::USmallInt(v) => Value::Int(i32::from(v)),
+        DkValue::UInt(v) => Value::Bigint(i64::from(v)),
+        // the values which doesn't fit in an i64
+        DkValue::UBigInt(v) => Value::BigDecimal(parse_decimal(&v.to_string())?),
+        DkValue::HugeInt(v) => Value::BigDecimal(parse_decimal(&v.to_string())?),
+        DkValue::Decimal(v) => Value::BigDecimal(parse_decimal(&v.to_string())?),
+        DkValue::Float(v) => Value::Float(v),
+        DkValue::Double(v) => Value::Double(v),
+        DkValue::Text(v) => Value::Text(v),
+        DkValue::Enum(v) => Value::Text(v),
+        DkValue::Blob(v) => Value::Blob(v),
+        // the infinite dates and timestamps and `24:00:00` are out of range of chrono
+        DkValue::Date32(days) => Value::Date(
+            unix_epoch()
+                .date()
+                .checked_add_signed(chrono::Duration::days(i64::from(days)))
+                .ok_or_else(|| out_of_range(format!("DATE {} days", days)))?,
+        ),
+        DkValue::Time64(unit, v) => {
+            let micros = unit.to_micros(v);
+            let time = if micros >= 0 {
+                u32::try_from(micros / 1_000_000).ok().and_then(|secs| {
+                    NaiveTime::from_num_seconds_from_midnight_opt(
+                        secs,
+                        (micros % 1_000_000 * 1_000) as u32,
+                    )
+                })
+            } else {
+                None
+            };
+            Value::Time(time.ok_or_else(|| out_of_range(format!("TIME {} micros", micros)))?)
+        }
+        DkValue::Timestamp(unit, v) => {
+            let micros = unit.to_micros(v);
+            Value::DateTime(
+                NaiveDateTime::from_timestamp_opt(
+                    micros.div_euclid(1_000_000),
+                    (micros.rem_euclid(1_000_000) * 1_000) as u32,
+                )
+                .ok_or_else(|| out_of_range(format!("TIMESTAMP {} micros", micros)))?,
+            )
+        }
+        DkValue::Interval {
+            months,
+            days,
+            nanos,
+        } => Value::Interval(Interval::new(nanos / 1_000, days, months)),
+        // the nested types: LIST, ARRAY, STRUCT, MAP and UNION
+        v => {
+            return Err(DbError::ConvertError(ConvertError::UnsupportedDataType(
+                format!("{:?}", v),
+            )))
+        }
+    };
+    Ok(value)
+}
+
+fn out_of_range(v: String) -> DbError {
+    DbError::ConvertError(ConvertError::UnsupportedDataType(v))
+}
+
+fn parse_decimal(v: &str) -> Result<BigDecimal, DbError> {
+    BigDecimal::from_str(v)
+        .map_err(|_| DbError::ConvertError(ConvertError::UnsupportedDataType(v.to_string())))
+}
+
+fn unix_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd(1970, 1, 1).and_hms(0, 0, 0)
+}
+
+fn timestamp_micros(v: &NaiveDateTime) -> i64 {
+    v.timestamp() * 1_000_000 + i64::from(v.timestamp_subsec_micros())
+}
+
+impl Database for DuckdbDB {
+    /// duckdb quotes the identifiers with `"` and accepts the `$1` placeholders same as postgresql
+    fn dialect(&self) -> Dialect {
+        Dialect::Postgres
+    }
+
+    fn supports_returning(&self) -> bool {
+        true
+    }
+
+    fn begin_transaction(&mut self) -> Result<(), DbError> {
+        self.execute_sql_with_return("BEGIN TRANSACTION", &[])?;
+        Ok(())
+    }
+
+    fn commit_transaction(&mut self) -> Result<(), DbError> {
+        self.execute_sql_with_return("COMMIT", &[])?;
+        Ok(())
+    }
+
+    fn rollback_transaction(&mut self) -> Result<(), DbError> {
+        self.execute_sql_with_return("ROLLBACK", &[])?;
+        Ok(())
+    }
+
+    fn execute_sql_with_return(&mut self, sql: &str, params: &[&Value]) -> Result<Rows, DbError> {
+        info!("executing sql: {}", sql);
+        info!("params: {:?}", params);
+        let values = params
+            .iter()
+            .map(|v| to_dk_value(v))
+            .collect::<Result<Vec<_>, DbError>>()?;
+        let mut stmt = self.0.prepare(sql).map_err(DuckdbError::from)?;
+        let mut records = vec![];
+        {
+            let mut rows = stmt
+                .query(params_from_iter(values))
+                .map_err(DuckdbError::from)?;
+            while let Some(row) = rows.next().map_err(DuckdbError::from)? {
+                let column_count = row.as_ref().column_count();
+                let mut record = Vec::with_capacity(column_count);
+                for i in 0..column_count {
+                    let raw: DkValue = row.get(i).map_err(DuckdbError::from)?;
+                    record.push(from_dk_value(raw)?);
+                }
+                records.push(record);
+            }
+        }
+        // the columns are only known once the statement is executed
+        let mut rows = Rows::new(stmt.column_names());
+        for record in records {
+            rows.push(record);
+        }
+        Ok(rows)
+    }
+
+    fn get_table(&mut self, table_name: &TableName) -> Result<Option<TableDef>, DbError> {
+        let tables = get_scoped_tables(&mut *self, &Scope::Table(table_name))?;
+        Ok(tables.into_iter().next())
+    }
+
+    /// duckdb sequences can not be altered
+    fn set_autoincrement_value(
+        &mut self,
+        _table_name: &TableName,
+        _sequence_value: i64,
+    ) -> Result<Option<i64>, DbError> {
+        Err(DbError::UnsupportedOperation(
+            "duckdb doesn't support changing the value of a sequence".to_string(),
+        ))
+    }
+
+    /// the last value of the sequence used in the default of the autoincrement column
+    fn get_autoincrement_last_value(
+        &mut self,
+        table_name: &TableName,
+    ) -> Result<Option<i64>, DbError> {
+        let table = match self.get_table(table_name)? {
+            Some(table) => table,
+            None => return Ok(None),
+        };
+        let sequence = table
+            .columns
+            .iter()
+            .flat_map(|column| column.specification.constraints.iter())
+            .find_map(|constraint| match constraint {
+                ColumnConstraint::AutoIncrement(Some(sequence)) => Some(sequence.to_string()),
+                _ => None,
+            });
+        let sequence = match sequence {
+            Some(sequence) => sequence,
+            None => return Ok(None),
+        };
+        let sql = "SELECT last_value FROM duckdb_sequences() \
+                   WHERE database_name = current_database() AND sequence_name = $1";
+        let rows = self.execute_sql_with_return(sql, &[&sequence.to_value()])?;
+        Ok(rows
+            .iter()
+            .next()
+            .and_then(|row| row.get_opt("last_value").expect("last_value")))
+    }
+
+    fn get_all_tables(&mut self) -> Result<Vec<TableDef>, DbError> {
+        get_scoped_tables(&mut *self, &Scope::AllTables)
+    }
+
+    fn get_tablenames(&mut self) -> Result<Vec<TableName>, DbError> {
+        Ok(get_table_names(&mut *self, &Scope::AllTables)?
+            .into_iter()
+            .map(|(table_name, _)| table_name)
+            .collect())
+    }
+
+    fn get_grouped_tables(&mut self) -> Result<Vec<SchemaContent>, DbError> {
+        let mut contents: Vec<SchemaContent> = vec![];
+        for (table_name, is_view) in get_table_names(&mut *self, &Scope::AllTables)? {
+            let schema = table_name.schema.clone().unwrap_or_default();
+            let position = match contents.iter().position(|c| c.schema == schema) {
+                Some(position) => position,
+                None => {
+                    contents.push(SchemaContent {
+                        schema,
+                        tablenames: vec![],
+                        views: vec![],
+                    });
+                    contents.len() - 1
+                }
+            };
+            if is_view {
+                contents[position].views.push(table_name);
+            } else {
+                contents[position].tablenames.push(table_name);
+            }
+        }
+        Ok(contents)
+    }
+
+    fn get_database_name(&mut self) -> Result<Option<DatabaseName>, DbError> {
+        let rows = self.execute_sql_with_return("SELECT current_database() AS name", &[])?;
+        Ok(rows.iter().next().map(|row| DatabaseName {
+            name: row.get("name").expect("name"),
+            description: None,
+        }))
+    }
+
+    /// the row count is the estimate kept by duckdb, the sizes of each table are not available
+    fn get_table_stats(&mut self, table_names: &[TableName]) -> Result<Vec<TableStat>, DbError> {
+        let sql = "SELECT schema_name AS schema, table_name AS name, estimated_size \
+                   FROM duckdb_tables() WHERE database_name = current_database()";
+        let rows = self.execute_sql_with_return(sql, &[])?;
+        let estimates: Vec<(String, String, Option<i64>)> = rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get("schema").expect("schema"),
+                    row.get("name").expect("name"),
+                    row.get_opt("estimated_size").expect("estimated_size"),
+                )
+            })
+            .collect();
+        Ok(table_names
+            .iter()
+            .filter_map(|table_name| {
+                let schema = table_name.schema.as_deref().unwrap_or(DEFAULT_SCHEMA);
+                estimates
+                    .iter()
+                    .find(|(s, name, _)| s == schema && *name == table_name.name)
+                    .map(|(_, _, row_count)| TableStat {
+                        table: table_name.clone(),
+                        row_count: *row_count,
+                        is_exact: false,
+                        table_size: None,
+                        index_size: None,
+                        last_vacuum: None,
+                        last_analyze: None,
+                    })
+            })
+            .collect())
+    }
+
+    /// duckdb doesn't keep track of the view dependencies,
+    /// the tables and views which names appear in the definition are listed instead
+    fn get_view_definition(&mut self, view_name: &TableName) -> Result<Option<ViewDef>, DbError> {
+        let scope = Scope::Table(view_name);
+        let sql = format!(
+            "SELECT sql FROM duckdb_views() \
+             WHERE database_name = current_database() AND NOT internal AND {}",
+            scope.condition("schema_name", "view_name")
+        );
+        let params = scope.params();
+        let params: Vec<&Value> = params.iter().collect();
+        let rows = self.execute_sql_with_return(&sql, &params)?;
+        let create_sql: String = match rows.iter().next() {
+            Some(row) => row.get("sql").expect("sql"),
+            None => return Ok(None),
+        };
+        let definition = extract_view_select(&create_sql)
+            .trim_end_matches(';')
+            .to_string();
+        let words = util::identifiers(&definition);
+        let dependencies = self
+            .get_tablenames()?
+            .into_iter()
+            .filter(|table| table.name != view_name.name)
+            .filter(|table| words.iter().any(|w| w.eq_ignore_ascii_case(&table.name)))
+            .collect();
+        Ok(Some(ViewDef {
+            name: view_name.clone(),
+            definition,
+            is_updatable: false,
+            is_materialized: false,
+            dependencies,
+        }))
+    }
+
+    /// duckdb doesn't have triggers
+    fn get_triggers(&mut self, _table_name: &TableName) -> Result<Vec<Trigger>, DbError> {
+        Ok(vec![])
+    }
+
+    /// duckdb doesn't have a schema version, a hash of the create sql of the tables, views and
+    /// indexes is used instead
+    fn get_schema_version(&mut self) -> Result<Option<String>, DbError> {
+        let sql = "SELECT CAST(hash(string_agg(sql, ';' ORDER BY sql)) AS VARCHAR) \
+                   AS schema_version FROM ( \
+                   SELECT sql FROM duckdb_tables() WHERE database_name = current_database() \
+                   UNION ALL SELECT sql FROM duckdb_views() \
+                   WHERE database_name = current_database() AND NOT internal \
+                   UNION ALL SELECT sql FROM duckdb_indexes() \
+                   WHERE database_name = current_database())";
+        let rows = self.execute_sql_with_return(sql, &[])?;
+        Ok(rows
+            .iter()
+            .next()
+            .and_then(|row| row.get_opt("schema_version").expect("schema_version")))
+    }
+
+    fn refresh_materialized_view(
+        &mut self,
+        _view_name: &TableName,
+        _concurrently: bool,
+    ) -> Result<(), DbError> {
+        Err(DbError::UnsupportedOperation(
+            "duckdb doesn't have materialized views".to_string(),
+        ))
+    }
+
+    /// the macros of duckdb are not listed as routines
+    fn get_routines(&mut self) -> Result<Vec<Routine>, DbError> {
+        Ok(vec![])
+    }
+
+    fn get_routine(&mut self, _routine_name: &TableName) -> Result<Vec<Routine>, DbError> {
+        Ok(vec![])
+    }
+
+    fn call_routine(
+        &mut self,
+        _routine: &Routine,
+        _args: &[&Value],
+    ) -> Result<RoutineResult, DbError> {
+        Err(DbError::UnsupportedOperation(
+            "duckdb doesn't have stored functions and procedures".to_string(),
+        ))
+    }
+
+    #[cfg(feature = "db-auth")]
+    fn get_users(&mut self) -> Result<Vec<User>, DbError> {
+        Err(DbError::UnsupportedOperation(
+            "duckdb doesn't have users".to_string(),
+        ))
+    }
+
+    #[cfg(feature = "db-auth")]
+    fn get_user_detail(&mut self, _username: &str) -> Result<Vec<User>, DbError> {
+        Err(DbError::UnsupportedOperation(
+            "duckdb doesn't have users".to_string(),
+        ))
+    }
+
+    #[cfg(feature = "db-auth")]
+    fn get_roles(&mut self, _username: &str) -> Result<Vec<Role>, DbError> {
+        Err(DbError::UnsupportedOperation(
+            "duckdb doesn't have roles".to_string(),
+        ))
+    }
+}
+
+/// the schema of the tables which are created without specifying a schema
+const DEFAULT_SCHEMA: &str = "main";
+
+/// The tables the catalog queries are restricted to,
+/// the queries are always restricted to the current database
+enum Scope<'a> {
+    /// all the tables and views that are not in the system schemas
+    AllTables,
+    Table(&'a TableName),
+}
+
+impl<'a> Scope<'a> {
+    /// the condition on the columns holding the schema and the table name in the catalog query
+    fn condition(&self, schema_column: &str, name_column: &str) -> String {
+        match self {
+            Scope::AllTables => format!(
+                "{} NOT IN ('information_schema', 'pg_catalog')",
+                schema_column
+            ),
+            Scope::Table(_) => format!("{} = $1 AND {} = $2", schema_column, name_column),
+        }
+    }
+
+    fn params(&self) -> Vec<Value> {
+        match self {
+            Scope::AllTables => vec![],
+            Scope::Table(table_name) => {
+                let schema = table_name.schema.as_deref().unwrap_or(DEFAULT_SCHEMA);
+                vec![schema.to_value(), table_name.name.to_value()]
+            }
+        }
+    }
+}
+
+/// the tables and views in the scope, and whether it is a view
+fn get_table_names(
+    db: &mut dyn Database,
+    scope: &Scope,
+) -> Result<Vec<(TableName, bool)>, DbError> {
+    let sql = format!(
+        "SELECT table_schema AS schema, table_name AS name, table_type = 'VIEW' AS is_view \
+         FROM information_schema.tables \
+         WHERE table_catalog = current_database() AND {} \
+         ORDER BY table_schema, table_name",
+        scope.condition("table_schema", "table_name")
+    );
+    let params = scope.params();
+    let params: Vec<&Value> = params.iter().collect();
+    let rows = db.execute_sql_with_return(&sql, &params)?;
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let table_name = TableName {
+                name: row.get("name").expect("name"),
+                schema: Some(row.get("schema").expect("schema")),
+                catalog: None,
+                alias: None,
+            };
+            (table_name, row.get("is_view").expect("is_view"))
+        })
+        .collect())
+}
+
+/// Load the tables in the scope with one query each for the tables, columns, constraints and
+/// indexes, keyed by the schema and the table name
+fn get_scoped_tables(db: &mut dyn Database, scope: &Scope) -> Result<Vec<TableDef>, DbError> {
+    let table_names = get_table_names(db, scope)?;
+    let mut columns = get_scoped_columns(db, scope)?;
+    let (mut keys, mut key_indexes) = get_scoped_table_keys(db, scope)?;
+    let mut checks = get_scoped_checks(db, scope)?;
+    let mut indexes = get_scoped_indexes(db, scope)?;
+
+    Ok(table_names
+        .into_iter()
+        .map(|(table_name, is_view)| {
+            let key = (
+                table_name.schema.clone().unwrap_or_default(),
+                table_name.name.clone(),
+            );
+            let columns = columns.remove(&key).unwrap_or_default();
+            let mut table_key = keys.remove(&key).unwrap_or_default();
+            for mut check in checks.remove(&key).unwrap_or_default() {
+                let used = util::identifiers(&check.expression);
+                check.columns = columns
+                    .iter()
+                    .filter(|column| {
+                        used.iter()
+                            .any(|w| w.eq_ignore_ascii_case(&column.name.name))
+                    })
+                    .map(|column| column.name.clone())
+                    .collect();
+                table_key.push(TableKey::Check(check));
+            }
+            let mut table_indexes = key_indexes.remove(&key).unwrap_or_default();
+            table_indexes.extend(indexes.remove(&key).unwrap_or_default());
+            TableDef {
+                name: table_name,
+                comment: None,
+                columns,
+                is_view,
+                table_key,
+                indexes: table_indexes,
+            }
+        })
+        .collect())
+}
+
+/// get the columns of the tables in the scope, grouped by schema and table name
+fn get_scoped_columns(
+    db: &mut dyn Database,
+    scope: &Scope,
+) -> Result<HashMap<(String, String), Vec<ColumnDef>>, DbError> {
+    let sql = format!(
+        "SELECT table_schema AS schema, table_name, column_name, data_type, column_default, \
+         is_nullable = 'YES' AS is_nullable \
+         FROM information_schema.columns \
+         WHERE table_catalog = current_database() AND {} \
+         ORDER BY table_schema, table_name, ordinal_position",
+        scope.condition("table_schema", "table_name")
+    );
+    let params = scope.params();
+    let params: Vec<&Value> = params.iter().collect();
+    let mut columns: HashMap<(String, String), Vec<ColumnDef>> = HashMap::new();
+    for row in db.execute_sql_with_return(&sql, &params)?.iter() {
+        let schema: String = row.get("schema").expect("schema");
+        let table_name: String = row.get("table_name").expect("table_name");
+        let column_name: String = row.get("column_name").expect("column_name");
+        let data_type: String = row.get("data_type").expect("data_type");
+        let default: Option<String> = row.get_opt("column_default").expect("column_default");
+        let is_nullable: bool = row.get("is_nullable").expect("is_nullable");
+
+        let (sql_type, capacity) = to_sql_type_capacity(&data_type);
+        let mut constraints = vec![];
+        if !is_nullable {
+            constraints.push(ColumnConstraint::NotNull);
+        }
+        if let Some(constraint) = default.and_then(|default| to_default(&default, &sql_type)) {
+            constraints.push(constraint);
+        }
+        let table = TableName {
+            name: table_name.clone(),
+            schema: Some(schema.clone()),
+            catalog: None,
+            alias: None,
+        };
+        columns
+            .entry((schema, table_name))
+            .or_default()
+            .push(ColumnDef {
+                table,
+                name: ColumnName::from(&column_name),
+                comment: None,
+                specification: ColumnSpecification {
+                    sql_type,
+                    capacity,
+                    constraints,
+                },
+                stat: None,
+            });
+    }
+    Ok(columns)
+}
+
+/// the sql type of the `data_type` of `information_schema.columns`,
+/// ie: `INTEGER`, `DECIMAL(18,3)`, `VARCHAR[]`
+fn to_sql_type_capacity(data_type: &str) -> (SqlType, Option<Capacity>) {
+    if data_type.ends_with("[]") {
+        let (element, _) = to_sql_type_capacity(data_type.trim_end_matches("[]"));
+        return (SqlType::Array(Box::new(element)), None);
+    }
+    // the choices are extracted from the original data type to keep their case
+    if data_type
+        .get(..5)
+        .map_or(false, |prefix| prefix.eq_ignore_ascii_case("enum("))
+    {
+        return (SqlType::Enum(String::new(), enum_choices(data_type)), None);
+    }
+    let data_type = data_type.to_lowercase();
+    let (dtype, capacity) = common::extract_datatype_with_capacity(&data_type);
+    let sql_type = match dtype.trim() {
+        "boolean" => SqlType::Bool,
+        "tinyint" => SqlType::Tinyint,
+        "smallint" | "utinyint" => SqlType::Smallint,
+        "integer" | "usmallint" => SqlType::Int,
+        "bigint" | "uinteger" => SqlType::Bigint,
+        "ubigint" | "hugeint" | "uhugeint" | "decimal" => SqlType::Numeric,
+        "float" | "real" => SqlType::Float,
+        "double" => SqlType::Double,
+        "varchar" => SqlType::Text,
+        "blob" => SqlType::Blob,
+        "json" => SqlType::Json,
+        "uuid" => SqlType::Uuid,
+        "date" => SqlType::Date,
+        "time" => SqlType::Time,
+        "time with time zone" => SqlType::TimeTz,
+        "timestamp" | "timestamp_s" | "timestamp_ms" | "timestamp_ns" => SqlType::Timestamp,
+        "timestamp with time zone" => SqlType::TimestampTz,
+        "interval" => SqlType::Interval,
+        _ => {
+            // the nested types: STRUCT, MAP and UNION are not supported by `Value`
+            info!("not yet handled: {:?}", dtype);
+            SqlType::Text
+        }
+    };
+    (sql_type, capacity)
+}
+
+/// the choices of an enum data type, ie: `enum('g', 'pg')`,
+/// the quotes inside a choice are escaped by doubling them, ie: `enum('it''s', 'a, b')`
+fn enum_choices(data_type: &str) -> Vec<String> {
+    let mut choices = vec![];
+    let mut choice: Option<String> = None;
+    let mut chars = data_type.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, choice.as_mut()) {
+            ('\'', None) => choice = Some(String::new()),
+            ('\'', Some(text)) if chars.peek() == Some(&'\'') => {
+                text.push('\'');
+                chars.next();
+            }
+            ('\'', Some(_)) => choices.extend(choice.take()),
+            (c, Some(text)) => text.push(c),
+            (_, None) => (),
+        }
+    }
+    choices
+}
+
+/// the column constraint of the `column_default` expression,
+/// the expressions which are not a literal are skipped
+fn to_default(default: &str, sql_type: &SqlType) -> Option<ColumnConstraint> {
+    let ic_default = default.to_lowercase();
+    if ic_default.starts_with("nextval(") {
+        let sequence = default
+            .split('\'')
+            .nth(1)
+            .map(ToString::to_string)
+            .filter(|sequence| !sequence.is_empty());
+        return Some(ColumnConstraint::AutoIncrement(sequence));
+    }
+    let literal = match &*ic_default {
+        "null" => Literal::Null,
+        "now()" | "current_timestamp" | "get_current_timestamp()" => Literal::CurrentTimestamp,
+        "current_date" | "today()" => Literal::CurrentDate,
+        "current_time" | "get_current_time()" => Literal::CurrentTime,
+        "gen_random_uuid()" | "uuid()" => Literal::UuidGenerateV4,
+        _ => {
+            // literals of other types are shown as a cast, ie: CAST('t' AS BOOLEAN)
+            let value = if ic_default.starts_with("cast(") {
+                &default["cast(".len()..default.rfind(" AS ")?]
+            } else {
+                default
+            };
+            if value.starts_with('\'') && value.ends_with('\'') && value.len() > 1 {
+                let text = value[1..value.len() - 1].replace("''", "'");
+                match sql_type {
+                    SqlType::Bool => Literal::Bool(text == "t" || text == "true"),
+                    _ => Literal::String(text),
+                }
+            } else if sql_type == &SqlType::Bool {
+                Literal::Bool(value.eq_ignore_ascii_case("true"))
+            } else if let Ok(v) = value.parse::<i64>() {
+                Literal::Integer(v)
+            } else if let Ok(v) = value.parse::<f64>() {
+                Literal::Double(v)
+            } else {
+                return None;
+            }
+        }
+    };
+    Some(ColumnConstraint::DefaultValue(literal))
+}
+
+/// get the Primary keys, Unique keys and Foreign keys of the tables in the scope from
+/// `duckdb_constraints()`, and the indexes backing the primary and unique keys,
+/// grouped by schema and table name
+#[allow(clippy::type_complexity)]
+fn get_scoped_table_keys(
+    db: &mut dyn Database,
+    scope: &Scope,
+) -> Result<
+    (
+        HashMap<(String, String), Vec<TableKey>>,
+        HashMap<(String, String), Vec<Index>>,
+    ),
+    DbError,
+> {
+    // the column lists are unnested side by side,
+    // so the columns of a foreign key line up with the referred columns
+    let sql = format!(
+        "SELECT schema_name AS schema, table_name, constraint_index, constraint_name, \
+         constraint_type, referenced_table, \
+         unnest(constraint_column_names) AS column_name, \
+         unnest(referenced_column_names) AS referred_column, \
+         generate_subscripts(constraint_column_names, 1) AS position \
+         FROM duckdb_constraints() \
+         WHERE database_name = current_database() \
+         AND constraint_type IN ('PRIMARY KEY', 'UNIQUE', 'FOREIGN KEY') AND {} \
+         ORDER BY schema_name, table_name, constraint_index, position",
+        scope.condition("schema_name", "table_name")
+    );
+    let params = scope.params();
+    let params: Vec<&Value> = params.iter().collect();
+
+    let mut keys: HashMap<(String, String), Vec<TableKey>> = HashMap::new();
+    let mut indexes: HashMap<(String, String), Vec<Index>> = HashMap::new();
+    let mut last: Option<((String, String), i64)> = None;
+    for row in db.execute_sql_with_return(&sql, &params)?.iter() {
+        let schema: String = row.get("schema").expect("schema");
+        let table_name: String = row.get("table_name").expect("table_name");
+        let constraint_index: i64 = row.get("constraint_index").expect("constraint_index");
+        let name: Option<String> = row.get_opt("constraint_name").expect("constraint_name");
+        let constraint_type: String = row.get("constraint_type").expect("constraint_type");
+        let column = ColumnName::from(&row.get::<String>("column_name").expect("column_name"));
+        let table_key = (schema, table_name);
+        let is_same = last
+            .as_ref()
+            .map(|(key, index)| *key == table_key && *index == constraint_index)
+            .unwrap_or(false);
+        let table_keys = keys.entry(table_key.clone()).or_default();
+        if is_same {
+            match table_keys.last_mut().expect("must have the constraint") {
+                TableKey::PrimaryKey(key) | TableKey::UniqueKey(key) => key.columns.push(column),
+                TableKey::ForeignKey(foreign_key) => {
+                    foreign_key.columns.push(column);
+                    let referred: String = row.get("referred_column").expect("referred_column");
+                    foreign_key
+                        .referred_columns
+                        .push(ColumnName::from(&referred));
+                }
+                _ => (),
+            }
+        } else {
+            match &*constraint_type {
+                "PRIMARY KEY" => table_keys.push(TableKey::PrimaryKey(Key {
+                    name,
+                    columns: vec![column],
+                })),
+                "UNIQUE" => table_keys.push(TableKey::UniqueKey(Key {
+                    name,
+                    columns: vec![column],
+                })),
+                _ => {
+                    let referenced_table: String =
+                        row.get("referenced_table").expect("referenced_table");
+                    let referred: String = row.get("referred_column").expect("referred_column");
+                    table_keys.push(TableKey::ForeignKey(ForeignKey {
+                        name,
+                        columns: vec![column],
+                        // duckdb only allows foreign keys to the tables in the same schema
+                        foreign_table: TableName {
+                            name: referenced_table,
+                            schema: Some(table_key.0.clone()),
+                            catalog: None,
+                            alias: None,
+                        },
+                        referred_columns: vec![ColumnName::from(&referred)],
+                        // duckdb doesn't support the referential actions
+                        on_delete: ReferentialAction::NoAction,
+                        on_update: ReferentialAction::NoAction,
+                        match_type: MatchType::Simple,
+                        is_deferrable: false,
+                        is_initially_deferred: false,
+                    }))
+                }
+            }
+        }
+        last = Some((table_key, constraint_index));
+    }
+
+    // the primary and unique keys are backed by an ART index
+    for (table_key, table_keys) in keys.iter() {
+        let key_indexes = table_keys.iter().filter_map(|table_key| {
+            let (key, is_primary) = match table_key {
+                TableKey::PrimaryKey(key) => (key, true),
+                TableKey::UniqueKey(key) => (key, false),
+                _ => return None,
+            };
+            Some(Index {
+                name: key.name.clone().unwrap_or_default(),
+                columns: key
+                    .columns
+                    .iter()
+                    .cloned()
+                    .map(IndexColumn::Column)
+                    .collect(),
+                is_unique: true,
+                is_primary,
+                method: Some("art".to_string()),
+                predicate: None,
+                included_columns: vec![],
+            })
+        });
+        indexes
+            .entry(table_key.clone())
+            .or_default()
+            .extend(key_indexes);
+    }
+    Ok((keys, indexes))
+}
+
+/// get the check constraints of the tables in the scope, grouped by schema and table name.
+/// The columns used in the expression are matched by their names once the columns are loaded
+fn get_scoped_checks(
+    db: &mut dyn Database,
+    scope: &Scope,
+) -> Result<HashMap<(String, String), Vec<Check>>, DbError> {
+    let sql = format!(
+        "SELECT schema_name AS schema, table_name, constraint_name, expression \
+         FROM duckdb_constraints() \
+         WHERE database_name = current_database() AND constraint_type = 'CHECK' AND {} \
+         ORDER BY schema_name, table_name, constraint_index",
+        scope.condition("schema_name", "table_name")
+    );
+    let params = scope.params();
+    let params: Vec<&Value> = params.iter().collect();
+    let mut checks: HashMap<(String, String), Vec<Check>> = HashMap::new();
+    for row in db.execute_sql_with_return(&sql, &params)?.iter() {
+        let schema: String = row.get("schema").expect("schema");
+        let table_name: String = row.get("table_name").expect("table_name");
+        let expression: String = row.get("expression").expect("expression");
+        checks.entry((schema, table_name)).or_default().push(Check {
+            name: row.get_opt("constraint_name").expect("constraint_name"),
+            expression: util::trim_enclosing_parenthesis(&expression).to_string(),
+            columns: vec![],
+        });
+    }
+    Ok(checks)
+}
+
+/// get the indexes created with `CREATE INDEX` of the tables in the scope,
+/// the key parts are extracted from the create sql of the index
+fn get_scoped_indexes(
+    db: &mut dyn Database,
+    scope: &Scope,
+) -> Result<HashMap<(String, String), Vec<Index>>, DbError> {
+    let sql = format!(
+        "SELECT schema_name AS schema, table_name, index_name, is_unique, is_primary, sql \
+         FROM duckdb_indexes() \
+         WHERE database_name = current_database() AND {} \
+         ORDER BY schema_name, table_name, index_name",
+        scope.condition("schema_name", "table_name")
+    );
+    let params = scope.params();
+    let params: Vec<&Value> = params.iter().collect();
+    let mut indexes: HashMap<(String, String), Vec<Index>> = HashMap::new();
+    for row in db.execute_sql_with_return(&sql, &params)?.iter() {
+        let schema: String = row.get("schema").expect("schema");
+        let table_name: String = row.get("table_name").expect("table_name");
+        let create_sql: Option<String> = row.get_opt("sql").expect("sql");
+        let columns = create_sql
+            .as_ref()
+            .map(|sql| extract_index_expressions(sql.trim_end_matches(';')))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|expression| {
+                let name = expression.trim_matches('"');
+                if name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                    IndexColumn::Column(ColumnName::from(name))
+                } else {
+                    IndexColumn::Expression(expression)
+                }
+            })
+            .collect();
+        indexes
+            .entry((schema, table_name))
+            .or_default()
+            .push(Index {
+                name: row.get("index_name").expect("index_name"),
+                columns,
+                is_unique: row.get("is_unique").expect("is_unique"),
+                is_primary: row.get("is_primary").expect("is_primary"),
+                method: Some("art".to_string()),
+                predicate: None,
+                included_columns: vec![],
+            });
+    }
+    Ok(indexes)
+}
+
+#[derive(Debug, Error)]
+pub enum DuckdbError {
+    #[error("Error executing {0}")]
+    SqlError(#[from] duckdb::Error),
+    #[error("Pool initialization error: {0}")]
+    PoolInitializationError(#[from] r2d2::Error),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        codegen::{FromDao, ToColumnNames, ToDao, ToTableName},
+        EntityManager, Pool,
+    };
+    use chrono::{DateTime, Utc};
+
+    /// an in-memory database, which is fresh for every pool
+    fn em() -> EntityManager {
+        let mut em = Pool::new().em("duckdb://").expect("must be ok");
+        let db = match em.0 {
+            DBPlatform::Duckdb(ref mut db) => db,
+            _ => unreachable!(),
+        };
+        // a prepared statement can only have one statement
+        db.0.execute_batch(
+            "CREATE SEQUENCE seq_actor_id; \
+                 CREATE TYPE mpaa_rating AS ENUM ('G', 'PG-13', 'it''s', 'a, b'); \
+                 CREATE TABLE actor ( \
+                     actor_id INTEGER PRIMARY KEY DEFAULT nextval('seq_actor_id'), \
+                     first_name VARCHAR NOT NULL, \
+                     last_name VARCHAR NOT NULL, \
+                     last_update TIMESTAMP NOT NULL DEFAULT current_timestamp, \
+                     mpaa_rating mpaa_rating, \
+                     UNIQUE (first_name, last_name), \
+                     CHECK (length(first_name) > 0) \
+                 ); \
+                 CREATE TABLE film_actor ( \
+                     actor_id INTEGER NOT NULL REFERENCES actor (actor_id), \
+                     film_id INTEGER NOT NULL, \
+                     rating DECIMAL(4,2) DEFAULT 0, \
+                     PRIMARY KEY (actor_id, film_id) \
+                 ); \
+                 CREATE INDEX idx_actor_last_name ON actor (last_name); \
+                 CREATE VIEW actor_info AS SELECT first_name, last_name FROM actor",
+        )
+        .expect("must create the tables");
+        em
+    }
+
+    #[test]
+    fn value_conversion() {
+        let mut em = em();
+        let sql = "SELECT $1 AS b, $2 AS i, $3 AS big, $4 AS d, $5 AS t, $6 AS date, \
+                   $7 AS ts, $8 AS blob, CAST('12.50' AS DECIMAL(4,2)) AS rating, \
+                   CAST(NULL AS VARCHAR) AS nil";
+        let date = NaiveDate::from_ymd(2020, 2, 29);
+        let ts = date.and_hms_micro(13, 14, 15, 123_456);
+        let rows = em
+            .db()
+            .execute_sql_with_return(
+                sql,
+                &[
+                    &Value::Bool(true),
+                    &Value::Int(42),
+                    &Value::Bigint(i64::MAX),
+                    &Value::Double(1.5),
+                    &Value::Text("rust".to_string()),
+                    &Value::Date(date),
+                    &Value::DateTime(ts),
+                    &Value::Blob(vec![0, 1, 2]),
+                ],
+            )
+            .expect("must select");
+        let row = rows.iter().next().expect("must have a row");
+        assert!(row.get::<bool>("b").unwrap());
+        assert_eq!(row.get::<i32>("i").unwrap(), 42);
+        assert_eq!(row.get::<i64>("big").unwrap(), i64::MAX);
+        assert_eq!(row.get::<f64>("d").unwrap(), 1.5);
+        assert_eq!(row.get::<String>("t").unwrap(), "rust");
+        assert_eq!(row.get::<NaiveDate>("date").unwrap(), date);
+        assert_eq!(row.get::<NaiveDateTime>("ts").unwrap(), ts);
+        assert_eq!(row.get::<Vec<u8>>("blob").unwrap(), vec![0, 1, 2]);
+        assert_eq!(
+            row.get_value("rating"),
+            Some(&Value::BigDecimal(BigDecimal::from_str("12.50").unwrap()))
+        );
+        assert_eq!(row.get_opt::<String>("nil").unwrap(), None);
+    }
+
+    #[test]
+    fn out_of_range_temporal_values() {
+        let mut em = em();
+        for sql in &[
+            "SELECT 'infinity'::DATE AS v",
+            "SELECT '-infinity'::DATE AS v",
+            "SELECT 'infinity'::TIMESTAMP AS v",
+            "SELECT TIME '24:00:00' AS v",
+        ] {
+            match em.db().execute_sql_with_return(sql, &[]) {
+                Err(DbError::ConvertError(ConvertError::UnsupportedDataType(_))) => (),
+                other => panic!("{} must be unsupported, got {:?}", sql, other),
+            }
+        }
+        let rows = em
+            .db()
+            .execute_sql_with_return("SELECT TIME '23:59:59.999999' AS v", &[])
+            .expect("must select");
+        let row = rows.iter().next().expect("must have a row");
+        assert_eq!(
+            row.get_value("v"),
+            Some(&Value::Time(NaiveTime::from_hms_micro(23, 59, 59, 999_999)))
+        );
+    }
+
+    #[test]
+    fn out_of_range_duckdb_values() {
+        // the representation of `infinity`, `-infinity` and `24:00:00` in duckdb
+        for value in vec![
+            DkValue::Date32(i32::MAX),
+            DkValue::Date32(-i32::MAX),
+            DkValue::Timestamp(TimeUnit::Microsecond, i64::MAX),
+            DkValue::Timestamp(TimeUnit::Microsecond, -i64::MAX),
+            DkValue::Time64(TimeUnit::Microsecond, 86_400_000_000),
+            DkValue::Time64(TimeUnit::Microsecond, -1),
+        ] {
+            match from_dk_value(value) {
+                Err(DbError::ConvertError(ConvertError::UnsupportedDataType(_))) => (),
+                other => panic!("must be unsupported, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn enum_data_type() {
+        assert_eq!(
+            to_sql_type_capacity("ENUM('G', 'PG-13', 'it''s', 'a, b')"),
+            (
+                SqlType::Enum(
+                    String::new(),
+                    vec![
+                        "G".to_string(),
+                        "PG-13".to_string(),
+                        "it's".to_string(),
+                        "a, b".to_string()
+                    ]
+                ),
+                None
+            )
+        );
+    }
+
+    #[test]
+    fn table_definition() {
+        let mut em = em();
+        let actor = em
+            .get_table(&TableName::from("actor"))
+            .expect("must be ok")
+            .expect("must have the table");
+        assert_eq!(actor.name, TableName::from("main.actor"));
+        assert!(!actor.is_view);
+        let column_names: Vec<&str> = actor.columns.iter().map(|c| &*c.name.name).collect();
+        assert_eq!(
+            column_names,
+            vec![
+                "actor_id",
+                "first_name",
+                "last_name",
+                "last_update",
+                "mpaa_rating"
+            ]
+        );
+        let actor_id = &actor.columns[0].specification;
+        assert_eq!(actor_id.sql_type, SqlType::Int);
+        assert!(actor_id
+            .constraints
+            .contains(&ColumnConstraint::AutoIncrement(Some(
+                "seq_actor_id".to_string()
+            ))));
+        assert!(actor.columns[3]
+            .specification
+            .constraints
+            .contains(&ColumnConstraint::DefaultValue(Literal::CurrentTimestamp)));
+        assert_eq!(
+            actor.columns[4].specification.sql_type,
+            SqlType::Enum(
+                String::new(),
+                vec![
+                    "G".to_string(),
+                    "PG-13".to_string(),
+                    "it's".to_string(),
+                    "a, b".to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            actor.get_primary_column_names(),
+            vec![&ColumnName::from("actor_id")]
+        );
+        let unique: Vec<&Key> = actor
+            .table_key
+            .iter()
+            .filter_map(|key| match key {
+                TableKey::UniqueKey(key) => Some(key),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(unique.len(), 1);
+        assert_eq!(
+            unique[0].columns,
+            vec![
+                ColumnName::from("first_name"),
+                ColumnName::from("last_name")
+            ]
+        );
+        let check = actor
+            .table_key
+            .iter()
+            .find_map(|key| match key {
+                TableKey::Check(check) => Some(check),
+                _ => None,
+            })
+            .expect("must have a check");
+        assert_eq!(check.columns, vec![ColumnName::from("first_name")]);
+        let index = actor
+            .indexes
+            .iter()
+            .find(|index| index.name == "idx_actor_last_name")
+            .expect("must have the index");
+        assert_eq!(
+            index.columns,
+            vec![IndexColumn::Column(ColumnName::from("last_name"))]
+        );
+        assert!(actor.indexes.iter().any(|index| index.is_primary));
+
+        let film_actor = em
+            .get_table(&TableName::from("film_actor"))
+            .expect("must be ok")
+            .expect("must have the table");
+        assert_eq!(film_actor.get_primary_column_names().len(), 2);
+        let foreign_key = film_actor.get_foreign_keys()[0];
+        assert_eq!(foreign_key.foreign_table, TableName::from("main.actor"));
+        assert_eq!(foreign_key.columns, vec![ColumnName::from("actor_id")]);
+        assert_eq!(
+            foreign_key.referred_columns,
+            vec![ColumnName::from("actor_id")]
+        );
+        assert_eq!(
+            film_actor.columns[2].specification.capacity,
+            Some(Capacity::Range(4, 2))
+        );
+    }
+
+    #[test]
+    fn grouped_tables_and_view() {
+        let mut em = em();
+        let grouped = em.get_grouped_tables().expect("must be ok");
+        assert_eq!(grouped.len(), 1);
+        assert_eq!(grouped[0].schema, "main");
+        assert_eq!(grouped[0].tablenames.len(), 2);
+        assert_eq!(grouped[0].views, vec![TableName::from("main.actor_info")]);
+
+        let view = em
+            .get_view_definition(&TableName::from("actor_info"))
+            .expect("must be ok")
+            .expect("must have the view");
+        assert!(view.definition.starts_with("SELECT"));
+        assert_eq!(view.dependencies, vec![TableName::from("main.actor")]);
+        assert!(em.db().get_schema_version().expect("must be ok").is_some());
+    }
+
+    #[test]
+    fn insert_and_append() {
+        #[derive(Debug, ToDao, ToColumnNames, ToTableName)]
+        struct Actor {
+            first_name: String,
+            last_name: String,
+        }
+
+        #[derive(Debug, FromDao, ToColumnNames)]
+        struct RetrieveActor {
+            actor_id: i32,
+            first_name: String,
+            last_update: DateTime<Utc>,
+        }
+
+        #[derive(Debug, ToDao, ToColumnNames, ToTableName)]
+        struct FilmActor {
+            actor_id: i32,
+            film_id: i32,
+            rating: f64,
+        }
+
+        let mut em = em();
+        let actor = Actor {
+            first_name: "HURRY".to_string(),
+            last_name: "POTTER".to_string(),
+        };
+        let inserted: Vec<RetrieveActor> = em.insert(&[&actor]).expect("must insert");
+        assert_eq!(inserted.len(), 1);
+        assert_eq!(inserted[0].first_name, "HURRY");
+        assert!(inserted[0].last_update.timestamp() > 0);
+
+        let film_actors: Vec<FilmActor> = (1..=100)
+            .map(|film_id| FilmActor {
+                actor_id: inserted[0].actor_id,
+                film_id,
+                rating: 9.5,
+            })
+            .collect();
+        let film_actors: Vec<&FilmActor> = film_actors.iter().collect();
+        assert_eq!(em.append(&film_actors).expect("must append"), 100);
+        assert_eq!(
+            em.get_total_records(&TableName::from("film_actor"))
+                .expect("must count"),
+            100
+        );
+    }
+
+    #[test]
+    fn unsupported_value_is_a_convert_error() {
+        let composite = Value::Composite(vec![("id".to_string(), Value::Int(1))]);
+        match to_dk_value(&composite) {
+            Err(DbError::ConvertError(ConvertError::UnsupportedDataType(_))) => (),
+            other => panic!("expecting a convert error, got: {:?}", other),
+        }
+        assert!(matches!(to_dk_value(&Value::Int(1)), Ok(DkValue::Int(1))));
+    }
+}
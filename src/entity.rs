@@ -1,29 +1,108 @@
 #[cfg(feature = "db-auth")]
 use crate::db_auth::{Role, User};
+#[cfg(feature = "with-postgres")]
+use crate::CopyFormat;
 use crate::{
-    table::SchemaContent, DBPlatform, DataError, Database, DatabaseName, DbError, TableDef,
-    ToValue, Value,
+    common, placeholder,
+    routine::{self, Routine, RoutineResult},
+    table::{SchemaContent, TableStat},
+    trigger::Trigger,
+    view::ViewDef,
+    DBPlatform, DataError, Database, DatabaseName, DbError, Dialect, SchemaCache, SchemaGraph,
+    TableDef, ToValue, Value,
 };
 
-use rustorm_dao::{FromDao, TableName, ToColumnNames, ToDao, ToTableName};
+#[cfg(any(feature = "with-postgres", feature = "with-mysql"))]
+use rustorm_dao::Rows;
+use rustorm_dao::{ColumnName, FromDao, TableName, ToColumnNames, ToDao, ToTableName};
 
-pub struct EntityManager(pub DBPlatform);
+/// the schema cache is shared with the pool this entity manager is created from, if enabled.
+/// The last field is whether a transaction was started with `begin_transaction` and is still open
+pub struct EntityManager(
+    pub(crate) DBPlatform,
+    pub(crate) Option<SchemaCache>,
+    pub(crate) bool,
+);
+
+/// the savepoint of the chunked inserts done inside the transaction of the caller
+pub(crate) const INSERT_SAVEPOINT: &str = "rustorm_insert";
+
+/// the savepoint of the mysql rows which are inserted one at a time, it is nested in the
+/// savepoint of the chunks so it has a different name
+#[cfg(feature = "with-mysql")]
+pub(crate) const MYSQL_ROWS_SAVEPOINT: &str = "rustorm_insert_rows";
 
 impl EntityManager {
+    /// an entity manager of the database connection, without a schema cache
+    pub fn new(db: DBPlatform) -> Self {
+        EntityManager(db, None, false)
+    }
+
     pub fn begin_transaction(&mut self) -> Result<(), DbError> {
-        self.0.begin_transaction()
+        self.0.begin_transaction()?;
+        self.2 = true;
+        Ok(())
     }
 
     pub fn commit_transaction(&mut self) -> Result<(), DbError> {
+        self.2 = false;
         self.0.commit_transaction()
     }
 
     pub fn rollback_transaction(&mut self) -> Result<(), DbError> {
+        self.2 = false;
         self.0.rollback_transaction()
     }
 
+    /// execute a `SAVEPOINT`, `RELEASE SAVEPOINT` or `ROLLBACK TO SAVEPOINT` statement,
+    /// duckdb has no savepoints so a failed write is left for the rollback of the caller
+    fn execute_savepoint(&mut self, sql: &str) -> Result<(), DbError> {
+        #[cfg(feature = "with-duckdb")]
+        {
+            if let DBPlatform::Duckdb(_) = self.0 {
+                return Ok(());
+            }
+        }
+        self.0.execute_sql_with_return(sql, &[])?;
+        Ok(())
+    }
+
+    /// start a transaction, or a savepoint when a transaction was started with
+    /// `begin_transaction`, so several statements are written as one. Returns whether it is a
+    /// savepoint
+    fn begin_atomic(&mut self, savepoint: &str) -> Result<bool, DbError> {
+        if self.2 {
+            self.execute_savepoint(&format!("SAVEPOINT {}", savepoint))?;
+            Ok(true)
+        } else {
+            self.begin_transaction()?;
+            Ok(false)
+        }
+    }
+
+    /// commit the transaction or release the savepoint started with `begin_atomic`
+    fn commit_atomic(&mut self, savepoint: &str, nested: bool) -> Result<(), DbError> {
+        if nested {
+            self.execute_savepoint(&format!("RELEASE SAVEPOINT {}", savepoint))
+        } else {
+            self.commit_transaction()
+        }
+    }
+
+    /// roll back the transaction or the savepoint started with `begin_atomic`
+    fn rollback_atomic(&mut self, savepoint: &str, nested: bool) -> Result<(), DbError> {
+        if nested {
+            self.execute_savepoint(&format!("ROLLBACK TO SAVEPOINT {}", savepoint))
+        } else {
+            self.rollback_transaction()
+        }
+    }
+
     pub fn set_session_user(&mut self, username: &str) -> Result<(), DbError> {
-        let sql = format!("SET SESSION ROLE '{}'", username);
+        let sql = format!(
+            "SET SESSION ROLE {}",
+            self.0.dialect().quote_literal(username)?
+        );
         self.0.execute_sql_with_return(&sql, &[])?;
         Ok(())
     }
@@ -59,18 +138,7 @@ impl EntityManager {
     where
         T: ToTableName + ToColumnNames + FromDao,
     {
-        let table = T::to_table_name();
-        let columns = T::to_column_names();
-        let enumerated_columns = columns
-            .iter()
-            .map(|c| c.name.to_owned())
-            .collect::<Vec<_>>()
-            .join(", ");
-        let sql = format!(
-            "SELECT {} FROM {}",
-            enumerated_columns,
-            table.complete_name()
-        );
+        let sql = select_all_sql::<T>(self.0.dialect())?;
         let rows = self.0.execute_sql_with_return(&sql, &[])?;
         let mut entities = vec![];
         for dao in rows.iter() {
@@ -82,7 +150,10 @@ impl EntityManager {
 
     /// get the table from database based on this column name
     pub fn get_table(&mut self, table_name: &TableName) -> Result<Option<TableDef>, DbError> {
-        self.0.get_table(table_name)
+        match self.1 {
+            Some(ref cache) => cache.get_table(&mut *self.0, table_name),
+            None => self.0.get_table(table_name),
+        }
     }
 
     /// set the autoincrement value of the primary column(if present) of this table.
@@ -102,10 +173,18 @@ impl EntityManager {
         self.0.get_autoincrement_last_value(table_name)
     }
 
-    /// get all the user table and views from the database
+    /// get all the user table and views from the database,
+    /// the columns, keys and indexes of all the tables are loaded with a fixed number of queries
     pub fn get_all_tables(&mut self) -> Result<Vec<TableDef>, DbError> {
-        info!("EXPENSIVE DB OPERATION: get_all_tables");
-        self.0.get_all_tables()
+        match self.1 {
+            Some(ref cache) => cache.get_all_tables(&mut *self.0),
+            None => self.0.get_all_tables(),
+        }
+    }
+
+    /// get the dependency graph of all the tables derived from their foreign keys
+    pub fn get_schema_graph(&mut self) -> Result<SchemaGraph, DbError> {
+        Ok(SchemaGraph::new(self.get_all_tables()?))
     }
 
     /// get all the tablenames
@@ -119,10 +198,7 @@ impl EntityManager {
         struct Count {
             count: i64,
         }
-        let sql = format!(
-            "SELECT COUNT(*) AS count FROM {}",
-            table_name.complete_name()
-        );
+        let sql = total_records_sql(table_name, self.0.dialect())?;
         let count: Result<Count, DbError> = self.execute_sql_with_one_return(&sql, &[]);
         count.map(|c| c.count as usize)
     }
@@ -136,23 +212,140 @@ impl EntityManager {
         self.0.get_grouped_tables()
     }
 
-    #[allow(unused_variables)]
+    /// get the estimated row count and size of the table without counting the records
+    pub fn get_table_stat(&mut self, table_name: &TableName) -> Result<Option<TableStat>, DbError> {
+        let mut stats = self.0.get_table_stats(&[table_name.clone()])?;
+        Ok(stats.pop())
+    }
+
+    /// get the estimated row count and size of these tables
+    pub fn get_table_stats(
+        &mut self,
+        table_names: &[TableName],
+    ) -> Result<Vec<TableStat>, DbError> {
+        self.0.get_table_stats(table_names)
+    }
+
+    /// get the estimated row count and size of all the tables in `get_grouped_tables`,
+    /// views are not included
+    pub fn get_grouped_table_stats(&mut self) -> Result<Vec<TableStat>, DbError> {
+        let table_names: Vec<TableName> = self
+            .get_grouped_tables()?
+            .into_iter()
+            .flat_map(|content| content.tablenames)
+            .collect();
+        self.0.get_table_stats(&table_names)
+    }
+
+    /// get the definition and dependencies of the view or materialized view
+    pub fn get_view_definition(
+        &mut self,
+        view_name: &TableName,
+    ) -> Result<Option<ViewDef>, DbError> {
+        self.0.get_view_definition(view_name)
+    }
+
+    /// get the triggers defined on this table
+    pub fn get_triggers(&mut self, table_name: &TableName) -> Result<Vec<Trigger>, DbError> {
+        self.0.get_triggers(table_name)
+    }
+
+    /// refresh the content of the materialized view
+    pub fn refresh_materialized_view(
+        &mut self,
+        view_name: &TableName,
+        concurrently: bool,
+    ) -> Result<(), DbError> {
+        self.0.refresh_materialized_view(view_name, concurrently)
+    }
+
+    /// get the stored functions and procedures of the database
+    pub fn get_routines(&mut self) -> Result<Vec<Routine>, DbError> {
+        self.0.get_routines()
+    }
+
+    /// call the stored function or procedure with these values for its IN and INOUT arguments,
+    /// the overload which has the same number of arguments and accepts the type of the values
+    /// is called
+    pub fn call_routine(
+        &mut self,
+        routine_name: &TableName,
+        args: &[&dyn ToValue],
+    ) -> Result<RoutineResult, DbError> {
+        let values: Vec<Value> = args.iter().map(|arg| arg.to_value()).collect();
+        let bvalues: Vec<&Value> = values.iter().collect();
+        let routines = self.0.get_routine(routine_name)?;
+        let routine = routine::find_overload(routine_name, routines, &bvalues)?;
+        self.0.call_routine(&routine, &bvalues)
+    }
+
+    /// insert the entities and return the inserted records, see `insert_with_progress`
     pub fn insert<T, R>(&mut self, entities: &[&T]) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+    {
+        self.insert_with_progress(entities, |_, _| ())
+    }
+
+    /// insert the entities in chunks which keep the number of parameters of each statement under
+    /// the limit of the platform, all the chunks are inserted in one transaction.
+    /// `progress` is called after each chunk with the number of entities inserted so far and the
+    /// total number of entities.
+    ///
+    /// Inside a transaction started with `begin_transaction` the chunks are inserted in a
+    /// savepoint instead, which is rolled back on error and the transaction is left open
+    pub fn insert_with_progress<T, R, F>(
+        &mut self,
+        entities: &[&T],
+        mut progress: F,
+    ) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+        F: FnMut(usize, usize),
+    {
+        let chunk_size = insert_chunk_size(T::to_column_names().len(), self.0.max_params());
+        if entities.len() <= chunk_size {
+            let retrieved_entities = self.insert_chunk(entities)?;
+            progress(entities.len(), entities.len());
+            return Ok(retrieved_entities);
+        }
+        let nested = self.begin_atomic(INSERT_SAVEPOINT)?;
+        let mut retrieved_entities = Vec::with_capacity(entities.len());
+        let mut inserted = 0;
+        for chunk in entities.chunks(chunk_size) {
+            match self.insert_chunk(chunk) {
+                Ok(retrieved) => retrieved_entities.extend(retrieved),
+                Err(e) => {
+                    // the error of the insert is more useful than the error of the rollback
+                    let _ = self.rollback_atomic(INSERT_SAVEPOINT, nested);
+                    return Err(e);
+                }
+            }
+            inserted += chunk.len();
+            progress(inserted, entities.len());
+        }
+        self.commit_atomic(INSERT_SAVEPOINT, nested)?;
+        Ok(retrieved_entities)
+    }
+
+    /// insert the entities which fit in a single statement
+    fn insert_chunk<T, R>(&mut self, entities: &[&T]) -> Result<Vec<R>, DbError>
     where
         T: ToTableName + ToColumnNames + ToDao,
         R: FromDao + ToColumnNames,
     {
         match self.0 {
-            #[cfg(feature = "with-sqlite")]
-            DBPlatform::Sqlite(_) => self.insert_simple(entities),
-            #[cfg(feature = "with-postgres")]
-            DBPlatform::Postgres(_) => self.insert_bulk_with_returning_support(entities),
             #[cfg(feature = "with-mysql")]
-            DBPlatform::Mysql(_) => self.insert_simple(entities),
+            DBPlatform::Mysql(_) => self.insert_mysql(entities),
+            _ if self.0.supports_returning() => self.insert_bulk_with_returning_support(entities),
+            _ => self.insert_simple(entities),
         }
     }
 
-    /// called when the platform used is postgresql
+    /// called when the platform supports `RETURNING`, which are postgresql, sqlite 3.35.0 and
+    /// later, duckdb, MariaDB 10.5 and later and the registered backends which say so
     pub fn insert_bulk_with_returning_support<T, R>(
         &mut self,
         entities: &[&T],
@@ -161,22 +354,10 @@ impl EntityManager {
         T: ToTableName + ToColumnNames + ToDao,
         R: FromDao + ToColumnNames,
     {
-        let columns = T::to_column_names();
-        let mut sql = self.build_insert_clause(entities);
-        let return_columns = R::to_column_names();
-        sql += &self.build_returning_clause(return_columns);
-
-        let mut values: Vec<Value> = Vec::with_capacity(entities.len() * columns.len());
-        for entity in entities {
-            let dao = entity.to_dao();
-            for col in columns.iter() {
-                let value = dao.get_value(&col.name);
-                match value {
-                    Some(value) => values.push(value.clone()),
-                    None => values.push(Value::Nil),
-                }
-            }
-        }
+        let dialect = self.0.dialect();
+        let mut sql = insert_clause(entities, dialect)?;
+        sql += &returning_clause(&R::to_column_names(), dialect)?;
+        let values = insert_values(entities);
         let bvalues: Vec<&Value> = values.iter().collect();
         let rows = self.0.execute_sql_with_return(&sql, &bvalues)?;
         let mut retrieved_entities = vec![];
@@ -187,55 +368,150 @@ impl EntityManager {
         Ok(retrieved_entities)
     }
 
+    /// insert a chunk of entities into mysql with a multi-row insert. The generated keys are
+    /// derived from `LAST_INSERT_ID` and the number of inserted rows, or taken from the entities
+    /// when they supply the primary key, then the records are selected back by their primary key.
+    /// MariaDB 10.5 and later use `INSERT ... RETURNING` instead
+    #[cfg(feature = "with-mysql")]
+    fn insert_mysql<T, R>(&mut self, entities: &[&T]) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+    {
+        let supports_returning = match self.0 {
+            DBPlatform::Mysql(ref mut db) => db.server_supports_returning()?,
+            _ => false,
+        };
+        if supports_returning {
+            return self.insert_bulk_with_returning_support(entities);
+        }
+        let table_name = T::to_table_name();
+        let table = self.get_table(&table_name)?.ok_or_else(|| {
+            DbError::DataError(DataError::TableNameNotFound(table_name.complete_name()))
+        })?;
+        let primary: Vec<ColumnName> = table
+            .get_primary_column_names()
+            .into_iter()
+            .cloned()
+            .collect();
+        let (supplied_keys, generated) = mysql_supplied_keys(&table_name, &primary, entities)?;
+        let increment = match self.0 {
+            DBPlatform::Mysql(ref mut db) if generated > 0 => db.auto_increment_increment()?,
+            _ => 1,
+        };
+        // the generated keys of a statement are only known to be consecutive when all of its rows
+        // are generated, so the rows are inserted one at a time when the keys are mixed
+        if generated == 0 || generated == entities.len() {
+            let chunk_size = entities.len().max(1);
+            return self.insert_mysql_rows(
+                entities,
+                &primary,
+                &supplied_keys,
+                chunk_size,
+                increment,
+            );
+        }
+        // the rows inserted one at a time are all inserted or none like a single statement
+        let nested = self.begin_atomic(MYSQL_ROWS_SAVEPOINT)?;
+        match self.insert_mysql_rows(entities, &primary, &supplied_keys, 1, increment) {
+            Ok(retrieved_entities) => {
+                self.commit_atomic(MYSQL_ROWS_SAVEPOINT, nested)?;
+                Ok(retrieved_entities)
+            }
+            Err(e) => {
+                let _ = self.rollback_atomic(MYSQL_ROWS_SAVEPOINT, nested);
+                Err(e)
+            }
+        }
+    }
+
+    /// insert the entities into mysql with statements of `chunk_size` rows, then select them back
+    /// by the supplied keys and the keys generated from `LAST_INSERT_ID`
+    #[cfg(feature = "with-mysql")]
+    fn insert_mysql_rows<T, R>(
+        &mut self,
+        entities: &[&T],
+        primary: &[ColumnName],
+        supplied_keys: &[Option<Vec<Value>>],
+        chunk_size: usize,
+        increment: u64,
+    ) -> Result<Vec<R>, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+        R: FromDao + ToColumnNames,
+    {
+        let table_name = T::to_table_name();
+        let dialect = self.0.dialect();
+        let mut keys: Vec<Vec<Value>> = Vec::with_capacity(entities.len());
+        for (chunk, chunk_keys) in entities
+            .chunks(chunk_size)
+            .zip(supplied_keys.chunks(chunk_size))
+        {
+            let sql = insert_clause(chunk, dialect)?;
+            let values = insert_values(chunk);
+            let bvalues: Vec<&Value> = values.iter().collect();
+            let (first_id, inserted) = match self.0 {
+                DBPlatform::Mysql(ref mut db) => db.execute_insert(&sql, &bvalues)?,
+                _ => unreachable!(),
+            };
+            push_mysql_keys(&mut keys, chunk_keys, first_id, inserted, increment);
+        }
+        check_record_count(&table_name, entities.len(), keys.len())?;
+        self.select_by_primary_keys(&table_name, primary, &keys)
+    }
+
+    /// select the records of the table with the primary key values, in the order of the keys.
+    /// A key which matches no record is a `RecordCountMismatch`, the records may have been
+    /// deleted by a trigger or their key was guessed wrong
+    #[cfg(feature = "with-mysql")]
+    fn select_by_primary_keys<R>(
+        &mut self,
+        table_name: &TableName,
+        primary: &[ColumnName],
+        keys: &[Vec<Value>],
+    ) -> Result<Vec<R>, DbError>
+    where
+        R: FromDao + ToColumnNames,
+    {
+        let dialect = self.0.dialect();
+        let mut retrieved_entities = Vec::with_capacity(keys.len());
+        let chunk_size = insert_chunk_size(primary.len(), self.0.max_params());
+        for chunk in keys.chunks(chunk_size) {
+            let sql = select_by_keys_sql::<R>(table_name, primary, chunk.len(), dialect)?;
+            let values: Vec<&Value> = chunk.iter().flatten().collect();
+            let rows = self.0.execute_sql_with_return(&sql, &values)?;
+            retrieved_entities.extend(in_key_order(&rows, primary, chunk));
+        }
+        check_record_count(table_name, keys.len(), retrieved_entities.len())?;
+        Ok(retrieved_entities)
+    }
+
     /// called multiple times when using database platform that doesn;t support multiple value
     /// insert such as sqlite
     pub fn single_insert<T>(&mut self, entity: &T) -> Result<(), DbError>
     where
         T: ToTableName + ToColumnNames + ToDao,
     {
-        let columns = T::to_column_names();
-        let sql = self.build_insert_clause(&[entity]);
-        let dao = entity.to_dao();
-        let mut values: Vec<Value> = Vec::with_capacity(columns.len());
-        for col in columns.iter() {
-            let value = dao.get_value(&col.name);
-            match value {
-                Some(value) => values.push(value.clone()),
-                None => values.push(Value::Nil),
-            }
-        }
+        let sql = insert_clause(&[entity], self.0.dialect())?;
+        let values = insert_values(&[entity]);
         let bvalues: Vec<&Value> = values.iter().collect();
         self.0.execute_sql_with_return(&sql, &bvalues)?;
         Ok(())
     }
 
-    /// this is soly for use with sqlite since sqlite doesn't support bulk insert
+    /// insert the entities one at a time and retrieve each of them with `LAST_INSERT_ROWID`, used
+    /// with the sqlite versions older than 3.35.0 and the registered backends which don't support
+    /// `RETURNING`.
+    ///
+    /// Note: the inserted records are looked up by ROWID, so this doesn't work with the tables
+    /// created `WITHOUT ROWID`
     pub fn insert_simple<T, R>(&mut self, entities: &[&T]) -> Result<Vec<R>, DbError>
     where
         T: ToTableName + ToColumnNames + ToDao,
         R: FromDao + ToColumnNames,
     {
-        let return_columns = R::to_column_names();
-        let return_column_names = return_columns
-            .iter()
-            .map(|rc| rc.name.to_owned())
-            .collect::<Vec<_>>()
-            .join(", ");
-
-        let table = T::to_table_name();
-        //TODO: move this specific query to sqlite
-        let last_insert_sql = format!(
-            "\
-             SELECT {} \
-             FROM {} \
-             WHERE ROWID = (\
-             SELECT LAST_INSERT_ROWID() FROM {})",
-            return_column_names,
-            table.complete_name(),
-            table.complete_name()
-        );
+        let last_insert_sql = last_insert_sql::<T, R>(self.0.dialect())?;
         let mut retrieved_entities = vec![];
-        println!("sql: {}", last_insert_sql);
         for entity in entities {
             self.single_insert(*entity)?;
             let retrieved = self.execute_sql_with_return(&last_insert_sql, &[])?;
@@ -244,67 +520,85 @@ impl EntityManager {
         Ok(retrieved_entities)
     }
 
-    /// build the returning clause
-    fn build_returning_clause(&self, return_columns: Vec<rustorm_dao::ColumnName>) -> String {
-        format!(
-            "\nRETURNING \n{}",
-            return_columns
-                .iter()
-                .map(|rc| rc.name.to_owned())
-                .collect::<Vec<_>>()
-                .join(", ")
-        )
+    /// bulk load the entities with the appender of duckdb, the inserted records are not returned.
+    ///
+    /// Note: the appender fills all the columns of the table, so the fields of the entity has to
+    /// be all the columns of the table, in the same order. The values which duckdb can not
+    /// store are a `ConvertError`
+    #[cfg(feature = "with-duckdb")]
+    pub fn append<T>(&mut self, entities: &[&T]) -> Result<usize, DbError>
+    where
+        T: ToTableName + ToColumnNames + ToDao,
+    {
+        let columns_len = T::to_column_names().len();
+        let values = insert_values(entities);
+        let rows: Vec<Vec<Value>> = values.chunks(columns_len).map(|row| row.to_vec()).collect();
+        match self.0 {
+            DBPlatform::Duckdb(ref mut db) => db.append(&T::to_table_name(), &rows),
+            _ => Err(DbError::UnsupportedOperation(
+                "append is only supported on duckdb".to_string(),
+            )),
+        }
     }
 
-    /// build an insert clause
-    fn build_insert_clause<T>(&self, entities: &[&T]) -> String
+    /// copy the entities into their table with `COPY ... FROM STDIN`, which is much faster than
+    /// inserting them. The copied records are not returned, only their count
+    #[cfg(feature = "with-postgres")]
+    pub fn copy_in<T>(&mut self, entities: &[&T], format: CopyFormat) -> Result<u64, DbError>
     where
         T: ToTableName + ToColumnNames + ToDao,
     {
-        let table = T::to_table_name();
         let columns = T::to_column_names();
-        let columns_len = columns.len();
-        let mut sql = String::new();
-        sql += &format!("INSERT INTO {} ", table.complete_name());
-        sql += &format!(
-            "({})\n",
-            columns
-                .iter()
-                .map(|c| c.name.to_owned())
-                .collect::<Vec<_>>()
-                .join(", ")
-        );
-        sql += "VALUES ";
-        sql += &entities
-            .iter()
-            .enumerate()
-            .map(|(y, _)| {
-                format!(
-                    "\n\t({})",
-                    columns
-                        .iter()
-                        .enumerate()
-                        .map(|(x, _)| {
-                            #[allow(unreachable_patterns)]
-                            match self.0 {
-                                #[cfg(feature = "with-sqlite")]
-                                DBPlatform::Sqlite(_) => format!("${}", y * columns_len + x + 1),
-                                #[cfg(feature = "with-postgres")]
-                                DBPlatform::Postgres(_) => format!("${}", y * columns_len + x + 1),
-                                #[cfg(feature = "with-mysql")]
-                                DBPlatform::Mysql(_) => "?".to_string(),
-                                _ => format!("${}", y * columns_len + x + 1),
-                            }
-                        })
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                )
-            })
-            .collect::<Vec<_>>()
-            .join(", ");
-        sql
+        let mut rows = Rows::new(columns.iter().map(|c| c.name.to_string()).collect());
+        for row in insert_values(entities).chunks(columns.len().max(1)) {
+            rows.push(row.to_vec());
+        }
+        self.copy_in_rows(&T::to_table_name(), &rows, format)
+    }
+
+    /// copy the rows into the table, the columns of the rows are the columns of the table
+    #[cfg(feature = "with-postgres")]
+    pub fn copy_in_rows(
+        &mut self,
+        table_name: &TableName,
+        rows: &Rows,
+        format: CopyFormat,
+    ) -> Result<u64, DbError> {
+        match self.0 {
+            DBPlatform::Postgres(ref mut db) => db.copy_in(table_name, rows, format),
+            _ => Err(DbError::UnsupportedOperation(
+                "copy is only supported on postgresql".to_string(),
+            )),
+        }
+    }
+
+    /// copy all the records of the table of the entity out with `COPY ... TO STDOUT`
+    #[cfg(feature = "with-postgres")]
+    pub fn copy_out<R>(&mut self, format: CopyFormat) -> Result<Vec<R>, DbError>
+    where
+        R: ToTableName + ToColumnNames + FromDao,
+    {
+        let rows = self.copy_out_rows(&R::to_table_name(), &R::to_column_names(), format)?;
+        Ok(rows.iter().map(|dao| R::from_dao(&dao)).collect())
+    }
+
+    /// copy the columns of all the records of the table out
+    #[cfg(feature = "with-postgres")]
+    pub fn copy_out_rows(
+        &mut self,
+        table_name: &TableName,
+        columns: &[ColumnName],
+        format: CopyFormat,
+    ) -> Result<Rows, DbError> {
+        match self.0 {
+            DBPlatform::Postgres(ref mut db) => db.copy_out(table_name, columns, format),
+            _ => Err(DbError::UnsupportedOperation(
+                "copy is only supported on postgresql".to_string(),
+            )),
+        }
     }
 
+    /// execute the sql, the `$n` or `?` placeholders are rewritten to the style of the platform
     #[allow(clippy::redundant_closure)]
     pub fn execute_sql_with_return<'a, R>(
         &mut self,
@@ -316,7 +610,30 @@ impl EntityManager {
     {
         let values: Vec<Value> = params.iter().map(|p| p.to_value()).collect();
         let bvalues: Vec<&Value> = values.iter().collect();
-        let rows = self.0.execute_sql_with_return(sql, &bvalues)?;
+        let rows = self.0.execute_portable_sql(sql, &bvalues)?;
+        if let Some(ref cache) = self.1 {
+            cache.invalidate_if_ddl(sql);
+        }
+        Ok(rows.iter().map(|dao| R::from_dao(&dao)).collect::<Vec<R>>())
+    }
+
+    /// execute the sql with named parameters, ie: `:actor_id`, which are bound to the values of
+    /// the dao, map or struct with the same name
+    pub fn execute_sql_with_named_params<R, P>(
+        &mut self,
+        sql: &str,
+        params: &P,
+    ) -> Result<Vec<R>, DbError>
+    where
+        R: FromDao,
+        P: ToDao,
+    {
+        let (sql, values) = placeholder::bind_named(sql, self.0.dialect(), &params.to_dao())?;
+        let bvalues: Vec<&Value> = values.iter().collect();
+        let rows = self.0.execute_sql_with_return(&sql, &bvalues)?;
+        if let Some(ref cache) = self.1 {
+            cache.invalidate_if_ddl(&sql);
+        }
         Ok(rows.iter().map(|dao| R::from_dao(&dao)).collect::<Vec<R>>())
     }
 
@@ -358,3 +675,256 @@ impl EntityManager {
         }
     }
 }
+
+/// the primary key values supplied by the entities, None for the entities which leave the key to
+/// be generated by `AUTO_INCREMENT`, and the number of keys to be generated
+#[cfg(feature = "with-mysql")]
+pub(crate) fn mysql_supplied_keys<T>(
+    table_name: &TableName,
+    primary: &[ColumnName],
+    entities: &[&T],
+) -> Result<(Vec<Option<Vec<Value>>>, usize), DbError>
+where
+    T: ToDao,
+{
+    if primary.is_empty() {
+        return Err(DbError::DataError(DataError::NoPrimaryKey(
+            table_name.complete_name(),
+        )));
+    }
+    let supplied_keys: Vec<Option<Vec<Value>>> = entities
+        .iter()
+        .map(|entity| {
+            let dao = entity.to_dao();
+            primary
+                .iter()
+                .map(|pk| match dao.get_value(&pk.name) {
+                    Some(Value::Nil) | None => None,
+                    Some(value) => Some(value.clone()),
+                })
+                .collect()
+        })
+        .collect();
+    let generated = supplied_keys.iter().filter(|key| key.is_none()).count();
+    if generated > 0 && primary.len() > 1 {
+        return Err(DbError::DataError(DataError::MissingPrimaryKeyValue(
+            table_name.complete_name(),
+            primary[0].name.to_string(),
+        )));
+    }
+    Ok((supplied_keys, generated))
+}
+
+/// push the keys of the rows of a mysql insert, the generated keys start at the first
+/// `AUTO_INCREMENT` value of the statement and step by `increment`
+#[cfg(feature = "with-mysql")]
+pub(crate) fn push_mysql_keys(
+    keys: &mut Vec<Vec<Value>>,
+    chunk_keys: &[Option<Vec<Value>>],
+    first_id: u64,
+    inserted: u64,
+    increment: u64,
+) {
+    let mut generated_ids = (0..inserted).map(|i| first_id + i * increment);
+    for key in chunk_keys {
+        match key {
+            Some(key) => keys.push(key.clone()),
+            None => {
+                if let Some(id) = generated_ids.next() {
+                    keys.push(vec![Value::Bigint(id as i64)]);
+                }
+            }
+        }
+    }
+}
+
+/// the number of records which are found is the number which is expected
+#[cfg(feature = "with-mysql")]
+pub(crate) fn check_record_count(
+    table_name: &TableName,
+    expected: usize,
+    found: usize,
+) -> Result<(), DbError> {
+    if expected != found {
+        return Err(DbError::DataError(DataError::RecordCountMismatch(
+            table_name.complete_name(),
+            expected,
+            found,
+        )));
+    }
+    Ok(())
+}
+
+/// select the columns of `R` and the primary key of the records with `key_count` keys
+#[cfg(feature = "with-mysql")]
+pub(crate) fn select_by_keys_sql<R>(
+    table_name: &TableName,
+    primary: &[ColumnName],
+    key_count: usize,
+    dialect: Dialect,
+) -> Result<String, DbError>
+where
+    R: ToColumnNames,
+{
+    let mut columns = R::to_column_names();
+    for pk in primary {
+        if !columns.iter().any(|column| column.name == pk.name) {
+            columns.push(pk.clone());
+        }
+    }
+    let keys = (0..key_count)
+        .map(|y| {
+            format!(
+                "({})",
+                (0..primary.len())
+                    .map(|x| placeholder::placeholder(dialect, y * primary.len() + x + 1))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(format!(
+        "SELECT {} FROM {} WHERE ({}) IN ({})",
+        common::quoted_column_names(&columns, dialect)?,
+        table_name.quoted_complete_name(dialect)?,
+        common::quoted_column_names(primary, dialect)?,
+        keys
+    ))
+}
+
+/// the records of the rows in the order of the keys, the keys which matched no row are skipped
+#[cfg(feature = "with-mysql")]
+pub(crate) fn in_key_order<R>(rows: &Rows, primary: &[ColumnName], keys: &[Vec<Value>]) -> Vec<R>
+where
+    R: FromDao,
+{
+    fn key_string<'a>(values: impl Iterator<Item = &'a Value>) -> String {
+        values
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\u{0}")
+    }
+
+    let mut daos: std::collections::HashMap<String, _> = rows
+        .iter()
+        .map(|dao| {
+            let key = key_string(primary.iter().filter_map(|pk| dao.get_value(&pk.name)));
+            (key, dao)
+        })
+        .collect();
+    keys.iter()
+        .filter_map(|key| daos.remove(&key_string(key.iter())))
+        .map(|dao| R::from_dao(&dao))
+        .collect()
+}
+
+/// select all the columns of the entity from its table
+pub(crate) fn select_all_sql<T>(dialect: Dialect) -> Result<String, DbError>
+where
+    T: ToTableName + ToColumnNames,
+{
+    Ok(format!(
+        "SELECT {} FROM {}",
+        common::quoted_column_names(&T::to_column_names(), dialect)?,
+        T::to_table_name().quoted_complete_name(dialect)?
+    ))
+}
+
+/// count the records of this table
+pub(crate) fn total_records_sql(
+    table_name: &TableName,
+    dialect: Dialect,
+) -> Result<String, DbError> {
+    Ok(format!(
+        "SELECT COUNT(*) AS count FROM {}",
+        table_name.quoted_complete_name(dialect)?
+    ))
+}
+
+/// select the last inserted record of the table of `T` in sqlite
+//TODO: move this specific query to sqlite
+pub(crate) fn last_insert_sql<T, R>(dialect: Dialect) -> Result<String, DbError>
+where
+    T: ToTableName,
+    R: ToColumnNames,
+{
+    let return_column_names = common::quoted_column_names(&R::to_column_names(), dialect)?;
+    let table_name = T::to_table_name().quoted_complete_name(dialect)?;
+    Ok(format!(
+        "\
+         SELECT {} \
+         FROM {} \
+         WHERE ROWID = (\
+         SELECT LAST_INSERT_ROWID() FROM {})",
+        return_column_names, table_name, table_name
+    ))
+}
+
+/// build the returning clause
+pub(crate) fn returning_clause(
+    return_columns: &[ColumnName],
+    dialect: Dialect,
+) -> Result<String, DbError> {
+    Ok(format!(
+        "\nRETURNING \n{}",
+        common::quoted_column_names(return_columns, dialect)?
+    ))
+}
+
+/// the number of rows of a multi-row insert which keeps the number of parameters under
+/// `max_params`, at least 1
+pub(crate) fn insert_chunk_size(columns_len: usize, max_params: usize) -> usize {
+    (max_params / columns_len.max(1)).max(1)
+}
+
+/// build an insert clause with a row of placeholders for each of the entities
+pub(crate) fn insert_clause<T>(entities: &[&T], dialect: Dialect) -> Result<String, DbError>
+where
+    T: ToTableName + ToColumnNames,
+{
+    let table = T::to_table_name();
+    let columns = T::to_column_names();
+    let columns_len = columns.len();
+    let mut sql = String::new();
+    sql += &format!("INSERT INTO {} ", table.quoted_complete_name(dialect)?);
+    sql += &format!("({})\n", common::quoted_column_names(&columns, dialect)?);
+    sql += "VALUES ";
+    sql += &entities
+        .iter()
+        .enumerate()
+        .map(|(y, _)| {
+            format!(
+                "\n\t({})",
+                columns
+                    .iter()
+                    .enumerate()
+                    .map(|(x, _)| placeholder::placeholder(dialect, y * columns_len + x + 1))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(sql)
+}
+
+/// the values of the entities in the order of the placeholders of the insert clause
+pub(crate) fn insert_values<T>(entities: &[&T]) -> Vec<Value>
+where
+    T: ToColumnNames + ToDao,
+{
+    let columns = T::to_column_names();
+    let mut values: Vec<Value> = Vec::with_capacity(entities.len() * columns.len());
+    for entity in entities {
+        let dao = entity.to_dao();
+        for col in columns.iter() {
+            let value = dao.get_value(&col.name);
+            match value {
+                Some(value) => values.push(value.clone()),
+                None => values.push(Value::Nil),
+            }
+        }
+    }
+    values
+}
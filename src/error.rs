@@ -1,5 +1,6 @@
 use cfg_if::cfg_if;
 use r2d2;
+use rustorm_dao::{NameParseError, UnsafeSql};
 use thiserror::Error;
 use url;
 
@@ -16,6 +17,10 @@ cfg_if! {if #[cfg(feature = "with-mysql")]{
     use crate::my::MysqlError;
 }}
 
+cfg_if! {if #[cfg(feature = "with-duckdb")]{
+    use crate::duck::DuckdbError;
+}}
+
 #[derive(Debug, Error)]
 pub enum ConnectError {
     #[error("No such pool connection")]
@@ -26,6 +31,9 @@ pub enum ConnectError {
     UnsupportedDb(String),
     #[error("{0}")]
     R2d2Error(#[from] r2d2::Error),
+    /// the connection of an async database is no longer usable, ie: its thread has stopped
+    #[error("The connection is closed")]
+    ConnectionClosed,
 }
 
 #[derive(Debug, Error)]
@@ -46,6 +54,9 @@ pub enum PlatformError {
     #[cfg(feature = "with-mysql")]
     #[error("{0}")]
     MysqlError(#[from] MysqlError),
+    #[cfg(feature = "with-duckdb")]
+    #[error("{0}")]
+    DuckdbError(#[from] DuckdbError),
 }
 
 impl Into<DataOpError> for PlatformError {
@@ -80,6 +91,7 @@ impl Into<DataOpError> for PlatformError {
                                     TableName {
                                         name: table.to_string(),
                                         schema: schema.clone(),
+                                        catalog: None,
                                         alias: None,
                                     }
                                     .complete_name(),
@@ -99,6 +111,40 @@ impl Into<DataOpError> for PlatformError {
                         }
                     }
                 }
+                #[cfg(feature = "with-postgres-async")]
+                PostgresError::AsyncSqlError(ref pg_err, ref sql) => {
+                    use std::error::Error;
+                    let db_err = pg_err
+                        .source()
+                        .and_then(|e| e.downcast_ref::<tokio_postgres::error::DbError>());
+                    if let Some(db_err) = db_err {
+                        use crate::TableName;
+                        DataOpError::ConstraintError {
+                            severity: db_err.severity().to_string(),
+                            code: db_err.code().code().to_string(),
+                            message: db_err.message().to_string(),
+                            detail: db_err.detail().map(ToString::to_string),
+                            cause_table: db_err.table().map(|table| {
+                                TableName {
+                                    name: table.to_string(),
+                                    schema: db_err.schema().map(ToString::to_string),
+                                    catalog: None,
+                                    alias: None,
+                                }
+                                .complete_name()
+                            }),
+                            constraint: db_err.constraint().map(ToString::to_string),
+                            column: db_err.column().map(ToString::to_string),
+                            datatype: db_err.datatype().map(ToString::to_string),
+                            sql: sql.to_string(),
+                        }
+                    } else {
+                        DataOpError::GenericError {
+                            message: postgres_err.to_string(),
+                            sql: None,
+                        }
+                    }
+                }
                 _ => DataOpError::GenericError {
                     message: postgres_err.to_string(),
                     sql: None,
@@ -114,6 +160,11 @@ impl Into<DataOpError> for PlatformError {
                 message: e.to_string(),
                 sql: None,
             },
+            #[cfg(feature = "with-duckdb")]
+            PlatformError::DuckdbError(e) => DataOpError::GenericError {
+                message: e.to_string(),
+                sql: None,
+            },
         }
     }
 }
@@ -148,6 +199,25 @@ impl From<MysqlError> for DbError {
     }
 }
 
+#[cfg(feature = "with-duckdb")]
+impl From<DuckdbError> for DbError {
+    fn from(e: DuckdbError) -> Self {
+        DbError::DataOpError(PlatformError::DuckdbError(e).into())
+    }
+}
+
+impl From<NameParseError> for DbError {
+    fn from(e: NameParseError) -> Self {
+        DbError::DataError(DataError::InvalidName(e))
+    }
+}
+
+impl From<UnsafeSql> for DbError {
+    fn from(e: UnsafeSql) -> Self {
+        DbError::SqlInjectionAttempt(e.0)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum DbError {
     #[error("Sql injection attempt error: {0}")]
@@ -202,4 +272,30 @@ pub enum DataError {
     MoreThan1RecordReturned,
     #[error("Table {0} not found")]
     TableNameNotFound(String),
+    #[error("Table {0} has no foreign key to table {1}")]
+    NoForeignKeyToTable(String, String),
+    #[error("Table {0} has no primary key")]
+    NoPrimaryKey(String),
+    #[error("Missing value for the primary key column {1} of table {0}")]
+    MissingPrimaryKeyValue(String, String),
+    #[error("Table {0} has no column {1}")]
+    ColumnNameNotFound(String, String),
+    #[error("Expecting {1} records of table {0}, {2} were found")]
+    RecordCountMismatch(String, usize, usize),
+    #[error("Invalid page token {0}")]
+    InvalidPageToken(String),
+    #[error("Invalid page size {0}, a page has to have at least 1 record")]
+    InvalidPageSize(usize),
+    #[error("Routine {0} with {1} arguments not found")]
+    RoutineNotFound(String, usize),
+    #[error("Routine {0} has {1} overloads with {2} arguments, the values don't single out one")]
+    AmbiguousRoutine(String, usize, usize),
+    #[error("Missing value for the named parameter :{0}")]
+    MissingNamedParameter(String),
+    #[error("The value of {0} is not used by any named parameter")]
+    UnusedNamedParameter(String),
+    #[error("Invalid placeholder: {0}")]
+    InvalidPlaceholder(String),
+    #[error("{0}")]
+    InvalidName(#[from] NameParseError),
 }
@@ -21,6 +21,7 @@ cfg_if! {if #[cfg(feature = "with-postgres")]{
     #[macro_use]
     extern crate postgres_shared;
     mod pg;
+    pub use pg::CopyFormat;
 }}
 cfg_if! {if #[cfg(feature = "with-sqlite")]{
     extern crate r2d2_sqlite;
@@ -30,7 +31,21 @@ cfg_if! {if #[cfg(feature = "with-sqlite")]{
 cfg_if! {if #[cfg(feature = "with-mysql")]{
     mod my;
 }}
+cfg_if! {if #[cfg(feature = "with-duckdb")]{
+    mod duck;
+    pub use duck::DuckdbDB;
+}}
+
+cfg_if! {if #[cfg(feature = "async")]{
+    mod async_database;
+    mod async_entity;
+    pub mod async_pool;
+    pub use async_database::{AsyncDBPlatform, AsyncDatabase};
+    pub use async_entity::AsyncEntityManager;
+    pub use async_pool::AsyncPool;
+}}
 
+pub mod backend;
 pub mod column;
 pub mod common;
 mod dao_manager;
@@ -39,13 +54,22 @@ mod database;
 mod db_auth;
 mod entity;
 pub mod error;
+pub mod paging;
+mod placeholder;
 mod platform;
 pub mod pool;
+mod relation;
+pub mod routine;
+pub mod schema_cache;
+pub mod schema_graph;
 pub mod table;
+pub mod trigger;
 pub mod types;
 
 pub mod util;
+pub mod view;
 
+pub use backend::{register_backend, Backend, BackendPool};
 pub use chrono;
 pub use column::ColumnDef;
 pub use dao_manager::DaoManager;
@@ -54,6 +78,8 @@ pub use entity::EntityManager;
 pub use error::{DataError, DbError};
 pub use platform::DBPlatform;
 pub use pool::Pool;
+pub use schema_cache::{SchemaCache, SchemaCacheConfig};
+pub use schema_graph::SchemaGraph;
 pub use table::TableDef;
 pub use uuid::{self, Uuid};
 
@@ -62,7 +88,8 @@ pub use uuid::{self, Uuid};
 pub use codegen::{FromDao, ToColumnNames, ToDao, ToTableName};
 
 pub use rustorm_dao::{
-    self, Array, ColumnName, ConvertError, Dao, FromValue, Rows, TableName, ToValue, Value,
+    self, Array, ColumnName, ConvertError, Dao, Dialect, FromValue, NameParseError, Rows,
+    TableName, ToValue, UnsafeSql, Value,
 };
 
 /// Wrap the rustorm_dao exports to avoid name conflict with the rustorm_codegen
@@ -0,0 +1,206 @@
+//! The async mysql driver.
+//!
+//! The values are converted by the same `MyValue` and `into_value` of the synchronous driver,
+//! by converting the values of mysql_async into their equivalent mysql value.
+use super::{into_value, is_mariadb_with_returning, MyValue, MysqlError, ServerSettings};
+use crate::{AsyncDatabase, ColumnName, DbError, Rows, TableName, Value};
+use async_trait::async_trait;
+use mysql_async::prelude::Queryable;
+use r2d2_mysql::mysql::{self, consts::ColumnType};
+
+pub fn init_pool(db_url: &str) -> Result<mysql_async::Pool, MysqlError> {
+    mysql_async::Pool::from_url(db_url)
+        .map_err(|e| MysqlError::AsyncSqlError(e, "Connection Manager Error".into()))
+}
+
+pub struct AsyncMysqlDB(pub mysql_async::Conn, ServerSettings);
+
+impl AsyncMysqlDB {
+    pub fn new(conn: mysql_async::Conn) -> Self {
+        AsyncMysqlDB(conn, ServerSettings::default())
+    }
+
+    /// execute an insert statement, returning the first `AUTO_INCREMENT` value generated by the
+    /// statement and the number of inserted rows
+    pub async fn execute_insert(
+        &mut self,
+        sql: &str,
+        params: &[&Value],
+    ) -> Result<(u64, u64), DbError> {
+        if params.is_empty() {
+            self.0.query_drop(sql).await
+        } else {
+            self.0.exec_drop(sql, to_async_params(params)?).await
+        }
+        .map_err(|e| MysqlError::AsyncSqlError(e, sql.to_string()))?;
+        Ok((self.0.last_insert_id().unwrap_or(0), self.0.affected_rows()))
+    }
+
+    /// whether the server is MariaDB 10.5 or later, which supports `INSERT ... RETURNING`
+    pub async fn server_supports_returning(&mut self) -> Result<bool, DbError> {
+        if let Some(supports_returning) = self.1.supports_returning {
+            return Ok(supports_returning);
+        }
+        let rows = self
+            .my_execute_sql_with_return("SELECT VERSION() AS version", &[])
+            .await?;
+        let version: Option<String> = rows
+            .iter()
+            .next()
+            .and_then(|row| row.get_opt("version").ok().flatten());
+        let supports_returning =
+            version.map_or(false, |version| is_mariadb_with_returning(&version));
+        self.1.supports_returning = Some(supports_returning);
+        Ok(supports_returning)
+    }
+
+    /// the step between the `AUTO_INCREMENT` values generated by a multi-row insert
+    pub async fn auto_increment_increment(&mut self) -> Result<u64, DbError> {
+        if let Some(increment) = self.1.auto_increment_increment {
+            return Ok(increment);
+        }
+        let rows = self
+            .my_execute_sql_with_return(
+                "SELECT CAST(@@auto_increment_increment AS SIGNED) AS increment",
+                &[],
+            )
+            .await?;
+        let increment: Option<i64> = rows
+            .iter()
+            .next()
+            .and_then(|row| row.get_opt("increment").ok().flatten());
+        let increment = increment.map_or(1, |increment| increment as u64);
+        self.1.auto_increment_increment = Some(increment);
+        Ok(increment)
+    }
+
+    /// the columns of the primary key of the table, the async api doesn't introspect the whole
+    /// table definition
+    pub async fn primary_key_columns(
+        &mut self,
+        table_name: &TableName,
+    ) -> Result<Vec<ColumnName>, DbError> {
+        let sql = "SELECT COLUMN_NAME AS name FROM information_schema.KEY_COLUMN_USAGE \
+             WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ? \
+             AND CONSTRAINT_NAME = 'PRIMARY' ORDER BY ORDINAL_POSITION";
+        let schema = match table_name.schema {
+            Some(ref schema) => Value::Text(schema.to_string()),
+            None => Value::Nil,
+        };
+        let name = Value::Text(table_name.name.to_string());
+        let rows = self
+            .my_execute_sql_with_return(sql, &[&schema, &name])
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| ColumnName {
+                name: row.get("name").expect("must have a column name"),
+                table: None,
+                alias: None,
+            })
+            .collect())
+    }
+    async fn my_execute_sql_with_return(
+        &mut self,
+        sql: &str,
+        param: &[&Value],
+    ) -> Result<Rows, DbError> {
+        let rows: Vec<mysql_async::Row> = if param.is_empty() {
+            self.0.query(sql).await
+        } else {
+            self.0.exec(sql, to_async_params(param)?).await
+        }
+        .map_err(|e| MysqlError::AsyncSqlError(e, sql.to_string()))?;
+
+        // the columns are only known from the rows
+        let columns = match rows.first() {
+            Some(row) => row.columns_ref().to_vec(),
+            None => vec![],
+        };
+        let column_names = columns
+            .iter()
+            .map(|c| std::str::from_utf8(c.name_ref()).map(ToString::to_string))
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(MysqlError::Utf8Error)?;
+        let column_types: Vec<ColumnType> = columns
+            .iter()
+            .map(|c| ColumnType::from(c.column_type() as u8))
+            .collect();
+
+        let mut records = Rows::new(column_names);
+        for row in rows {
+            let record = row
+                .unwrap()
+                .into_iter()
+                .zip(column_types.iter())
+                .map(|(cell, column_type)| into_value(to_sync_value(cell), column_type))
+                .collect::<Result<Vec<Value>, MysqlError>>()?;
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+#[async_trait]
+impl AsyncDatabase for AsyncMysqlDB {
+    async fn begin_transaction(&mut self) -> Result<(), DbError> {
+        self.execute_sql_with_return("START TRANSACTION", &[])
+            .await?;
+        Ok(())
+    }
+
+    async fn commit_transaction(&mut self) -> Result<(), DbError> {
+        self.execute_sql_with_return("COMMIT", &[]).await?;
+        Ok(())
+    }
+
+    async fn rollback_transaction(&mut self) -> Result<(), DbError> {
+        self.execute_sql_with_return("ROLLBACK", &[]).await?;
+        Ok(())
+    }
+
+    async fn execute_sql_with_return(
+        &mut self,
+        sql: &str,
+        param: &[&Value],
+    ) -> Result<Rows, DbError> {
+        Ok(self.my_execute_sql_with_return(sql, param).await?)
+    }
+}
+
+fn to_async_params(values: &[&Value]) -> Result<mysql_async::Params, DbError> {
+    let values = values
+        .iter()
+        .map(|v| MyValue(v).to_value().map(to_async_value))
+        .collect::<Result<Vec<_>, DbError>>()?;
+    Ok(mysql_async::Params::Positional(values))
+}
+
+/// the equivalent value of the synchronous driver
+fn to_sync_value(value: mysql_async::Value) -> mysql::Value {
+    use mysql_async::Value as AsyncValue;
+    match value {
+        AsyncValue::NULL => mysql::Value::NULL,
+        AsyncValue::Bytes(v) => mysql::Value::Bytes(v),
+        AsyncValue::Int(v) => mysql::Value::Int(v),
+        AsyncValue::UInt(v) => mysql::Value::UInt(v),
+        AsyncValue::Float(v) => mysql::Value::Float(f64::from(v)),
+        AsyncValue::Double(v) => mysql::Value::Float(v),
+        AsyncValue::Date(y, m, d, h, i, s, us) => mysql::Value::Date(y, m, d, h, i, s, us),
+        AsyncValue::Time(neg, d, h, i, s, us) => mysql::Value::Time(neg, d, h, i, s, us),
+    }
+}
+
+/// the equivalent value of mysql_async
+fn to_async_value(value: mysql::Value) -> mysql_async::Value {
+    use mysql_async::Value as AsyncValue;
+    match value {
+        mysql::Value::NULL => AsyncValue::NULL,
+        mysql::Value::Bytes(v) => AsyncValue::Bytes(v),
+        mysql::Value::Int(v) => AsyncValue::Int(v),
+        mysql::Value::UInt(v) => AsyncValue::UInt(v),
+        mysql::Value::Float(v) => AsyncValue::Double(v),
+        mysql::Value::Date(y, m, d, h, i, s, us) => AsyncValue::Date(y, m, d, h, i, s, us),
+        mysql::Value::Time(neg, d, h, i, s, us) => AsyncValue::Time(neg, d, h, i, s, us),
+    }
+}
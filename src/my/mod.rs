@@ -1,14 +1,31 @@
 #[cfg(feature = "db-auth")]
 use crate::db_auth::{Role, User};
 use crate::{
-    column, common, table::SchemaContent, types::SqlType, ColumnDef, ColumnName, DataError,
-    Database, DatabaseName, DbError, FromDao, TableDef, TableName, ToValue, Value,
+    backend::{Backend, BackendPool},
+    column::{self, ColumnStat, MostCommonValue},
+    common,
+    error::{ConnectError, ConvertError},
+    routine::{ArgumentMode, Routine, RoutineArgument, RoutineKind, RoutineResult},
+    table::{
+        Check, ForeignKey, Index, IndexColumn, Key, MatchType, ReferentialAction, SchemaContent,
+        TableKey, TableStat,
+    },
+    trigger::{Trigger, TriggerEvent, TriggerTiming},
+    types::SqlType,
+    util,
+    view::ViewDef,
+    ColumnDef, ColumnName, DBPlatform, Dao, DataError, Database, DatabaseName, DbError, Dialect,
+    FromDao, TableDef, TableName, ToValue, Value,
 };
 use r2d2::ManageConnection;
 use r2d2_mysql::{self, mysql};
 use rustorm_dao::{FromDao, Rows};
+use std::{collections::HashMap, sync::Arc};
 use thiserror::Error;
 
+#[cfg(feature = "with-mysql-async")]
+pub(crate) mod async_my;
+
 pub fn init_pool(
     db_url: &str,
 ) -> Result<r2d2::Pool<r2d2_mysql::MysqlConnectionManager>, MysqlError> {
@@ -29,21 +46,131 @@ pub fn test_connection(db_url: &str) -> Result<(), MysqlError> {
     Ok(())
 }
 
-pub struct MysqlDB(pub r2d2::PooledConnection<r2d2_mysql::MysqlConnectionManager>);
+/// the built-in mysql backend
+pub struct MysqlBackend;
+
+impl Backend for MysqlBackend {
+    fn init_pool(&self, db_url: &str) -> Result<Arc<dyn BackendPool>, DbError> {
+        Ok(Arc::new(init_pool(db_url)?))
+    }
+
+    fn test_connection(&self, db_url: &str) -> Result<(), DbError> {
+        Ok(test_connection(db_url)?)
+    }
+}
+
+impl BackendPool for r2d2::Pool<r2d2_mysql::MysqlConnectionManager> {
+    fn connect(&self) -> Result<DBPlatform, DbError> {
+        let conn = self.get().map_err(ConnectError::R2d2Error)?;
+        Ok(DBPlatform::Mysql(Box::new(MysqlDB::new(conn))))
+    }
+}
+
+pub struct MysqlDB(
+    pub r2d2::PooledConnection<r2d2_mysql::MysqlConnectionManager>,
+    ServerSettings,
+);
+
+/// the settings of the server which are looked up once per connection
+#[derive(Default)]
+struct ServerSettings {
+    supports_returning: Option<bool>,
+    auto_increment_increment: Option<u64>,
+}
+
+impl MysqlDB {
+    pub fn new(conn: r2d2::PooledConnection<r2d2_mysql::MysqlConnectionManager>) -> Self {
+        MysqlDB(conn, ServerSettings::default())
+    }
+
+    /// execute an insert statement, returning the first `AUTO_INCREMENT` value generated by the
+    /// statement and the number of inserted rows
+    pub fn execute_insert(&mut self, sql: &str, params: &[&Value]) -> Result<(u64, u64), DbError> {
+        if params.is_empty() {
+            let result = self
+                .0
+                .query(&sql)
+                .map_err(|e| MysqlError::SqlError(e, sql.to_string()))?;
+            Ok((result.last_insert_id(), result.affected_rows()))
+        } else {
+            let mut stmt = self
+                .0
+                .prepare(&sql)
+                .map_err(|e| MysqlError::SqlError(e, sql.to_string()))?;
+            let result = stmt
+                .execute(&to_my_params(params)?)
+                .map_err(|e| MysqlError::SqlError(e, sql.to_string()))?;
+            Ok((result.last_insert_id(), result.affected_rows()))
+        }
+    }
+
+    /// whether the server is MariaDB 10.5 or later, which supports `INSERT ... RETURNING`
+    pub fn server_supports_returning(&mut self) -> Result<bool, DbError> {
+        if let Some(supports_returning) = self.1.supports_returning {
+            return Ok(supports_returning);
+        }
+        let rows = self.execute_sql_with_return("SELECT VERSION() AS version", &[])?;
+        let version: Option<String> = rows
+            .iter()
+            .next()
+            .and_then(|row| row.get_opt("version").ok().flatten());
+        let supports_returning =
+            version.map_or(false, |version| is_mariadb_with_returning(&version));
+        self.1.supports_returning = Some(supports_returning);
+        Ok(supports_returning)
+    }
+
+    /// the step between the `AUTO_INCREMENT` values generated by a multi-row insert
+    pub fn auto_increment_increment(&mut self) -> Result<u64, DbError> {
+        if let Some(increment) = self.1.auto_increment_increment {
+            return Ok(increment);
+        }
+        let rows = self.execute_sql_with_return(
+            "SELECT CAST(@@auto_increment_increment AS SIGNED) AS increment",
+            &[],
+        )?;
+        let increment: Option<i64> = rows
+            .iter()
+            .next()
+            .and_then(|row| row.get_opt("increment").ok().flatten());
+        let increment = increment.map_or(1, |increment| increment as u64);
+        self.1.auto_increment_increment = Some(increment);
+        Ok(increment)
+    }
+}
+
+/// whether the version of the server, ie: `10.5.8-MariaDB-1:10.5.8+maria~focal`, is MariaDB 10.5
+/// or later
+fn is_mariadb_with_returning(version: &str) -> bool {
+    if !version.contains("MariaDB") {
+        return false;
+    }
+    let mut numbers = version
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|n| n.parse::<u32>());
+    match (numbers.next(), numbers.next()) {
+        (Some(Ok(major)), Some(Ok(minor))) => (major, minor) >= (10, 5),
+        _ => false,
+    }
+}
 
 impl Database for MysqlDB {
+    fn dialect(&self) -> Dialect {
+        Dialect::Mysql
+    }
+
     fn begin_transaction(&mut self) -> Result<(), DbError> {
         self.execute_sql_with_return("START TRANSACTION", &[])?;
         Ok(())
     }
 
     fn commit_transaction(&mut self) -> Result<(), DbError> {
-        self.execute_sql_with_return("COMMIT TRANSACTION", &[])?;
+        self.execute_sql_with_return("COMMIT", &[])?;
         Ok(())
     }
 
     fn rollback_transaction(&mut self) -> Result<(), DbError> {
-        self.execute_sql_with_return("ROLLBACK TRANSACTION", &[])?;
+        self.execute_sql_with_return("ROLLBACK", &[])?;
         Ok(())
     }
 
@@ -81,15 +208,8 @@ impl Database for MysqlDB {
                 .prepare(&sql)
                 .map_err(|e| MysqlError::SqlError(e, sql.to_string()))?;
 
-            let params: mysql::Params = param
-                .iter()
-                .map(|v| MyValue(v))
-                .map(|v| mysql::prelude::ToValue::to_value(&v))
-                .collect::<Vec<_>>()
-                .into();
-
             let rows = stmt
-                .execute(&params)
+                .execute(&to_my_params(param)?)
                 .map_err(|e| MysqlError::SqlError(e, sql.to_string()))?;
 
             collect(rows)
@@ -97,147 +217,11 @@ impl Database for MysqlDB {
     }
 
     fn get_table(&mut self, table_name: &TableName) -> Result<Option<TableDef>, DbError> {
-        #[derive(Debug, FromDao)]
-        struct TableSpec {
-            schema: String,
-            name: String,
-            comment: String,
-            is_view: i32,
+        let mut tables = get_scoped_tables(&mut *self, Some(table_name))?;
+        match tables.len() {
+            0 => Err(DbError::DataError(DataError::ZeroRecordReturned)),
+            _ => Ok(Some(tables.remove(0))),
         }
-
-        let schema = table_name
-            .schema
-            .as_ref()
-            .map(String::as_str)
-            .unwrap_or("__DUMMY__")
-            .into();
-        let table_name = &table_name.name.clone().into();
-
-        let mut tables: Vec<TableSpec> = self
-            .execute_sql_with_return(
-                r#"
-                SELECT TABLE_SCHEMA AS `schema`,
-                       TABLE_NAME AS name,
-                       TABLE_COMMENT AS comment,
-                       CASE TABLE_TYPE WHEN 'VIEW' THEN TRUE ELSE FALSE END AS is_view
-                  FROM INFORMATION_SCHEMA.TABLES
-                 WHERE TABLE_SCHEMA = CASE ? WHEN '__DUMMY__' THEN DATABASE() ELSE ? END AND TABLE_NAME = ?"#,
-                &[
-                    &schema, &schema,
-                    &table_name,
-                ],
-            )?
-            .iter()
-            .map(|dao| FromDao::from_dao(&dao))
-            .collect();
-
-        let table_spec = match tables.len() {
-            0 => return Err(DbError::DataError(DataError::ZeroRecordReturned)),
-            _ => tables.remove(0),
-        };
-
-        #[derive(Debug, FromDao)]
-        struct ColumnSpec {
-            schema: String,
-            table_name: String,
-            name: String,
-            comment: String,
-            type_: String,
-        }
-
-        let columns: Vec<ColumnDef> = self
-            .execute_sql_with_return(
-                r#"
-                SELECT TABLE_SCHEMA AS `schema`,
-                       TABLE_NAME AS table_name,
-                       COLUMN_NAME AS name,
-                       COLUMN_COMMENT AS comment,
-                       CAST(COLUMN_TYPE as CHAR(255)) AS type_
-                  FROM INFORMATION_SCHEMA.COLUMNS
-                 WHERE TABLE_SCHEMA = ? AND TABLE_NAME = ?"#,
-                &[&table_spec.schema.clone().into(), &table_name],
-            )?
-            .iter()
-            .map(|dao| FromDao::from_dao(&dao))
-            .map(|spec: ColumnSpec| {
-                let (sql_type, capacity) =
-                    if spec.type_.starts_with("enum(") || spec.type_.starts_with("set(") {
-                        let start = spec.type_.find('(');
-                        let end = spec.type_.find(')');
-                        if let (Some(start), Some(end)) = (start, end) {
-                            let dtype = &spec.type_[0..start];
-                            println!("dtype: {:?}", dtype);
-                            let range = &spec.type_[start + 1..end];
-                            let choices = range
-                                .split(',')
-                                .map(|v| v.to_owned())
-                                .collect::<Vec<String>>();
-
-                            match dtype {
-                                "enum" => (SqlType::Enum(dtype.to_owned(), choices), None),
-                                "set" => (SqlType::Enum(dtype.to_owned(), choices), None),
-                                _ => panic!("not yet handled: {}", dtype),
-                            }
-                        } else {
-                            panic!("not yet handled spec_type: {:?}", spec.type_)
-                        }
-                    } else {
-                        let (dtype, capacity) = common::extract_datatype_with_capacity(&spec.type_);
-                        let sql_type = match &*dtype {
-                            "tinyint" | "tinyint unsigned" => SqlType::Tinyint,
-                            "smallint" | "smallint unsigned" | "year" => SqlType::Smallint,
-                            "mediumint" | "mediumint unsigned" => SqlType::Int,
-                            "int" | "int unsigned" => SqlType::Int,
-                            "bigint" | "bigin unsigned" => SqlType::Bigint,
-                            "float" | "float unsigned" => SqlType::Float,
-                            "double" | "double unsigned" => SqlType::Double,
-                            "decimal" => SqlType::Numeric,
-                            "tinyblob" => SqlType::Tinyblob,
-                            "mediumblob" => SqlType::Mediumblob,
-                            "blob" => SqlType::Blob,
-                            "longblob" => SqlType::Longblob,
-                            "binary" | "varbinary" => SqlType::Varbinary,
-                            "char" => SqlType::Char,
-                            "varchar" => SqlType::Varchar,
-                            "tinytext" => SqlType::Tinytext,
-                            "mediumtext" => SqlType::Mediumtext,
-                            "text" | "longtext" => SqlType::Text,
-                            "date" => SqlType::Date,
-                            "datetime" | "timestamp" => SqlType::Timestamp,
-                            "time" => SqlType::Time,
-                            _ => panic!("not yet handled: {}", dtype),
-                        };
-
-                        (sql_type, capacity)
-                    };
-
-                ColumnDef {
-                    table: TableName::from(&format!("{}.{}", spec.schema, spec.table_name)),
-                    name: ColumnName::from(&spec.name),
-                    comment: Some(spec.comment),
-                    specification: column::ColumnSpecification {
-                        capacity,
-                        // TODO: implementation
-                        constraints: vec![],
-                        sql_type,
-                    },
-                    stat: None,
-                }
-            })
-            .collect();
-
-        Ok(Some(TableDef {
-            name: TableName {
-                name: table_spec.name,
-                schema: Some(table_spec.schema),
-                alias: None,
-            },
-            comment: Some(table_spec.comment),
-            columns,
-            is_view: table_spec.is_view == 1,
-            // TODO: implementation
-            table_key: vec![],
-        }))
     }
 
     fn get_tablenames(&mut self) -> Result<Vec<TableName>, DbError> {
@@ -266,11 +250,7 @@ impl Database for MysqlDB {
     }
 
     fn get_all_tables(&mut self) -> Result<Vec<TableDef>, DbError> {
-        let tablenames = self.get_tablenames()?;
-        Ok(tablenames
-            .iter()
-            .filter_map(|tablename| self.get_table(tablename).ok().flatten())
-            .collect())
+        get_scoped_tables(&mut *self, None)
     }
 
     fn get_grouped_tables(&mut self) -> Result<Vec<SchemaContent>, DbError> {
@@ -357,6 +337,621 @@ impl Database for MysqlDB {
     ) -> Result<Option<i64>, DbError> {
         todo!()
     }
+
+    /// TABLE_ROWS is exact for MyISAM tables but only an estimate for InnoDB tables
+    fn get_table_stats(&mut self, table_names: &[TableName]) -> Result<Vec<TableStat>, DbError> {
+        if table_names.is_empty() {
+            return Ok(vec![]);
+        }
+        let condition = table_names
+            .iter()
+            .map(|_| format!("({})", scope_condition("TABLE_SCHEMA", "TABLE_NAME")))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let params: Vec<Value> = table_names
+            .iter()
+            .flat_map(|table_name| scope_params(Some(table_name)))
+            .collect();
+        let params: Vec<&Value> = params.iter().collect();
+        let sql = format!(
+            r#"
+            SELECT TABLE_SCHEMA AS `schema`,
+                   TABLE_NAME AS name,
+                   ENGINE AS engine,
+                   CAST(TABLE_ROWS AS SIGNED) AS row_count,
+                   CAST(DATA_LENGTH AS SIGNED) AS table_size,
+                   CAST(INDEX_LENGTH AS SIGNED) AS index_size,
+                   DATABASE() AS current_schema
+              FROM INFORMATION_SCHEMA.TABLES
+             WHERE TABLE_TYPE = 'BASE TABLE'
+               AND ({})"#,
+            condition
+        );
+        let mut current_schema: Option<String> = None;
+        let mut rows: HashMap<(String, String), Dao> = HashMap::new();
+        for row in self.execute_sql_with_return(&sql, &params)?.iter() {
+            let schema: String = row.get("schema").expect("must have a schema");
+            let name: String = row.get("name").expect("must have a table name");
+            current_schema = row.get_opt("current_schema").expect("must not error");
+            rows.insert((schema, name), row);
+        }
+        let mut stats = Vec::with_capacity(table_names.len());
+        for table_name in table_names {
+            let row = table_name
+                .schema
+                .as_ref()
+                .or_else(|| current_schema.as_ref())
+                .and_then(|schema| rows.get(&(schema.to_string(), table_name.name.to_string())));
+            if let Some(row) = row {
+                let engine: Option<String> = row.get_opt("engine").expect("must not error");
+                stats.push(TableStat {
+                    table: TableName {
+                        name: row.get("name").expect("must have a table name"),
+                        schema: row.get_opt("schema").expect("must not error"),
+                        catalog: None,
+                        alias: None,
+                    },
+                    row_count: row.get_opt("row_count").expect("must not error"),
+                    is_exact: engine.as_ref().map(String::as_str) == Some("MyISAM"),
+                    table_size: row.get_opt("table_size").expect("must not error"),
+                    index_size: row.get_opt("index_size").expect("must not error"),
+                    // mysql doesn't record when the tables are optimized or analyzed
+                    last_vacuum: None,
+                    last_analyze: None,
+                });
+            }
+        }
+        Ok(stats)
+    }
+
+    fn get_view_definition(&mut self, view_name: &TableName) -> Result<Option<ViewDef>, DbError> {
+        let schema: Value = view_name
+            .schema
+            .as_ref()
+            .map(String::as_str)
+            .unwrap_or("__DUMMY__")
+            .into();
+        let name: Value = view_name.name.clone().into();
+        let rows = self.execute_sql_with_return(
+            r#"
+            SELECT TABLE_SCHEMA AS `schema`,
+                   TABLE_NAME AS name,
+                   VIEW_DEFINITION AS definition,
+                   IS_UPDATABLE AS is_updatable
+              FROM INFORMATION_SCHEMA.VIEWS
+             WHERE TABLE_SCHEMA = CASE ? WHEN '__DUMMY__' THEN DATABASE() ELSE ? END AND TABLE_NAME = ?"#,
+            &[&schema, &schema, &name],
+        )?;
+        let row = match rows.iter().next() {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+        let schema: String = row.get("schema").expect("must have a schema");
+        let name: String = row.get("name").expect("must have a view name");
+        let definition: String = row.get("definition").expect("must have a definition");
+        let is_updatable: String = row.get("is_updatable").expect("must have is_updatable");
+
+        // VIEW_TABLE_USAGE is only available in mysql 8.0.13 onwards
+        let dependencies = if has_information_schema_table(self, "VIEW_TABLE_USAGE")? {
+            self.execute_sql_with_return(
+                r#"
+                SELECT TABLE_SCHEMA AS `schema`, TABLE_NAME AS name
+                  FROM INFORMATION_SCHEMA.VIEW_TABLE_USAGE
+                 WHERE VIEW_SCHEMA = ? AND VIEW_NAME = ?
+              ORDER BY TABLE_SCHEMA, TABLE_NAME"#,
+                &[&schema.to_value(), &name.to_value()],
+            )?
+            .iter()
+            .map(|row| TableName {
+                name: row.get("name").expect("must have a table name"),
+                schema: row.get_opt("schema").expect("must not error"),
+                catalog: None,
+                alias: None,
+            })
+            .collect()
+        } else {
+            vec![]
+        };
+
+        Ok(Some(ViewDef {
+            name: TableName {
+                name,
+                schema: Some(schema),
+                catalog: None,
+                alias: None,
+            },
+            definition,
+            is_updatable: is_updatable == "YES",
+            is_materialized: false,
+            dependencies,
+        }))
+    }
+
+    fn get_triggers(&mut self, table_name: &TableName) -> Result<Vec<Trigger>, DbError> {
+        let schema: Value = table_name
+            .schema
+            .as_ref()
+            .map(String::as_str)
+            .unwrap_or("__DUMMY__")
+            .into();
+        let name: Value = table_name.name.clone().into();
+        let rows = self.execute_sql_with_return(
+            r#"
+            SELECT TRIGGER_NAME AS name,
+                   ACTION_TIMING AS timing,
+                   EVENT_MANIPULATION AS event,
+                   ACTION_ORIENTATION AS orientation,
+                   ACTION_STATEMENT AS action
+              FROM INFORMATION_SCHEMA.TRIGGERS
+             WHERE EVENT_OBJECT_SCHEMA = CASE ? WHEN '__DUMMY__' THEN DATABASE() ELSE ? END
+               AND EVENT_OBJECT_TABLE = ?
+          ORDER BY TRIGGER_NAME"#,
+            &[&schema, &schema, &name],
+        )?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let timing: String = row.get("timing").expect("must have a timing");
+                let event: String = row.get("event").expect("must have an event");
+                let orientation: String = row.get("orientation").expect("must have orientation");
+                let timing = match &*timing {
+                    "BEFORE" => TriggerTiming::Before,
+                    _ => TriggerTiming::After,
+                };
+                // mysql triggers are fired by exactly one event
+                let events = match &*event {
+                    "INSERT" => vec![TriggerEvent::Insert],
+                    "UPDATE" => vec![TriggerEvent::Update],
+                    "DELETE" => vec![TriggerEvent::Delete],
+                    _ => vec![],
+                };
+                Trigger {
+                    name: row.get("name").expect("must have a trigger name"),
+                    table: table_name.clone(),
+                    timing,
+                    events,
+                    for_each_row: orientation == "ROW",
+                    action: row.get("action").expect("must have an action"),
+                    definition: None,
+                }
+            })
+            .collect())
+    }
+
+    /// mysql doesn't keep a schema version, the number of columns and the last time a table
+    /// is created or rebuilt by an ALTER TABLE is used instead
+    fn get_schema_version(&mut self) -> Result<Option<String>, DbError> {
+        let sql = r#"
+            SELECT CONCAT(
+                (SELECT COUNT(*) FROM INFORMATION_SCHEMA.COLUMNS WHERE TABLE_SCHEMA = DATABASE()),
+                ':',
+                (SELECT COALESCE(MAX(CREATE_TIME), '') FROM INFORMATION_SCHEMA.TABLES
+                  WHERE TABLE_SCHEMA = DATABASE())
+            ) AS schema_version"#;
+        let rows = self.execute_sql_with_return(sql, &[])?;
+        Ok(rows
+            .iter()
+            .next()
+            .and_then(|row| row.get_opt("schema_version").expect("schema_version")))
+    }
+
+    fn refresh_materialized_view(
+        &mut self,
+        _view_name: &TableName,
+        _concurrently: bool,
+    ) -> Result<(), DbError> {
+        Err(DbError::UnsupportedOperation(
+            "mysql doesn't have materialized views".to_string(),
+        ))
+    }
+
+    fn get_routines(&mut self) -> Result<Vec<Routine>, DbError> {
+        get_scoped_routines(&mut *self, None)
+    }
+
+    fn get_routine(&mut self, routine_name: &TableName) -> Result<Vec<Routine>, DbError> {
+        get_scoped_routines(&mut *self, Some(routine_name))
+    }
+
+    /// functions are called with `SELECT`, procedures with `CALL` where the INOUT and OUT
+    /// arguments are passed as user variables which are then selected for their values
+    fn call_routine(
+        &mut self,
+        routine: &Routine,
+        args: &[&Value],
+    ) -> Result<RoutineResult, DbError> {
+        if routine.in_arguments().len() != args.len() {
+            return Err(DbError::DataError(DataError::RoutineNotFound(
+                routine.name.complete_name(),
+                args.len(),
+            )));
+        }
+        let mut placeholders = vec![];
+        let mut params = vec![];
+        let mut out_variables = vec![];
+        let mut values = args.iter();
+        for (i, arg) in routine.arguments.iter().enumerate() {
+            let variable = format!("@rustorm_arg{}", i + 1);
+            let name = match arg.name {
+                Some(ref name) => name.to_string(),
+                None => format!("arg{}", i + 1),
+            };
+            match arg.mode {
+                ArgumentMode::InOut => {
+                    let value = values.next().expect("must have a value for the argument");
+                    self.execute_sql_with_return(&format!("SET {} = ?", variable), &[*value])?;
+                    placeholders.push(variable.to_string());
+                    out_variables.push(format!(
+                        "{} AS {}",
+                        variable,
+                        Dialect::Mysql.quote_identifier(&name)?
+                    ));
+                }
+                ArgumentMode::Out | ArgumentMode::Table => {
+                    placeholders.push(variable.to_string());
+                    out_variables.push(format!(
+                        "{} AS {}",
+                        variable,
+                        Dialect::Mysql.quote_identifier(&name)?
+                    ));
+                }
+                ArgumentMode::In | ArgumentMode::Variadic => {
+                    params.push(*values.next().expect("must have a value for the argument"));
+                    placeholders.push("?".to_string());
+                }
+            }
+        }
+        let name = routine.name.quoted_complete_name(Dialect::Mysql)?;
+        match routine.kind {
+            RoutineKind::Procedure => {
+                let sql = format!("CALL {}({})", name, placeholders.join(", "));
+                let result_sets = if params.is_empty() {
+                    let rows = self
+                        .0
+                        .query(&sql)
+                        .map_err(|e| MysqlError::SqlError(e, sql.to_string()))?;
+                    collect_result_sets(rows)?
+                } else {
+                    let mut stmt = self
+                        .0
+                        .prepare(&sql)
+                        .map_err(|e| MysqlError::SqlError(e, sql.to_string()))?;
+                    let rows = stmt
+                        .execute(&to_my_params(&params)?)
+                        .map_err(|e| MysqlError::SqlError(e, sql.to_string()))?;
+                    collect_result_sets(rows)?
+                };
+                let out_params = if out_variables.is_empty() {
+                    Dao::new()
+                } else {
+                    let sql = format!("SELECT {}", out_variables.join(", "));
+                    let rows = self.execute_sql_with_return(&sql, &[])?;
+                    rows.iter().next().unwrap_or_default()
+                };
+                Ok(RoutineResult {
+                    out_params,
+                    result_sets,
+                })
+            }
+            _ => {
+                let sql = format!(
+                    "SELECT {}({}) AS {}",
+                    name,
+                    placeholders.join(", "),
+                    Dialect::Mysql.quote_identifier(&routine.name.name)?
+                );
+                let rows = self.execute_sql_with_return(&sql, &params)?;
+                Ok(RoutineResult {
+                    out_params: Dao::new(),
+                    result_sets: vec![rows],
+                })
+            }
+        }
+    }
+}
+
+fn to_my_params(values: &[&Value]) -> Result<mysql::Params, DbError> {
+    let values = values
+        .iter()
+        .map(|v| MyValue(v).to_value())
+        .collect::<Result<Vec<_>, DbError>>()?;
+    Ok(values.into())
+}
+
+/// collect each of the result sets returned by a stored procedure into its own rows,
+/// the last result set is the status of the call which has no columns and is skipped
+fn collect_result_sets(mut rows: mysql::QueryResult) -> Result<Vec<Rows>, DbError> {
+    let mut result_sets = vec![];
+    while rows.more_results_exists() {
+        let column_types: Vec<_> = rows.columns_ref().iter().map(|c| c.column_type()).collect();
+        let column_names = rows
+            .columns_ref()
+            .iter()
+            .map(|c| std::str::from_utf8(c.name_ref()).map(ToString::to_string))
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(MysqlError::Utf8Error)?;
+        let mut records = Rows::new(column_names);
+        for r in rows.by_ref() {
+            records.push(into_record(r.map_err(MysqlError::from)?, &column_types)?);
+        }
+        if !records.columns.is_empty() {
+            result_sets.push(records);
+        }
+    }
+    Ok(result_sets)
+}
+
+/// get the stored functions and procedures of the current database, or only the ones with
+/// this name, the arguments of all the routines are loaded in a single query
+fn get_scoped_routines(
+    db: &mut dyn Database,
+    routine_name: Option<&TableName>,
+) -> Result<Vec<Routine>, DbError> {
+    let params = scope_params(routine_name);
+    let params: Vec<&Value> = params.iter().collect();
+
+    // the ordinal position 0 is the returned value of a function
+    let sql = format!(
+        r#"
+        SELECT SPECIFIC_NAME AS routine_name,
+               ROUTINE_TYPE AS routine_type,
+               PARAMETER_MODE AS mode,
+               PARAMETER_NAME AS name,
+               DTD_IDENTIFIER AS data_type
+          FROM INFORMATION_SCHEMA.PARAMETERS
+         WHERE {}
+           AND ORDINAL_POSITION > 0
+      ORDER BY SPECIFIC_NAME, ORDINAL_POSITION"#,
+        scope_condition("SPECIFIC_SCHEMA", "SPECIFIC_NAME")
+    );
+    let mut arguments: HashMap<(String, String), Vec<RoutineArgument>> = HashMap::new();
+    for row in db.execute_sql_with_return(&sql, &params)?.iter() {
+        let routine_name: String = row.get("routine_name").expect("routine_name");
+        let routine_type: String = row.get("routine_type").expect("routine_type");
+        let mode: Option<String> = row.get_opt("mode").expect("mode");
+        let argument = RoutineArgument {
+            name: row.get_opt("name").expect("name"),
+            mode: match mode.as_deref() {
+                Some("OUT") => ArgumentMode::Out,
+                Some("INOUT") => ArgumentMode::InOut,
+                _ => ArgumentMode::In,
+            },
+            data_type: row.get("data_type").expect("data_type"),
+        };
+        arguments
+            .entry((routine_name, routine_type))
+            .or_default()
+            .push(argument);
+    }
+
+    let sql = format!(
+        r#"
+        SELECT ROUTINE_SCHEMA AS routine_schema,
+               ROUTINE_NAME AS routine_name,
+               ROUTINE_TYPE AS routine_type,
+               DTD_IDENTIFIER AS return_type
+          FROM INFORMATION_SCHEMA.ROUTINES
+         WHERE {}
+      ORDER BY ROUTINE_NAME, ROUTINE_TYPE"#,
+        scope_condition("ROUTINE_SCHEMA", "ROUTINE_NAME")
+    );
+    Ok(db
+        .execute_sql_with_return(&sql, &params)?
+        .iter()
+        .map(|row| {
+            let name: String = row.get("routine_name").expect("routine_name");
+            let routine_type: String = row.get("routine_type").expect("routine_type");
+            let kind = if routine_type == "PROCEDURE" {
+                RoutineKind::Procedure
+            } else {
+                RoutineKind::Function
+            };
+            Routine {
+                name: TableName {
+                    name: name.to_string(),
+                    schema: Some(row.get("routine_schema").expect("routine_schema")),
+                    catalog: None,
+                    alias: None,
+                },
+                kind,
+                arguments: arguments.remove(&(name, routine_type)).unwrap_or_default(),
+                return_type: row.get_opt("return_type").expect("return_type"),
+                returns_set: false,
+            }
+        })
+        .collect())
+}
+
+/// The condition restricting the INFORMATION_SCHEMA queries to the tables of a schema,
+/// which defaults to the current database, and optionally to a single table.
+/// The parameters are supplied by `scope_params`.
+fn scope_condition(schema_column: &str, table_column: &str) -> String {
+    format!(
+        "{} = CASE ? WHEN '__DUMMY__' THEN DATABASE() ELSE ? END AND (? IS NULL OR {} = ?)",
+        schema_column, table_column
+    )
+}
+
+fn scope_params(table_name: Option<&TableName>) -> Vec<Value> {
+    let schema = table_name
+        .and_then(|table_name| table_name.schema.as_ref())
+        .map(String::as_str)
+        .unwrap_or("__DUMMY__")
+        .to_value();
+    let name = match table_name {
+        Some(table_name) => table_name.name.to_value(),
+        None => Value::Nil,
+    };
+    vec![schema.clone(), schema, name.clone(), name]
+}
+
+/// Load the tables of the current database, or only the named table.
+/// The columns, keys, indexes and column statistics of all the tables are each loaded
+/// in a single query and then assembled by table name.
+fn get_scoped_tables(
+    db: &mut MysqlDB,
+    table_name: Option<&TableName>,
+) -> Result<Vec<TableDef>, DbError> {
+    #[derive(Debug, FromDao)]
+    struct TableSpec {
+        schema: String,
+        name: String,
+        comment: String,
+        is_view: i32,
+    }
+
+    let params = scope_params(table_name);
+    let params: Vec<&Value> = params.iter().collect();
+    let sql = format!(
+        r#"
+                SELECT TABLE_SCHEMA AS `schema`,
+                       TABLE_NAME AS name,
+                       TABLE_COMMENT AS comment,
+                       CASE TABLE_TYPE WHEN 'VIEW' THEN TRUE ELSE FALSE END AS is_view
+                  FROM INFORMATION_SCHEMA.TABLES
+                 WHERE {}
+              ORDER BY TABLE_NAME"#,
+        scope_condition("TABLE_SCHEMA", "TABLE_NAME")
+    );
+    let tables: Vec<TableSpec> = db
+        .execute_sql_with_return(&sql, &params)?
+        .iter()
+        .map(|dao| FromDao::from_dao(&dao))
+        .collect();
+
+    let mut table_columns = get_scoped_columns(db, &params)?;
+    let mut column_stats = get_column_stats(db, &params)?;
+    let mut table_keys = get_scoped_table_keys(db, &params, &table_columns)?;
+    let mut indexes = get_scoped_indexes(db, &params)?;
+
+    Ok(tables
+        .into_iter()
+        .map(|table_spec| {
+            let mut columns = table_columns.remove(&table_spec.name).unwrap_or_default();
+            for (column_name, stat) in column_stats.remove(&table_spec.name).unwrap_or_default() {
+                if let Some(column) = columns.iter_mut().find(|c| c.name.name == column_name) {
+                    column.stat = Some(stat);
+                }
+            }
+            TableDef {
+                table_key: table_keys.remove(&table_spec.name).unwrap_or_default(),
+                indexes: indexes.remove(&table_spec.name).unwrap_or_default(),
+                name: TableName {
+                    name: table_spec.name,
+                    schema: Some(table_spec.schema),
+                    catalog: None,
+                    alias: None,
+                },
+                comment: Some(table_spec.comment),
+                columns,
+                is_view: table_spec.is_view == 1,
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, FromDao)]
+struct ColumnSpec {
+    schema: String,
+    table_name: String,
+    name: String,
+    comment: String,
+    type_: String,
+}
+
+impl ColumnSpec {
+    fn to_column(&self) -> ColumnDef {
+        let (sql_type, capacity) =
+            if self.type_.starts_with("enum(") || self.type_.starts_with("set(") {
+                let start = self.type_.find('(');
+                let end = self.type_.find(')');
+                if let (Some(start), Some(end)) = (start, end) {
+                    let dtype = &self.type_[0..start];
+                    let range = &self.type_[start + 1..end];
+                    let choices = range
+                        .split(',')
+                        .map(|v| v.to_owned())
+                        .collect::<Vec<String>>();
+
+                    match dtype {
+                        "enum" => (SqlType::Enum(dtype.to_owned(), choices), None),
+                        "set" => (SqlType::Enum(dtype.to_owned(), choices), None),
+                        _ => panic!("not yet handled: {}", dtype),
+                    }
+                } else {
+                    panic!("not yet handled spec_type: {:?}", self.type_)
+                }
+            } else {
+                let (dtype, capacity) = common::extract_datatype_with_capacity(&self.type_);
+                let sql_type = match &*dtype {
+                    "tinyint" | "tinyint unsigned" => SqlType::Tinyint,
+                    "smallint" | "smallint unsigned" | "year" => SqlType::Smallint,
+                    "mediumint" | "mediumint unsigned" => SqlType::Int,
+                    "int" | "int unsigned" => SqlType::Int,
+                    "bigint" | "bigin unsigned" => SqlType::Bigint,
+                    "float" | "float unsigned" => SqlType::Float,
+                    "double" | "double unsigned" => SqlType::Double,
+                    "decimal" => SqlType::Numeric,
+                    "tinyblob" => SqlType::Tinyblob,
+                    "mediumblob" => SqlType::Mediumblob,
+                    "blob" => SqlType::Blob,
+                    "longblob" => SqlType::Longblob,
+                    "binary" | "varbinary" => SqlType::Varbinary,
+                    "char" => SqlType::Char,
+                    "varchar" => SqlType::Varchar,
+                    "tinytext" => SqlType::Tinytext,
+                    "mediumtext" => SqlType::Mediumtext,
+                    "text" | "longtext" => SqlType::Text,
+                    "date" => SqlType::Date,
+                    "datetime" | "timestamp" => SqlType::Timestamp,
+                    "time" => SqlType::Time,
+                    _ => panic!("not yet handled: {}", dtype),
+                };
+
+                (sql_type, capacity)
+            };
+
+        ColumnDef {
+            table: TableName::from(&format!("{}.{}", self.schema, self.table_name)),
+            name: ColumnName::from(&self.name),
+            comment: Some(self.comment.to_string()),
+            specification: column::ColumnSpecification {
+                capacity,
+                // TODO: implementation
+                constraints: vec![],
+                sql_type,
+            },
+            stat: None,
+        }
+    }
+}
+
+/// get the columns of the tables in the scope, grouped by table name
+fn get_scoped_columns(
+    db: &mut dyn Database,
+    params: &[&Value],
+) -> Result<HashMap<String, Vec<ColumnDef>>, DbError> {
+    let sql = format!(
+        r#"
+                SELECT TABLE_SCHEMA AS `schema`,
+                       TABLE_NAME AS table_name,
+                       COLUMN_NAME AS name,
+                       COLUMN_COMMENT AS comment,
+                       CAST(COLUMN_TYPE as CHAR(255)) AS type_
+                  FROM INFORMATION_SCHEMA.COLUMNS
+                 WHERE {}
+              ORDER BY TABLE_NAME, ORDINAL_POSITION"#,
+        scope_condition("TABLE_SCHEMA", "TABLE_NAME")
+    );
+    let mut columns: HashMap<String, Vec<ColumnDef>> = HashMap::new();
+    for dao in db.execute_sql_with_return(&sql, params)?.iter() {
+        let spec: ColumnSpec = FromDao::from_dao(&dao);
+        columns
+            .entry(spec.table_name.to_string())
+            .or_default()
+            .push(spec.to_column());
+    }
+    Ok(columns)
 }
 
 fn get_table_names(db: &mut dyn Database, kind: &str) -> Result<Vec<TableName>, DbError> {
@@ -380,12 +975,352 @@ fn get_table_names(db: &mut dyn Database, kind: &str) -> Result<Vec<TableName>,
     Ok(table_names)
 }
 
+/// the error code of mysql for a table which does not exist in `INFORMATION_SCHEMA`
+const ER_UNKNOWN_TABLE: u16 = 1109;
+
+/// whether the table exists in `INFORMATION_SCHEMA`, the tables added in the later versions are
+/// unknown to the older servers and to mariadb. The other errors are returned
+fn has_information_schema_table(db: &mut MysqlDB, table: &str) -> Result<bool, DbError> {
+    let probe = format!("SELECT 1 FROM INFORMATION_SCHEMA.{} LIMIT 0", table);
+    match db.0.query(&probe) {
+        Ok(_) => Ok(true),
+        Err(mysql::Error::MySqlError(ref e)) if e.code == ER_UNKNOWN_TABLE => Ok(false),
+        Err(e) => Err(MysqlError::SqlError(e, probe).into()),
+    }
+}
+
+/// get the column statistics from the histograms created with
+/// `ANALYZE TABLE .. UPDATE HISTOGRAM ON ..`, which is only available in mysql 8.0 onwards,
+/// grouped by table name
+fn get_column_stats(
+    db: &mut MysqlDB,
+    params: &[&Value],
+) -> Result<HashMap<String, Vec<(String, ColumnStat)>>, DbError> {
+    // COLUMN_STATISTICS is unknown in mysql before 8.0 and in mariadb
+    let probe = "SELECT 1 FROM INFORMATION_SCHEMA.COLUMN_STATISTICS LIMIT 0";
+    match db.0.query(probe) {
+        Ok(_) => (),
+        Err(mysql::Error::MySqlError(ref e)) if e.code == ER_UNKNOWN_TABLE => {
+            return Ok(HashMap::new())
+        }
+        Err(e) => return Err(MysqlError::SqlError(e, probe.to_string()).into()),
+    }
+    let sql = format!(
+        r#"
+        SELECT TABLE_NAME AS table_name,
+               COLUMN_NAME AS column_name,
+               CAST(HISTOGRAM AS CHAR) AS histogram
+          FROM INFORMATION_SCHEMA.COLUMN_STATISTICS
+         WHERE {}"#,
+        scope_condition("SCHEMA_NAME", "TABLE_NAME")
+    );
+    let mut stats: HashMap<String, Vec<(String, ColumnStat)>> = HashMap::new();
+    for row in db.execute_sql_with_return(&sql, params)?.iter() {
+        let table_name: String = row.get("table_name").expect("table_name");
+        let column_name: String = row.get("column_name").expect("column_name");
+        let histogram: String = row.get("histogram").expect("histogram");
+        let stat = serde_json::from_str(&histogram)
+            .ok()
+            .and_then(|histogram| histogram_to_stat(&histogram));
+        if let Some(stat) = stat {
+            stats
+                .entry(table_name)
+                .or_default()
+                .push((column_name, stat));
+        }
+    }
+    Ok(stats)
+}
+
+/// convert the histogram json to column stat.
+/// The buckets of a singleton histogram are `[value, cumulative frequency]`,
+/// while the buckets of an equi-height histogram are
+/// `[lower bound, upper bound, cumulative frequency, number of distinct values]`
+fn histogram_to_stat(histogram: &serde_json::Value) -> Option<ColumnStat> {
+    let null_fraction = histogram["null-values"].as_f64().map(|f| f as f32);
+    let buckets = histogram["buckets"].as_array()?;
+    match histogram["histogram-type"].as_str()? {
+        "singleton" => {
+            let mut previous = 0.0;
+            let mut most_common_values = Vec::with_capacity(buckets.len());
+            for bucket in buckets {
+                let cumulative = bucket[1].as_f64()?;
+                most_common_values.push(MostCommonValue {
+                    value: histogram_value(&bucket[0]),
+                    frequency: (cumulative - previous) as f32,
+                });
+                previous = cumulative;
+            }
+            most_common_values.sort_by(|a, b| {
+                b.frequency
+                    .partial_cmp(&a.frequency)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+            Some(ColumnStat {
+                avg_width: None,
+                n_distinct: most_common_values.len() as f32,
+                null_fraction,
+                most_common_values,
+                histogram_bounds: vec![],
+            })
+        }
+        "equi-height" => {
+            let mut n_distinct = 0.0;
+            let mut histogram_bounds = Vec::with_capacity(buckets.len() + 1);
+            for (i, bucket) in buckets.iter().enumerate() {
+                if i == 0 {
+                    histogram_bounds.push(histogram_value(&bucket[0]));
+                }
+                histogram_bounds.push(histogram_value(&bucket[1]));
+                n_distinct += bucket[3].as_f64()?;
+            }
+            Some(ColumnStat {
+                avg_width: None,
+                n_distinct: n_distinct as f32,
+                null_fraction,
+                most_common_values: vec![],
+                histogram_bounds,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// the numbers are stored as json numbers while the strings are encoded as `base64:type254:YWJj`
+fn histogram_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) if s.starts_with("base64:") => {
+            match s.splitn(3, ':').nth(2).map(base64::decode) {
+                Some(Ok(bytes)) => String::from_utf8_lossy(&bytes).into_owned(),
+                _ => s.to_string(),
+            }
+        }
+        serde_json::Value::String(s) => s.to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// get the primary key, unique keys, foreign keys and check constraints of the tables in the scope,
+/// grouped by table name
+fn get_scoped_table_keys(
+    db: &mut MysqlDB,
+    params: &[&Value],
+    table_columns: &HashMap<String, Vec<ColumnDef>>,
+) -> Result<HashMap<String, Vec<TableKey>>, DbError> {
+    struct KeyColumnSimple {
+        table_name: String,
+        name: String,
+        kind: String,
+        column_name: String,
+        foreign_schema: Option<String>,
+        foreign_table: Option<String>,
+        referred_column: Option<String>,
+        on_update: Option<String>,
+        on_delete: Option<String>,
+        match_type: Option<String>,
+    }
+    let sql = format!(
+        r#"
+        SELECT tc.TABLE_NAME AS table_name,
+               tc.CONSTRAINT_NAME AS name,
+               tc.CONSTRAINT_TYPE AS kind,
+               kcu.COLUMN_NAME AS column_name,
+               kcu.REFERENCED_TABLE_SCHEMA AS foreign_schema,
+               kcu.REFERENCED_TABLE_NAME AS foreign_table,
+               kcu.REFERENCED_COLUMN_NAME AS referred_column,
+               rc.UPDATE_RULE AS on_update,
+               rc.DELETE_RULE AS on_delete,
+               rc.MATCH_OPTION AS match_type
+          FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS AS tc
+          JOIN INFORMATION_SCHEMA.KEY_COLUMN_USAGE AS kcu
+            ON kcu.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA
+           AND kcu.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
+           AND kcu.TABLE_NAME = tc.TABLE_NAME
+     LEFT JOIN INFORMATION_SCHEMA.REFERENTIAL_CONSTRAINTS AS rc
+            ON rc.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA
+           AND rc.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
+           AND rc.TABLE_NAME = tc.TABLE_NAME
+         WHERE {}
+           AND tc.CONSTRAINT_TYPE IN ('PRIMARY KEY', 'UNIQUE', 'FOREIGN KEY')
+      ORDER BY tc.TABLE_NAME,
+               FIELD(tc.CONSTRAINT_TYPE, 'PRIMARY KEY', 'UNIQUE', 'FOREIGN KEY'),
+               tc.CONSTRAINT_NAME, kcu.ORDINAL_POSITION"#,
+        scope_condition("tc.TABLE_SCHEMA", "tc.TABLE_NAME")
+    );
+    let key_columns: Vec<KeyColumnSimple> = db
+        .execute_sql_with_return(&sql, params)?
+        .iter()
+        .map(|row| KeyColumnSimple {
+            table_name: row.get("table_name").expect("must have a table name"),
+            name: row.get("name").expect("must have a constraint name"),
+            kind: row.get("kind").expect("must have a constraint type"),
+            column_name: row.get("column_name").expect("must have a column name"),
+            foreign_schema: row.get_opt("foreign_schema").expect("must not error"),
+            foreign_table: row.get_opt("foreign_table").expect("must not error"),
+            referred_column: row.get_opt("referred_column").expect("must not error"),
+            on_update: row.get_opt("on_update").expect("must not error"),
+            on_delete: row.get_opt("on_delete").expect("must not error"),
+            match_type: row.get_opt("match_type").expect("must not error"),
+        })
+        .collect();
+
+    let mut table_keys: HashMap<String, Vec<TableKey>> = HashMap::new();
+    let mut rest = &key_columns[..];
+    while let Some(first) = rest.first() {
+        let count = rest
+            .iter()
+            .take_while(|k| k.table_name == first.table_name && k.name == first.name)
+            .count();
+        let (group, remaining) = rest.split_at(count);
+        rest = remaining;
+        let columns: Vec<ColumnName> = group
+            .iter()
+            .map(|k| ColumnName::from(&k.column_name))
+            .collect();
+        let table_key = match &*first.kind {
+            "PRIMARY KEY" => TableKey::PrimaryKey(Key {
+                name: Some(first.name.to_string()),
+                columns,
+            }),
+            "UNIQUE" => TableKey::UniqueKey(Key {
+                name: Some(first.name.to_string()),
+                columns,
+            }),
+            _ => TableKey::ForeignKey(ForeignKey {
+                name: Some(first.name.to_string()),
+                columns,
+                foreign_table: TableName {
+                    name: first.foreign_table.clone().unwrap_or_default(),
+                    schema: first.foreign_schema.clone(),
+                    catalog: None,
+                    alias: None,
+                },
+                referred_columns: group
+                    .iter()
+                    .filter_map(|k| k.referred_column.as_ref())
+                    .map(|column| ColumnName::from(column))
+                    .collect(),
+                on_delete: ReferentialAction::from_sql(
+                    first.on_delete.as_ref().map(String::as_str).unwrap_or(""),
+                ),
+                on_update: ReferentialAction::from_sql(
+                    first.on_update.as_ref().map(String::as_str).unwrap_or(""),
+                ),
+                match_type: MatchType::from_sql(
+                    first.match_type.as_ref().map(String::as_str).unwrap_or(""),
+                ),
+                // mysql checks the foreign keys immediately
+                is_deferrable: false,
+                is_initially_deferred: false,
+            }),
+        };
+        table_keys
+            .entry(first.table_name.to_string())
+            .or_default()
+            .push(table_key);
+    }
+
+    // CHECK_CONSTRAINTS is only available in mysql 8.0.16 onwards
+    if !has_information_schema_table(db, "CHECK_CONSTRAINTS")? {
+        return Ok(table_keys);
+    }
+    let sql = format!(
+        r#"
+            SELECT tc.TABLE_NAME AS table_name,
+                   cc.CONSTRAINT_NAME AS name,
+                   cc.CHECK_CLAUSE AS expression
+              FROM INFORMATION_SCHEMA.TABLE_CONSTRAINTS AS tc
+              JOIN INFORMATION_SCHEMA.CHECK_CONSTRAINTS AS cc
+                ON cc.CONSTRAINT_SCHEMA = tc.CONSTRAINT_SCHEMA
+               AND cc.CONSTRAINT_NAME = tc.CONSTRAINT_NAME
+             WHERE {}
+               AND tc.CONSTRAINT_TYPE = 'CHECK'
+          ORDER BY tc.TABLE_NAME, cc.CONSTRAINT_NAME"#,
+        scope_condition("tc.TABLE_SCHEMA", "tc.TABLE_NAME")
+    );
+    let rows = db.execute_sql_with_return(&sql, params)?;
+    for row in rows.iter() {
+        let table_name: String = row.get("table_name").expect("must have a table name");
+        let expression: String = row.get("expression").expect("must have a clause");
+        let expression = util::trim_enclosing_parenthesis(&expression).to_string();
+        let used = util::identifiers(&expression);
+        let columns = table_columns
+            .get(&table_name)
+            .map(|columns| {
+                columns
+                    .iter()
+                    .map(|c| &c.name)
+                    .filter(|c| used.iter().any(|w| w.eq_ignore_ascii_case(&c.name)))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let check = Check {
+            name: row.get_opt("name").expect("must not error"),
+            columns,
+            expression,
+        };
+        table_keys
+            .entry(table_name)
+            .or_default()
+            .push(TableKey::Check(check));
+    }
+    Ok(table_keys)
+}
+
+/// get the indexes of the tables in the scope from `INFORMATION_SCHEMA.STATISTICS`,
+/// grouped by table name.
+/// All the columns are selected since `EXPRESSION` is only available in mysql 8.0.13 onwards
+fn get_scoped_indexes(
+    db: &mut dyn Database,
+    params: &[&Value],
+) -> Result<HashMap<String, Vec<Index>>, DbError> {
+    let sql = format!(
+        r#"
+        SELECT *
+          FROM INFORMATION_SCHEMA.STATISTICS
+         WHERE {}
+      ORDER BY TABLE_NAME, INDEX_NAME, SEQ_IN_INDEX"#,
+        scope_condition("TABLE_SCHEMA", "TABLE_NAME")
+    );
+    let rows = db.execute_sql_with_return(&sql, params)?;
+    let mut indexes: HashMap<String, Vec<Index>> = HashMap::new();
+    for row in rows.iter() {
+        let table_name: String = row.get("TABLE_NAME").expect("must have a table name");
+        let name: String = row.get("INDEX_NAME").expect("must have an index name");
+        let non_unique: i64 = row.get("NON_UNIQUE").expect("must have non_unique");
+        let index_type: Option<String> = row.get_opt("INDEX_TYPE").expect("must not error");
+        let column: Option<String> = row.get_opt("COLUMN_NAME").expect("must not error");
+        let expression: Option<String> = row.get_opt("EXPRESSION").expect("must not error");
+        let part = match column {
+            Some(column) => IndexColumn::Column(ColumnName::from(&column)),
+            None => IndexColumn::Expression(expression.unwrap_or_default()),
+        };
+        let table_indexes = indexes.entry(table_name).or_default();
+        match table_indexes.last_mut() {
+            Some(index) if index.name == name => index.columns.push(part),
+            _ => table_indexes.push(Index {
+                is_primary: name == "PRIMARY",
+                name,
+                columns: vec![part],
+                is_unique: non_unique == 0,
+                method: index_type.map(|t| t.to_lowercase()),
+                // mysql doesn't have partial indexes
+                predicate: None,
+                included_columns: vec![],
+            }),
+        }
+    }
+    Ok(indexes)
+}
+
 #[derive(Debug)]
 pub struct MyValue<'a>(&'a Value);
 
-impl mysql::prelude::ToValue for MyValue<'_> {
-    fn to_value(&self) -> mysql::Value {
-        match self.0 {
+impl MyValue<'_> {
+    /// the value for the mysql driver, the types which can not be stored in mysql are an error
+    pub fn to_value(&self) -> Result<mysql::Value, DbError> {
+        let value = match self.0 {
             Value::Bool(ref v) => v.into(),
             Value::Tinyint(ref v) => v.into(),
             Value::Smallint(ref v) => v.into(),
@@ -401,12 +1336,19 @@ impl mysql::prelude::ToValue for MyValue<'_> {
             Value::Timestamp(ref v) => v.naive_utc().into(),
             Value::DateTime(ref v) => v.into(),
             Value::Time(ref v) => v.into(),
-            Value::Interval(ref _v) => panic!("storing interval in DB is not supported"),
             Value::Json(ref v) => v.into(),
             Value::Nil => mysql::Value::NULL,
-            Value::BigDecimal(_) => unimplemented!("we need to upgrade bigdecimal crate"),
-            Value::Point(_) | Value::Array(_) => unimplemented!("unsupported type"),
-        }
+            Value::Interval(_)
+            | Value::BigDecimal(_)
+            | Value::Point(_)
+            | Value::Array(_)
+            | Value::Composite(_) => {
+                return Err(DbError::ConvertError(ConvertError::UnsupportedDataType(
+                    format!("{:?}", self.0),
+                )))
+            }
+        };
+        Ok(value)
     }
 }
 
@@ -414,8 +1356,6 @@ fn into_record(
     mut row: mysql::Row,
     column_types: &[mysql::consts::ColumnType],
 ) -> Result<Vec<Value>, MysqlError> {
-    use mysql::{consts::ColumnType, from_value_opt as fvo};
-
     column_types
         .iter()
         .enumerate()
@@ -424,62 +1364,67 @@ fn into_record(
                 .take_opt(i)
                 .unwrap_or_else(|| unreachable!("column length does not enough"))
                 .unwrap_or_else(|_| unreachable!("could not convert as `mysql::Value`"));
-
-            if cell == mysql::Value::NULL {
-                return Ok(Value::Nil);
-            }
-
-            match column_type {
-                ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => fvo(cell)
-                    .and_then(|v: Vec<u8>| {
-                        bigdecimal::BigDecimal::parse_bytes(&v, 10)
-                            .ok_or(mysql::FromValueError(mysql::Value::Bytes(v)))
-                    })
-                    .map(Value::BigDecimal),
-                ColumnType::MYSQL_TYPE_TINY => fvo(cell).map(Value::Tinyint),
-                ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_YEAR => {
-                    fvo(cell).map(Value::Smallint)
-                }
-                ColumnType::MYSQL_TYPE_LONG | ColumnType::MYSQL_TYPE_INT24 => {
-                    fvo(cell).map(Value::Int)
-                }
-                ColumnType::MYSQL_TYPE_LONGLONG => fvo(cell).map(Value::Bigint),
-                ColumnType::MYSQL_TYPE_FLOAT => fvo(cell).map(Value::Float),
-                ColumnType::MYSQL_TYPE_DOUBLE => fvo(cell).map(Value::Double),
-                ColumnType::MYSQL_TYPE_NULL => fvo(cell).map(|_: mysql::Value| Value::Nil),
-                ColumnType::MYSQL_TYPE_TIMESTAMP => fvo(cell).map(|v: chrono::NaiveDateTime| {
-                    Value::Timestamp(chrono::DateTime::from_utc(v, chrono::Utc))
-                }),
-                ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => {
-                    fvo(cell).map(Value::Date)
-                }
-                ColumnType::MYSQL_TYPE_TIME => fvo(cell).map(Value::Time),
-                ColumnType::MYSQL_TYPE_DATETIME => fvo(cell).map(Value::DateTime),
-                ColumnType::MYSQL_TYPE_VARCHAR
-                | ColumnType::MYSQL_TYPE_VAR_STRING
-                | ColumnType::MYSQL_TYPE_STRING => fvo(cell).map(Value::Text),
-                ColumnType::MYSQL_TYPE_JSON => fvo(cell).map(Value::Json),
-                ColumnType::MYSQL_TYPE_TINY_BLOB
-                | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
-                | ColumnType::MYSQL_TYPE_LONG_BLOB
-                | ColumnType::MYSQL_TYPE_BLOB => fvo(cell).map(Value::Blob),
-                ColumnType::MYSQL_TYPE_TIMESTAMP2
-                | ColumnType::MYSQL_TYPE_DATETIME2
-                | ColumnType::MYSQL_TYPE_TIME2 => {
-                    panic!("only used in server side: {:?}", column_type)
-                }
-                ColumnType::MYSQL_TYPE_BIT
-                | ColumnType::MYSQL_TYPE_ENUM
-                | ColumnType::MYSQL_TYPE_SET
-                | ColumnType::MYSQL_TYPE_GEOMETRY => {
-                    panic!("not yet handling this kind: {:?}", column_type)
-                }
-            }
-            .map_err(MysqlError::from)
+            into_value(cell, column_type)
         })
         .collect()
 }
 
+/// convert the value of a cell according to the type of its column
+fn into_value(
+    cell: mysql::Value,
+    column_type: &mysql::consts::ColumnType,
+) -> Result<Value, MysqlError> {
+    use mysql::{consts::ColumnType, from_value_opt as fvo};
+
+    if cell == mysql::Value::NULL {
+        return Ok(Value::Nil);
+    }
+
+    match column_type {
+        ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => fvo(cell)
+            .and_then(|v: Vec<u8>| {
+                bigdecimal::BigDecimal::parse_bytes(&v, 10)
+                    .ok_or(mysql::FromValueError(mysql::Value::Bytes(v)))
+            })
+            .map(Value::BigDecimal),
+        ColumnType::MYSQL_TYPE_TINY => fvo(cell).map(Value::Tinyint),
+        ColumnType::MYSQL_TYPE_SHORT | ColumnType::MYSQL_TYPE_YEAR => {
+            fvo(cell).map(Value::Smallint)
+        }
+        ColumnType::MYSQL_TYPE_LONG | ColumnType::MYSQL_TYPE_INT24 => fvo(cell).map(Value::Int),
+        ColumnType::MYSQL_TYPE_LONGLONG => fvo(cell).map(Value::Bigint),
+        ColumnType::MYSQL_TYPE_FLOAT => fvo(cell).map(Value::Float),
+        ColumnType::MYSQL_TYPE_DOUBLE => fvo(cell).map(Value::Double),
+        ColumnType::MYSQL_TYPE_NULL => fvo(cell).map(|_: mysql::Value| Value::Nil),
+        ColumnType::MYSQL_TYPE_TIMESTAMP => fvo(cell).map(|v: chrono::NaiveDateTime| {
+            Value::Timestamp(chrono::DateTime::from_utc(v, chrono::Utc))
+        }),
+        ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => fvo(cell).map(Value::Date),
+        ColumnType::MYSQL_TYPE_TIME => fvo(cell).map(Value::Time),
+        ColumnType::MYSQL_TYPE_DATETIME => fvo(cell).map(Value::DateTime),
+        ColumnType::MYSQL_TYPE_VARCHAR
+        | ColumnType::MYSQL_TYPE_VAR_STRING
+        | ColumnType::MYSQL_TYPE_STRING => fvo(cell).map(Value::Text),
+        ColumnType::MYSQL_TYPE_JSON => fvo(cell).map(Value::Json),
+        ColumnType::MYSQL_TYPE_TINY_BLOB
+        | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
+        | ColumnType::MYSQL_TYPE_LONG_BLOB
+        | ColumnType::MYSQL_TYPE_BLOB => fvo(cell).map(Value::Blob),
+        ColumnType::MYSQL_TYPE_TIMESTAMP2
+        | ColumnType::MYSQL_TYPE_DATETIME2
+        | ColumnType::MYSQL_TYPE_TIME2 => {
+            panic!("only used in server side: {:?}", column_type)
+        }
+        ColumnType::MYSQL_TYPE_BIT
+        | ColumnType::MYSQL_TYPE_ENUM
+        | ColumnType::MYSQL_TYPE_SET
+        | ColumnType::MYSQL_TYPE_GEOMETRY => {
+            panic!("not yet handling this kind: {:?}", column_type)
+        }
+    }
+    .map_err(MysqlError::from)
+}
+
 #[derive(Debug, Error)]
 pub enum MysqlError {
     #[error("{0}")]
@@ -492,6 +1437,9 @@ pub enum MysqlError {
     ConvertError(#[from] mysql::FromValueError),
     #[error("Pool initialization error: {0}")]
     PoolInitializationError(#[from] r2d2::Error),
+    #[cfg(feature = "with-mysql-async")]
+    #[error("Error executing {1}: {0}")]
+    AsyncSqlError(mysql_async::Error, String),
 }
 
 impl From<mysql::Error> for MysqlError {
@@ -499,3 +1447,18 @@ impl From<mysql::Error> for MysqlError {
         MysqlError::SqlError(e, "Generic Error".into())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mariadb_returning_support() {
+        assert!(is_mariadb_with_returning(
+            "10.5.8-MariaDB-1:10.5.8+maria~focal"
+        ));
+        assert!(is_mariadb_with_returning("11.2.2-MariaDB"));
+        assert!(!is_mariadb_with_returning("10.4.17-MariaDB"));
+        assert!(!is_mariadb_with_returning("8.0.22"));
+    }
+}
@@ -0,0 +1,450 @@
+//! Retrieving the records of a table one page at a time.
+//!
+//! Keyset pagination is used when the sort columns, together with the primary key or a unique key
+//! of the table as the tie breaker, identifies each record. The next page then continues after
+//! the key values of the last record of the previous page, which doesn't slow down on the later
+//! pages and doesn't skip or repeat records when records are inserted in between.
+//! Otherwise it falls back to LIMIT/OFFSET.
+use crate::{
+    common,
+    table::{TableDef, TableKey},
+    ColumnDef, ColumnName, Dao, DataError, DbError, Dialect, EntityManager, Value,
+};
+use rustorm_dao::{FromDao, ToColumnNames, ToTableName};
+use serde::{Deserialize, Serialize};
+
+/// alias used for the key columns which are retrieved for building the next page token
+const KEY_ALIAS: &str = "rustorm_page_key";
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Direction {
+    Asc,
+    Desc,
+}
+
+impl Direction {
+    fn keyword(self) -> &'static str {
+        match self {
+            Direction::Asc => "ASC",
+            Direction::Desc => "DESC",
+        }
+    }
+
+    fn comparison(self) -> &'static str {
+        match self {
+            Direction::Asc => ">",
+            Direction::Desc => "<",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Order {
+    pub column: ColumnName,
+    pub direction: Direction,
+}
+
+impl Order {
+    pub fn asc(column: &str) -> Self {
+        Order {
+            column: ColumnName::from(column),
+            direction: Direction::Asc,
+        }
+    }
+
+    pub fn desc(column: &str) -> Self {
+        Order {
+            column: ColumnName::from(column),
+            direction: Direction::Desc,
+        }
+    }
+}
+
+/// The position where the next page starts
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum PageToken {
+    /// the key values of the last record of the previous page
+    Keyset(Vec<Value>),
+    /// the number of records to skip
+    Offset(usize),
+}
+
+impl PageToken {
+    /// encode this token into a string that can be passed around, such as in a url parameter
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("must serialize page token")
+    }
+
+    pub fn decode(token: &str) -> Result<Self, DbError> {
+        serde_json::from_str(token)
+            .map_err(|_| DbError::DataError(DataError::InvalidPageToken(token.to_string())))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct PageRequest {
+    pub order: Vec<Order>,
+    pub page_size: usize,
+    /// the token of the page to retrieve, None for the first page
+    pub token: Option<PageToken>,
+    /// also count the total number of records in the table
+    pub with_total: bool,
+}
+
+impl PageRequest {
+    pub fn new(page_size: usize) -> Self {
+        PageRequest {
+            order: vec![],
+            page_size,
+            token: None,
+            with_total: false,
+        }
+    }
+
+    pub fn order_by(mut self, order: Order) -> Self {
+        self.order.push(order);
+        self
+    }
+
+    pub fn token(mut self, token: PageToken) -> Self {
+        self.token = Some(token);
+        self
+    }
+
+    pub fn with_total(mut self) -> Self {
+        self.with_total = true;
+        self
+    }
+}
+
+#[derive(Debug)]
+pub struct Page<T> {
+    pub rows: Vec<T>,
+    /// the token for retrieving the next page, None if this is the last page
+    pub next_page: Option<PageToken>,
+    /// the total number of records, when requested
+    pub total: Option<usize>,
+}
+
+impl EntityManager {
+    /// get a page of the records of `T`, sorted by the order in the request
+    pub fn get_page<T>(&mut self, request: &PageRequest) -> Result<Page<T>, DbError>
+    where
+        T: ToTableName + ToColumnNames + FromDao,
+    {
+        if request.page_size == 0 {
+            return Err(DbError::DataError(DataError::InvalidPageSize(
+                request.page_size,
+            )));
+        }
+        let table_name = T::to_table_name();
+        let table = match self.get_table(&table_name)? {
+            Some(table) => table,
+            None => {
+                return Err(DbError::DataError(DataError::TableNameNotFound(
+                    table_name.complete_name(),
+                )))
+            }
+        };
+        for order in request.order.iter() {
+            if find_column(&table, &order.column).is_none() {
+                return Err(DbError::DataError(DataError::ColumnNameNotFound(
+                    table.complete_name(),
+                    order.column.name.to_string(),
+                )));
+            }
+        }
+        let keyset = keyset_order(&table, &request.order);
+        let columns = common::quoted_column_names(&T::to_column_names(), self.0.dialect())?;
+
+        let (rows, next_page) = match (&keyset, &request.token) {
+            (_, Some(PageToken::Offset(offset))) => {
+                self.get_offset_page(&table, &columns, request, *offset)?
+            }
+            (None, None) => self.get_offset_page(&table, &columns, request, 0)?,
+            (Some(keyset), token) => {
+                let after = match token {
+                    Some(PageToken::Keyset(values)) if values.len() == keyset.len() => {
+                        Some(values.as_slice())
+                    }
+                    Some(token) => {
+                        return Err(DbError::DataError(DataError::InvalidPageToken(
+                            token.encode(),
+                        )))
+                    }
+                    None => None,
+                };
+                self.get_keyset_page(&table, &columns, request, keyset, after)?
+            }
+            (None, Some(token)) => {
+                return Err(DbError::DataError(DataError::InvalidPageToken(
+                    token.encode(),
+                )))
+            }
+        };
+        let total = if request.with_total {
+            Some(self.get_total_records(&table.name)?)
+        } else {
+            None
+        };
+        Ok(Page {
+            rows: rows.iter().map(T::from_dao).collect(),
+            next_page,
+            total,
+        })
+    }
+
+    fn get_keyset_page(
+        &mut self,
+        table: &TableDef,
+        columns: &str,
+        request: &PageRequest,
+        keyset: &[Order],
+        after: Option<&[Value]>,
+    ) -> Result<(Vec<Dao>, Option<PageToken>), DbError> {
+        let dialect = self.0.dialect();
+        let key_aliases: Vec<String> = (0..keyset.len())
+            .map(|i| format!("{}_{}", KEY_ALIAS, i))
+            .collect();
+        let key_columns = keyset
+            .iter()
+            .map(|order| order.column.quoted_name(dialect))
+            .collect::<Result<Vec<_>, _>>()?;
+        let select_keys = key_columns
+            .iter()
+            .zip(key_aliases.iter())
+            .map(|(column, alias)| format!("{} AS {}", column, alias))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut sql = format!(
+            "SELECT {}, {} FROM {}",
+            columns,
+            select_keys,
+            table.quoted_complete_name(dialect)?
+        );
+        let mut params: Vec<&Value> = vec![];
+        if let Some(after) = after {
+            // (k1 > v1) OR (k1 = v1 AND k2 > v2) OR ...
+            // expanded instead of the row value comparison since the direction may differ
+            // per column
+            let mut param_index = 0;
+            let mut alternatives = vec![];
+            for (i, order) in keyset.iter().enumerate() {
+                let mut conditions = vec![];
+                for (j, previous) in key_columns[0..i].iter().enumerate() {
+                    param_index += 1;
+                    conditions.push(format!(
+                        "{} = {}",
+                        previous,
+                        self.0.placeholder(param_index)
+                    ));
+                    params.push(&after[j]);
+                }
+                param_index += 1;
+                conditions.push(format!(
+                    "{} {} {}",
+                    key_columns[i],
+                    order.direction.comparison(),
+                    self.0.placeholder(param_index)
+                ));
+                params.push(&after[i]);
+                alternatives.push(format!("({})", conditions.join(" AND ")));
+            }
+            sql += &format!(" WHERE {}", alternatives.join(" OR "));
+        }
+        sql += &format!(
+            " ORDER BY {} LIMIT {}",
+            order_by_clause(keyset, dialect)?,
+            request.page_size + 1
+        );
+
+        let rows = self.0.execute_sql_with_return(&sql, &params)?;
+        let mut records: Vec<Dao> = rows.iter().collect();
+        let has_next = records.len() > request.page_size;
+        records.truncate(request.page_size);
+        let mut next_page = None;
+        for (i, record) in records.iter_mut().enumerate() {
+            let key: Vec<Value> = key_aliases
+                .iter()
+                .map(|alias| record.remove(alias).unwrap_or(Value::Nil))
+                .collect();
+            if has_next && i == request.page_size - 1 {
+                next_page = Some(PageToken::Keyset(key));
+            }
+        }
+        Ok((records, next_page))
+    }
+
+    fn get_offset_page(
+        &mut self,
+        table: &TableDef,
+        columns: &str,
+        request: &PageRequest,
+        offset: usize,
+    ) -> Result<(Vec<Dao>, Option<PageToken>), DbError> {
+        let dialect = self.0.dialect();
+        let mut sql = format!(
+            "SELECT {} FROM {}",
+            columns,
+            table.quoted_complete_name(dialect)?
+        );
+        if !request.order.is_empty() {
+            sql += &format!(" ORDER BY {}", order_by_clause(&request.order, dialect)?);
+        }
+        sql += &format!(" LIMIT {} OFFSET {}", request.page_size + 1, offset);
+        let rows = self.0.execute_sql_with_return(&sql, &[])?;
+        let mut records: Vec<Dao> = rows.iter().collect();
+        let next_page = if records.len() > request.page_size {
+            records.truncate(request.page_size);
+            Some(PageToken::Offset(offset + request.page_size))
+        } else {
+            None
+        };
+        Ok((records, next_page))
+    }
+}
+
+/// find the column matching the name, disregarding the table of the column name
+fn find_column<'t>(table: &'t TableDef, column_name: &ColumnName) -> Option<&'t ColumnDef> {
+    table
+        .columns
+        .iter()
+        .find(|column| column.name.name == column_name.name)
+}
+
+fn order_by_clause(order: &[Order], dialect: Dialect) -> Result<String, DbError> {
+    let clauses = order
+        .iter()
+        .map(|order| {
+            Ok(format!(
+                "{} {}",
+                order.column.quoted_name(dialect)?,
+                order.direction.keyword()
+            ))
+        })
+        .collect::<Result<Vec<_>, DbError>>()?;
+    Ok(clauses.join(", "))
+}
+
+/// the sort order with the primary key or a unique key appended as the tie breaker,
+/// None when keyset pagination can not be used on this table and order.
+///
+/// All of the key columns has to be not null, since null values can not be compared
+fn keyset_order(table: &TableDef, order: &[Order]) -> Option<Vec<Order>> {
+    let last_direction = order
+        .last()
+        .map(|order| order.direction)
+        .unwrap_or(Direction::Asc);
+    let unique_keys = table.table_key.iter().filter_map(|key| match key {
+        TableKey::PrimaryKey(key) | TableKey::UniqueKey(key) => Some(key),
+        _ => None,
+    });
+    for key in unique_keys {
+        let mut keyset = order.to_vec();
+        for column in key.columns.iter() {
+            if !keyset.iter().any(|order| order.column.name == column.name) {
+                keyset.push(Order {
+                    column: column.clone(),
+                    direction: last_direction,
+                });
+            }
+        }
+        let all_not_null = keyset.iter().all(|order| {
+            find_column(table, &order.column)
+                .map(|column| column.is_not_null() || table.is_primary_column(column))
+                .unwrap_or(false)
+        });
+        if all_not_null {
+            return Some(keyset);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+#[cfg(feature = "with-sqlite")]
+mod test {
+    use super::*;
+    use crate::*;
+
+    #[derive(Debug, FromDao, ToColumnNames, ToTableName)]
+    struct Actor {
+        actor_id: i32,
+        last_name: String,
+    }
+
+    fn get_all_pages(em: &mut EntityManager, request: PageRequest) -> (Vec<Actor>, usize) {
+        let mut request = request.with_total();
+        let mut actors = vec![];
+        loop {
+            let page: Page<Actor> = em.get_page(&request).expect("must get page");
+            let total = page.total.expect("must have total");
+            actors.extend(page.rows);
+            match page.next_page {
+                Some(next_page) => {
+                    let token = PageToken::decode(&next_page.encode()).unwrap();
+                    request.token = Some(token);
+                }
+                None => return (actors, total),
+            }
+        }
+    }
+
+    #[test]
+    fn keyset_pages_of_actors_by_last_name() {
+        let db_url = "sqlite://sakila.db";
+        let pool = Pool::new();
+        let mut em = pool.em(db_url).unwrap();
+        let request = PageRequest::new(2).order_by(Order::desc("last_name"));
+        let (actors, total) = get_all_pages(&mut em, request);
+        assert_eq!(actors.len(), total);
+        let mut ids: Vec<i32> = actors.iter().map(|a| a.actor_id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), total);
+        assert!(actors
+            .windows(2)
+            .all(|pair| pair[0].last_name >= pair[1].last_name));
+    }
+
+    #[test]
+    fn offset_page() {
+        let db_url = "sqlite://sakila.db";
+        let pool = Pool::new();
+        let mut em = pool.em(db_url).unwrap();
+        let request = PageRequest::new(2).token(PageToken::Offset(1));
+        let page: Page<Actor> = em.get_page(&request).unwrap();
+        assert_eq!(page.rows.len(), 2);
+        assert_eq!(page.total, None);
+    }
+
+    #[test]
+    fn zero_page_size() {
+        let db_url = "sqlite://sakila.db";
+        let pool = Pool::new();
+        let mut em = pool.em(db_url).unwrap();
+        for request in &[
+            PageRequest::new(0),
+            PageRequest::new(0).order_by(Order::asc("actor_id")),
+        ] {
+            let page: Result<Page<Actor>, DbError> = em.get_page(request);
+            match page {
+                Err(DbError::DataError(DataError::InvalidPageSize(0))) => (),
+                other => panic!(
+                    "expecting an invalid page size, got: {:?}",
+                    other.map(|p| p.rows.len())
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn unknown_order_column() {
+        let db_url = "sqlite://sakila.db";
+        let pool = Pool::new();
+        let mut em = pool.em(db_url).unwrap();
+        let request = PageRequest::new(2).order_by(Order::asc("no_such_column"));
+        let page: Result<Page<Actor>, DbError> = em.get_page(&request);
+        assert!(page.is_err());
+    }
+}
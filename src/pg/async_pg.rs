@@ -0,0 +1,214 @@
+//! The async postgresql driver.
+//!
+//! The values of the builtin types are converted by the same `PgValue` and `OwnedPgValue` of the
+//! synchronous driver, with the postgres type of the same oid. The user defined types, which are
+//! only known to the connection they are looked up by, are converted with the types of
+//! tokio-postgres.
+use super::{read_composite, read_range, write_composite, OwnedPgValue, PgValue, PostgresError};
+use crate::{AsyncDatabase, DbError, Rows, Value};
+use async_trait::async_trait;
+use bytes::BytesMut;
+use postgres::types::{FromSql, IsNull, ToSql, Type};
+use rustorm_dao::value::Array;
+use std::error::Error;
+use tokio_postgres::types::{
+    self as async_types, to_sql_checked, FromSql as AsyncFromSql, Kind, ToSql as AsyncToSql,
+};
+
+pub fn init_pool(db_url: &str) -> Result<deadpool_postgres::Pool, PostgresError> {
+    let config: tokio_postgres::Config = db_url
+        .parse()
+        .map_err(|e| PostgresError::AsyncSqlError(e, "Connection Manager Error".into()))?;
+    let manager = deadpool_postgres::Manager::new(config, tokio_postgres::NoTls);
+    Ok(deadpool_postgres::Pool::new(manager, 16))
+}
+
+pub struct AsyncPostgresDB(pub deadpool_postgres::Client);
+
+impl AsyncPostgresDB {
+    async fn pg_execute_sql_with_return(
+        &mut self,
+        sql: &str,
+        param: &[&Value],
+    ) -> Result<Rows, tokio_postgres::Error> {
+        let stmt = self.0.prepare(sql).await?;
+        let pg_values: Vec<PgValue> = param.iter().map(|v| PgValue(v)).collect();
+        let sql_types: Vec<&(dyn AsyncToSql + Sync)> = pg_values
+            .iter()
+            .map(|v| v as &(dyn AsyncToSql + Sync))
+            .collect();
+        let rows = self.0.query(&stmt, &sql_types).await?;
+        let column_names: Vec<String> = stmt
+            .columns()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        let mut records = Rows::new(column_names);
+        for r in rows.iter() {
+            let mut record: Vec<Value> = Vec::with_capacity(r.len());
+            for i in 0..r.len() {
+                let value: OwnedPgValue = r.try_get(i)?;
+                record.push(value.0);
+            }
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+#[async_trait]
+impl AsyncDatabase for AsyncPostgresDB {
+    async fn begin_transaction(&mut self) -> Result<(), DbError> {
+        self.execute_sql_with_return("BEGIN TRANSACTION", &[])
+            .await?;
+        Ok(())
+    }
+
+    async fn commit_transaction(&mut self) -> Result<(), DbError> {
+        self.execute_sql_with_return("COMMIT TRANSACTION", &[])
+            .await?;
+        Ok(())
+    }
+
+    async fn rollback_transaction(&mut self) -> Result<(), DbError> {
+        self.execute_sql_with_return("ROLLBACK TRANSACTION", &[])
+            .await?;
+        Ok(())
+    }
+
+    async fn execute_sql_with_return(
+        &mut self,
+        sql: &str,
+        param: &[&Value],
+    ) -> Result<Rows, DbError> {
+        self.pg_execute_sql_with_return(sql, param)
+            .await
+            .map_err(|e| PostgresError::AsyncSqlError(e, sql.to_string()).into())
+    }
+}
+
+fn to_async_is_null(is_null: IsNull) -> async_types::IsNull {
+    match is_null {
+        IsNull::Yes => async_types::IsNull::Yes,
+        IsNull::No => async_types::IsNull::No,
+    }
+}
+
+fn is_enum_array(ty: &async_types::Type) -> bool {
+    match *ty.kind() {
+        Kind::Array(ref member) => matches!(member.kind(), Kind::Enum(_)),
+        _ => false,
+    }
+}
+
+impl<'a> AsyncToSql for PgValue<'a> {
+    to_sql_checked!();
+
+    fn to_sql(
+        &self,
+        ty: &async_types::Type,
+        out: &mut BytesMut,
+    ) -> Result<async_types::IsNull, Box<dyn Error + 'static + Sync + Send>> {
+        if let Some(builtin) = Type::from_oid(ty.oid()) {
+            let mut buf = vec![];
+            let is_null = ToSql::to_sql(self, &builtin, &mut buf)?;
+            out.extend_from_slice(&buf);
+            return Ok(to_async_is_null(is_null));
+        }
+        match (ty.kind(), self.0) {
+            (_, Value::Nil) => Ok(async_types::IsNull::Yes),
+            // domains are sent as their base type
+            (Kind::Domain(base), _) => AsyncToSql::to_sql(self, base, out),
+            (Kind::Enum(_), Value::Text(v)) => AsyncToSql::to_sql(v, ty, out),
+            (Kind::Enum(_), Value::Char(v)) => AsyncToSql::to_sql(&v.to_string(), ty, out),
+            (Kind::Array(_), Value::Array(Array::Text(v))) if is_enum_array(ty) => {
+                AsyncToSql::to_sql(v, ty, out)
+            }
+            (Kind::Composite(type_fields), Value::Composite(fields)) => {
+                let layout: Vec<(&str, u32)> = type_fields
+                    .iter()
+                    .map(|field| (field.name(), field.type_().oid()))
+                    .collect();
+                let mut buf = vec![];
+                let is_null = write_composite(fields, &layout, &mut buf, |i, value, buf| {
+                    let mut field_out = BytesMut::new();
+                    let is_null = AsyncToSql::to_sql(
+                        &PgValue(value),
+                        type_fields[i].type_(),
+                        &mut field_out,
+                    )?;
+                    buf.extend_from_slice(&field_out);
+                    Ok(match is_null {
+                        async_types::IsNull::Yes => IsNull::Yes,
+                        async_types::IsNull::No => IsNull::No,
+                    })
+                })?;
+                out.extend_from_slice(&buf);
+                Ok(to_async_is_null(is_null))
+            }
+            (Kind::Range(_), _) => {
+                let name = ty.name();
+                let message = format!(
+                    "{} is read only, cast the text, ie: $1::text::{}",
+                    name, name
+                );
+                Err(message.into())
+            }
+            (_, value) => Err(format!("unable to store {:?} as {}", value, ty.name()).into()),
+        }
+    }
+
+    fn accepts(_ty: &async_types::Type) -> bool {
+        true
+    }
+}
+
+impl<'a> AsyncFromSql<'a> for OwnedPgValue {
+    fn from_sql(
+        ty: &async_types::Type,
+        raw: &'a [u8],
+    ) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        if let Some(builtin) = Type::from_oid(ty.oid()) {
+            return FromSql::from_sql(&builtin, raw);
+        }
+        match *ty.kind() {
+            Kind::Enum(_) => {
+                let text: String = AsyncFromSql::from_sql(ty, raw)?;
+                Ok(OwnedPgValue(Value::Text(text)))
+            }
+            Kind::Array(_) if is_enum_array(ty) => {
+                let texts: Vec<String> = AsyncFromSql::from_sql(ty, raw)?;
+                Ok(OwnedPgValue(Value::Array(Array::Text(texts))))
+            }
+            Kind::Domain(ref base) => AsyncFromSql::from_sql(base, raw),
+            Kind::Composite(ref fields) => {
+                let raw_fields = read_composite(raw)?;
+                if raw_fields.len() != fields.len() {
+                    return Err(format!("composite {} has changed", ty.name()).into());
+                }
+                let mut values = Vec::with_capacity(fields.len());
+                for (field, (_oid, raw)) in fields.iter().zip(raw_fields) {
+                    let value: OwnedPgValue = AsyncFromSql::from_sql_nullable(field.type_(), raw)?;
+                    values.push((field.name().to_string(), value.0));
+                }
+                Ok(OwnedPgValue(Value::Composite(values)))
+            }
+            // a user defined range of a builtin subtype, decoded to its text representation
+            Kind::Range(ref subtype) => match Type::from_oid(subtype.oid()) {
+                Some(subtype) => Ok(OwnedPgValue(Value::Text(read_range(&subtype, raw)?))),
+                None => Err(format!("unable to convert from range {}", ty.name()).into()),
+            },
+            ref kind => {
+                Err(format!("unable to convert from {} of kind {:?}", ty.name(), kind).into())
+            }
+        }
+    }
+
+    fn accepts(_ty: &async_types::Type) -> bool {
+        true
+    }
+
+    fn from_sql_null(_ty: &async_types::Type) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        Ok(OwnedPgValue(Value::Nil))
+    }
+}
@@ -0,0 +1,866 @@
+//! Copy records into and out of the tables with `COPY`, which is much faster than inserting
+//! them one statement at a time.
+//!
+//! The binary format encodes the values the same way as the parameters of a statement, using
+//! `PgValue` and `OwnedPgValue`. The text and csv formats use the text representation of the
+//! values, the dates and timestamps are expected in the ISO `DateStyle`.
+use super::{read_i32, read_value, OwnedPgValue, PgValue, PostgresDB, PostgresError};
+use crate::{common, types::SqlType, ColumnName, DbError, Dialect, TableName, Value};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use postgres::types::{self, FromSql, IsNull, ToSql, Type};
+use postgres_shared::types::Kind;
+use rustorm_dao::{value::Array, Rows};
+use std::{
+    error::Error,
+    fmt::Write as _,
+    io::{self, Read, Write},
+    str::FromStr,
+};
+use uuid::Uuid;
+
+/// the format of the data of a `COPY`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CopyFormat {
+    /// tab separated values, NULL is `\N`
+    Text,
+    /// comma separated values, NULL is an unquoted empty value
+    Csv,
+    /// the binary format of postgresql
+    Binary,
+}
+
+impl CopyFormat {
+    fn options(self) -> &'static str {
+        match self {
+            CopyFormat::Text => "(FORMAT text)",
+            CopyFormat::Csv => "(FORMAT csv)",
+            CopyFormat::Binary => "(FORMAT binary)",
+        }
+    }
+}
+
+/// the signature at the start of the binary format
+const BINARY_SIGNATURE: &[u8] = b"PGCOPY\n\xff\r\n\0";
+
+/// the rows are encoded into a buffer of about this size at a time
+const BUFFER_SIZE: usize = 64 * 1024;
+
+impl PostgresDB {
+    /// copy the rows into the table with `COPY ... FROM STDIN`, the columns of the rows are the
+    /// columns of the table they are copied into. Returns the number of copied rows
+    pub fn copy_in(
+        &mut self,
+        table_name: &TableName,
+        rows: &Rows,
+        format: CopyFormat,
+    ) -> Result<u64, DbError> {
+        let columns: Vec<ColumnName> = rows
+            .columns
+            .iter()
+            .map(|name| ColumnName {
+                name: name.to_string(),
+                table: None,
+                alias: None,
+            })
+            .collect();
+        let types = self.column_types(table_name, &columns)?;
+        let sql = format!(
+            "COPY {} ({}) FROM STDIN {}",
+            table_name.quoted_complete_name(Dialect::Postgres)?,
+            common::quoted_column_names(&columns, Dialect::Postgres)?,
+            format.options()
+        );
+        let stmt = self
+            .0
+            .prepare(&sql)
+            .map_err(|e| PostgresError::SqlError(e, sql.to_string()))?;
+        let mut reader = CopyInReader::new(&rows.data, &types, format);
+        let copied = stmt
+            .copy_in(&[], &mut reader)
+            .map_err(|e| PostgresError::SqlError(e, sql.to_string()))?;
+        Ok(copied)
+    }
+
+    /// copy the columns of the table out with `COPY ... TO STDOUT`, the rows are decoded as the
+    /// data arrives
+    pub fn copy_out(
+        &mut self,
+        table_name: &TableName,
+        columns: &[ColumnName],
+        format: CopyFormat,
+    ) -> Result<Rows, DbError> {
+        let types = self.column_types(table_name, columns)?;
+        let sql = format!(
+            "COPY {} ({}) TO STDOUT {}",
+            table_name.quoted_complete_name(Dialect::Postgres)?,
+            common::quoted_column_names(columns, Dialect::Postgres)?,
+            format.options()
+        );
+        let stmt = self
+            .0
+            .prepare(&sql)
+            .map_err(|e| PostgresError::SqlError(e, sql.to_string()))?;
+        let rows = Rows::new(columns.iter().map(|c| c.name.to_string()).collect());
+        let mut writer = CopyOutWriter::new(rows, &types, format);
+        let copied = stmt.copy_out(&[], &mut writer);
+        // the error of the decoding is the reason the copy failed
+        if let Some(e) = writer.error.take() {
+            return Err(e);
+        }
+        copied.map_err(|e| PostgresError::SqlError(e, sql.to_string()))?;
+        writer.finish()
+    }
+
+    /// the types of the columns, from the description of a select of the columns
+    fn column_types(
+        &mut self,
+        table_name: &TableName,
+        columns: &[ColumnName],
+    ) -> Result<Vec<Type>, DbError> {
+        let sql = format!(
+            "SELECT {} FROM {}",
+            common::quoted_column_names(columns, Dialect::Postgres)?,
+            table_name.quoted_complete_name(Dialect::Postgres)?
+        );
+        let stmt = self
+            .0
+            .prepare(&sql)
+            .map_err(|e| PostgresError::SqlError(e, sql.to_string()))?;
+        Ok(stmt.columns().iter().map(|c| c.type_().clone()).collect())
+    }
+}
+
+fn copy_error(e: Box<dyn Error + Sync + Send>) -> DbError {
+    PostgresError::CopyError(e.to_string()).into()
+}
+
+/// encodes the rows as they are read by the `COPY`, so the whole data is never in memory
+struct CopyInReader<'a> {
+    rows: std::slice::Iter<'a, Vec<Value>>,
+    types: &'a [Type],
+    format: CopyFormat,
+    buf: Vec<u8>,
+    pos: usize,
+    started: bool,
+    finished: bool,
+}
+
+impl<'a> CopyInReader<'a> {
+    fn new(rows: &'a [Vec<Value>], types: &'a [Type], format: CopyFormat) -> Self {
+        CopyInReader {
+            rows: rows.iter(),
+            types,
+            format,
+            buf: Vec::with_capacity(BUFFER_SIZE),
+            pos: 0,
+            started: false,
+            finished: false,
+        }
+    }
+
+    fn fill(&mut self) -> Result<(), Box<dyn Error + Sync + Send>> {
+        self.buf.clear();
+        self.pos = 0;
+        if !self.started {
+            self.started = true;
+            if self.format == CopyFormat::Binary {
+                write_binary_header(&mut self.buf);
+            }
+        }
+        while self.buf.len() < BUFFER_SIZE {
+            match self.rows.next() {
+                Some(row) => match self.format {
+                    CopyFormat::Binary => write_binary_row(row, self.types, &mut self.buf)?,
+                    CopyFormat::Text => write_text_row(row, &mut self.buf),
+                    CopyFormat::Csv => write_csv_row(row, &mut self.buf),
+                },
+                None => {
+                    self.finished = true;
+                    if self.format == CopyFormat::Binary {
+                        self.buf.extend_from_slice(&(-1i16).to_be_bytes());
+                    }
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// decodes the rows as the data of the `COPY` is written, only the data of the records which
+/// have not fully arrived yet is buffered
+struct CopyOutWriter<'a> {
+    rows: Rows,
+    types: &'a [Type],
+    format: CopyFormat,
+    /// the data of the records which are not complete yet
+    buf: Vec<u8>,
+    /// how far the csv data in the buffer is scanned for the end of a record, and whether the
+    /// scan ended inside quotes
+    scanned: usize,
+    in_quotes: bool,
+    header_read: bool,
+    finished: bool,
+    error: Option<DbError>,
+}
+
+impl<'a> CopyOutWriter<'a> {
+    fn new(rows: Rows, types: &'a [Type], format: CopyFormat) -> Self {
+        CopyOutWriter {
+            rows,
+            types,
+            format,
+            buf: Vec::with_capacity(BUFFER_SIZE),
+            scanned: 0,
+            in_quotes: false,
+            header_read: false,
+            finished: false,
+            error: None,
+        }
+    }
+
+    /// decode the complete records in the buffer
+    fn decode(&mut self) -> Result<(), DbError> {
+        match self.format {
+            CopyFormat::Binary => {
+                let mut data = &self.buf[..];
+                if !self.header_read {
+                    match binary_header_len(data).map_err(copy_error)? {
+                        Some(len) => data = &data[len..],
+                        None => return Ok(()),
+                    }
+                    self.header_read = true;
+                }
+                while !self.finished {
+                    match read_binary_record(&mut data, self.types).map_err(copy_error)? {
+                        BinaryRecord::Record(record) => self.rows.push(record),
+                        BinaryRecord::Trailer => self.finished = true,
+                        BinaryRecord::Incomplete => break,
+                    }
+                }
+                let consumed = self.buf.len() - data.len();
+                self.buf.drain(..consumed);
+            }
+            CopyFormat::Text => {
+                // the newlines in the values are escaped, so each line is a record
+                if let Some(end) = self.buf.iter().rposition(|b| *b == b'\n') {
+                    let records = read_text(&self.buf[..=end]).map_err(copy_error)?;
+                    self.push_text_records(records)?;
+                    self.buf.drain(..=end);
+                }
+            }
+            CopyFormat::Csv => {
+                let mut end = None;
+                for (i, b) in self.buf.iter().enumerate().skip(self.scanned) {
+                    match *b {
+                        b'"' => self.in_quotes = !self.in_quotes,
+                        b'\n' if !self.in_quotes => end = Some(i),
+                        _ => (),
+                    }
+                }
+                self.scanned = self.buf.len();
+                if let Some(end) = end {
+                    let records = read_csv(&self.buf[..=end]).map_err(copy_error)?;
+                    self.push_text_records(records)?;
+                    self.buf.drain(..=end);
+                    self.scanned -= end + 1;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push_text_records(&mut self, records: Vec<Vec<Option<String>>>) -> Result<(), DbError> {
+        for record in records {
+            let values = record
+                .iter()
+                .zip(self.types.iter())
+                .map(|(field, ty)| match field {
+                    Some(text) => from_pg_text(ty, text),
+                    None => Ok(Value::Nil),
+                })
+                .collect::<Result<Vec<Value>, DbError>>()?;
+            self.rows.push(values);
+        }
+        Ok(())
+    }
+
+    /// the rows, after the last record which does not end with a newline is decoded
+    fn finish(mut self) -> Result<Rows, DbError> {
+        match self.format {
+            CopyFormat::Binary => {
+                if !self.finished || !self.buf.is_empty() {
+                    return Err(PostgresError::CopyError(
+                        "the binary copy does not end with its trailer".to_string(),
+                    )
+                    .into());
+                }
+            }
+            CopyFormat::Text => {
+                let records = read_text(&self.buf).map_err(copy_error)?;
+                self.push_text_records(records)?;
+            }
+            CopyFormat::Csv => {
+                let records = read_csv(&self.buf).map_err(copy_error)?;
+                self.push_text_records(records)?;
+            }
+        }
+        Ok(self.rows)
+    }
+}
+
+impl Write for CopyOutWriter<'_> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.error.is_none() {
+            self.buf.extend_from_slice(data);
+            if let Err(e) = self.decode() {
+                self.error = Some(e);
+            }
+        }
+        match self.error {
+            Some(_) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid data of the copy",
+            )),
+            None => Ok(data.len()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for CopyInReader<'_> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buf.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.fill()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        }
+        let len = out.len().min(self.buf.len() - self.pos);
+        out[..len].copy_from_slice(&self.buf[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+fn write_binary_header(out: &mut Vec<u8>) {
+    out.extend_from_slice(BINARY_SIGNATURE);
+    // the flags and the length of the header extension
+    out.extend_from_slice(&0i32.to_be_bytes());
+    out.extend_from_slice(&0i32.to_be_bytes());
+}
+
+/// the number of values followed by each value prefixed with its length, -1 for NULL
+fn write_binary_row(
+    row: &[Value],
+    types: &[Type],
+    out: &mut Vec<u8>,
+) -> Result<(), Box<dyn Error + Sync + Send>> {
+    if row.len() != types.len() {
+        return Err(format!("expecting {} values, found {}", types.len(), row.len()).into());
+    }
+    out.extend_from_slice(&(row.len() as i16).to_be_bytes());
+    for (value, ty) in row.iter().zip(types) {
+        if let Value::Interval(_) = value {
+            return Err("interval values can not be copied in the binary format".into());
+        }
+        let cast = cast_integer(value, ty)?;
+        let value = cast.as_ref().unwrap_or(value);
+        let len_pos = out.len();
+        out.extend_from_slice(&[0; 4]);
+        let len = match PgValue(value).to_sql(ty, out)? {
+            IsNull::Yes => -1,
+            IsNull::No => (out.len() - len_pos - 4) as i32,
+        };
+        out[len_pos..len_pos + 4].copy_from_slice(&len.to_be_bytes());
+    }
+    Ok(())
+}
+
+/// the integer cast to the integer type of the column, ie: an `Int` copied into a `bigint`
+/// column, None for the other values. The integers which do not fit in the column are an error
+/// instead of being truncated
+fn cast_integer(value: &Value, ty: &Type) -> Result<Option<Value>, Box<dyn Error + Sync + Send>> {
+    fn integer_type(ty: &Type) -> Option<SqlType> {
+        if let Kind::Domain(ref base) = *ty.kind() {
+            return integer_type(base);
+        }
+        match *ty {
+            types::INT2 => Some(SqlType::Smallint),
+            types::INT4 => Some(SqlType::Int),
+            types::INT8 => Some(SqlType::Bigint),
+            _ => None,
+        }
+    }
+
+    fn as_i64(value: &Value) -> Option<i64> {
+        match *value {
+            Value::Smallint(v) => Some(i64::from(v)),
+            Value::Int(v) => Some(i64::from(v)),
+            Value::Bigint(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    let (int, sql_type) = match (as_i64(value), integer_type(ty)) {
+        (Some(int), Some(sql_type)) => (int, sql_type),
+        _ => return Ok(None),
+    };
+    let cast = common::cast_type(value, &sql_type);
+    if as_i64(&cast) == Some(int) {
+        Ok(Some(cast))
+    } else {
+        Err(format!("{} does not fit in a {} column", int, ty.name()).into())
+    }
+}
+
+fn write_text_row(row: &[Value], out: &mut Vec<u8>) {
+    for (i, value) in row.iter().enumerate() {
+        if i > 0 {
+            out.push(b'\t');
+        }
+        match to_pg_text(value) {
+            Some(text) => {
+                for b in text.bytes() {
+                    match b {
+                        b'\\' => out.extend_from_slice(b"\\\\"),
+                        b'\n' => out.extend_from_slice(b"\\n"),
+                        b'\r' => out.extend_from_slice(b"\\r"),
+                        b'\t' => out.extend_from_slice(b"\\t"),
+                        _ => out.push(b),
+                    }
+                }
+            }
+            None => out.extend_from_slice(b"\\N"),
+        }
+    }
+    out.push(b'\n');
+}
+
+/// all the values are quoted, so an empty text is not mistaken for a NULL
+fn write_csv_row(row: &[Value], out: &mut Vec<u8>) {
+    for (i, value) in row.iter().enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        if let Some(text) = to_pg_text(value) {
+            out.push(b'"');
+            for b in text.bytes() {
+                if b == b'"' {
+                    out.push(b'"');
+                }
+                out.push(b);
+            }
+            out.push(b'"');
+        }
+    }
+    out.push(b'\n');
+}
+
+/// the text representation of the value which is accepted by postgresql, None for NULL
+fn to_pg_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Nil => None,
+        Value::DateTime(v) => Some(v.format("%Y-%m-%d %H:%M:%S%.f").to_string()),
+        Value::Blob(v) => {
+            let mut hex = String::with_capacity(2 + v.len() * 2);
+            hex.push_str("\\x");
+            for b in v {
+                let _ = write!(hex, "{:02x}", b);
+            }
+            Some(hex)
+        }
+        Value::Interval(v) => Some(format!(
+            "{} months {} days {} microseconds",
+            v.months, v.days, v.microseconds
+        )),
+        Value::Point(v) => Some(format!("({},{})", v.x(), v.y())),
+        Value::Array(v) => Some(array_text(v)),
+        _ => Some(value.to_string()),
+    }
+}
+
+/// the array literal, ie: {1,2,3} or {"a","b c"}
+fn array_text(array: &Array) -> String {
+    let elements: Vec<String> = match array {
+        Array::Int(v) => v.iter().map(ToString::to_string).collect(),
+        Array::Float(v) => v.iter().map(ToString::to_string).collect(),
+        Array::Text(v) => v
+            .iter()
+            .map(|text| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect(),
+    };
+    format!("{{{}}}", elements.join(","))
+}
+
+fn read_i16(buf: &mut &[u8]) -> Result<i16, Box<dyn Error + Sync + Send>> {
+    if buf.len() < 2 {
+        return Err("invalid buffer size".into());
+    }
+    let (bytes, rest) = buf.split_at(2);
+    *buf = rest;
+    Ok(i16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// the length of the header of the binary format, None when the data is not complete yet
+fn binary_header_len(data: &[u8]) -> Result<Option<usize>, Box<dyn Error + Sync + Send>> {
+    let signature_len = BINARY_SIGNATURE.len().min(data.len());
+    if data[..signature_len] != BINARY_SIGNATURE[..signature_len] {
+        return Err("invalid signature of the binary copy".into());
+    }
+    let mut rest = match data.get(BINARY_SIGNATURE.len() + 8..) {
+        Some(_) => &data[BINARY_SIGNATURE.len()..],
+        None => return Ok(None),
+    };
+    let _flags = read_i32(&mut rest)?;
+    let extension_len = read_i32(&mut rest)? as usize;
+    let len = BINARY_SIGNATURE.len() + 8 + extension_len;
+    Ok(if data.len() < len { None } else { Some(len) })
+}
+
+/// a record of the binary format
+enum BinaryRecord {
+    Record(Vec<Value>),
+    /// the end of the binary format
+    Trailer,
+    /// the data of the record has not arrived yet
+    Incomplete,
+}
+
+/// read the record at the start of the data, the data is advanced past it
+fn read_binary_record(
+    data: &mut &[u8],
+    types: &[Type],
+) -> Result<BinaryRecord, Box<dyn Error + Sync + Send>> {
+    if data.len() < 2 {
+        return Ok(BinaryRecord::Incomplete);
+    }
+    let mut record_data = *data;
+    let field_count = read_i16(&mut record_data)?;
+    if field_count == -1 {
+        *data = record_data;
+        return Ok(BinaryRecord::Trailer);
+    }
+    if field_count as usize != types.len() {
+        return Err(format!("expecting {} fields, found {}", types.len(), field_count).into());
+    }
+    // the lengths of the fields are checked before any of them is decoded
+    let mut len = 2;
+    for _ in 0..types.len() {
+        let field_len = match data.get(len..len + 4) {
+            Some(b) => i32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+            None => return Ok(BinaryRecord::Incomplete),
+        };
+        len += 4 + field_len.max(0) as usize;
+    }
+    if data.len() < len {
+        return Ok(BinaryRecord::Incomplete);
+    }
+    let mut record = Vec::with_capacity(types.len());
+    for ty in types {
+        let raw = read_value(&mut record_data)?;
+        record.push(OwnedPgValue::from_sql_nullable(ty, raw)?.0);
+    }
+    *data = record_data;
+    Ok(BinaryRecord::Record(record))
+}
+
+/// the fields of the text format, None for NULL
+fn read_text(data: &[u8]) -> Result<Vec<Vec<Option<String>>>, Box<dyn Error + Sync + Send>> {
+    let text = std::str::from_utf8(data)?;
+    Ok(text
+        .split_terminator('\n')
+        .map(|line| {
+            line.split('\t')
+                .map(|field| {
+                    if field == "\\N" {
+                        None
+                    } else {
+                        Some(unescape_text(field))
+                    }
+                })
+                .collect()
+        })
+        .collect())
+}
+
+fn unescape_text(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('v') => out.push('\u{b}'),
+            Some(c) => out.push(c),
+            None => (),
+        }
+    }
+    out
+}
+
+/// the fields of the csv format, an unquoted empty field is a NULL
+fn read_csv(data: &[u8]) -> Result<Vec<Vec<Option<String>>>, Box<dyn Error + Sync + Send>> {
+    fn take_field(field: &mut String, quoted: &mut bool) -> Option<String> {
+        let value = if field.is_empty() && !*quoted {
+            None
+        } else {
+            Some(std::mem::take(field))
+        };
+        *quoted = false;
+        value
+    }
+
+    let text = std::str::from_utf8(data)?;
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_quotes = true;
+                quoted = true;
+            }
+            ',' => record.push(take_field(&mut field, &mut quoted)),
+            '\n' => {
+                record.push(take_field(&mut field, &mut quoted));
+                records.push(std::mem::take(&mut record));
+            }
+            '\r' => (),
+            _ => field.push(c),
+        }
+    }
+    if !record.is_empty() || !field.is_empty() || quoted {
+        record.push(take_field(&mut field, &mut quoted));
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// convert the text representation of a value of this type
+fn from_pg_text(ty: &Type, text: &str) -> Result<Value, DbError> {
+    let unsupported = || {
+        DbError::from(PostgresError::CopyError(format!(
+            "invalid {} value: {}",
+            ty.name(),
+            text
+        )))
+    };
+    match *ty.kind() {
+        Kind::Domain(ref base) => return from_pg_text(base, text),
+        Kind::Enum(_) => return Ok(Value::Text(text.to_string())),
+        _ => (),
+    }
+    let value = match *ty {
+        types::BOOL => Value::Bool(text == "t" || text == "true"),
+        types::INT2 => Value::Smallint(text.parse().map_err(|_| unsupported())?),
+        types::INT4 => Value::Int(text.parse().map_err(|_| unsupported())?),
+        types::INT8 => Value::Bigint(text.parse().map_err(|_| unsupported())?),
+        types::FLOAT4 => Value::Float(text.parse().map_err(|_| unsupported())?),
+        types::FLOAT8 => Value::Double(text.parse().map_err(|_| unsupported())?),
+        types::NUMERIC => Value::BigDecimal(BigDecimal::from_str(text).map_err(|_| unsupported())?),
+        types::BPCHAR if text.chars().count() == 1 => Value::Char(text.chars().next().unwrap()),
+        types::BPCHAR => Value::Text(text.trim_end().to_string()),
+        types::UUID => Value::Uuid(Uuid::parse_str(text).map_err(|_| unsupported())?),
+        types::DATE => {
+            Value::Date(NaiveDate::parse_from_str(text, "%Y-%m-%d").map_err(|_| unsupported())?)
+        }
+        types::TIME => {
+            Value::Time(NaiveTime::parse_from_str(text, "%H:%M:%S%.f").map_err(|_| unsupported())?)
+        }
+        types::TIMESTAMP => {
+            let ts = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f")
+                .map_err(|_| unsupported())?;
+            Value::Timestamp(DateTime::from_utc(ts, Utc))
+        }
+        types::TIMESTAMPTZ => {
+            // the minutes of the offset are left out when they are zero, ie: +02
+            let bytes = text.as_bytes();
+            let text = match bytes.len().checked_sub(3).map(|i| bytes[i]) {
+                Some(b'+') | Some(b'-') => format!("{}00", text),
+                _ => text.to_string(),
+            };
+            let ts = DateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f%z")
+                .map_err(|_| unsupported())?;
+            Value::Timestamp(ts.with_timezone(&Utc))
+        }
+        types::JSON | types::JSONB => Value::Json(text.to_string()),
+        types::BYTEA => {
+            let hex = text.strip_prefix("\\x").ok_or_else(unsupported)?;
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|i| {
+                    hex.get(i..i + 2)
+                        .and_then(|b| u8::from_str_radix(b, 16).ok())
+                })
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(unsupported)?;
+            Value::Blob(bytes)
+        }
+        _ => Value::Text(text.to_string()),
+    };
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// decode the data written a few bytes at a time, as the `COPY` of a large table arrives
+    fn copy_out(data: &[u8], types: &[Type], format: CopyFormat) -> Result<Rows, DbError> {
+        let mut writer = CopyOutWriter::new(Rows::new(vec![]), types, format);
+        for piece in data.chunks(3) {
+            if writer.write_all(piece).is_err() {
+                return Err(writer.error.take().unwrap());
+            }
+        }
+        writer.finish()
+    }
+
+    #[test]
+    fn text_round_trip() {
+        let row = vec![
+            Value::Int(1),
+            Value::Text("tab\there\nand \\ backslash".to_string()),
+            Value::Nil,
+            Value::Blob(vec![0, 255]),
+        ];
+        let mut data = vec![];
+        write_text_row(&row, &mut data);
+        assert_eq!(
+            String::from_utf8(data.clone()).unwrap(),
+            "1\ttab\\there\\nand \\\\ backslash\t\\N\t\\x00ff\n"
+        );
+        let records = read_text(&data).unwrap();
+        assert_eq!(
+            records,
+            vec![vec![
+                Some("1".to_string()),
+                Some("tab\there\nand \\ backslash".to_string()),
+                None,
+                Some("\\x00ff".to_string()),
+            ]]
+        );
+        assert_eq!(
+            from_pg_text(&types::BYTEA, "\\x00ff").unwrap(),
+            Value::Blob(vec![0, 255])
+        );
+    }
+
+    #[test]
+    fn csv_round_trip() {
+        let row = vec![
+            Value::Text("say \"hi\",\nbye".to_string()),
+            Value::Text(String::new()),
+            Value::Nil,
+            Value::Array(Array::Text(vec!["a".to_string(), "b c".to_string()])),
+        ];
+        let mut data = vec![];
+        write_csv_row(&row, &mut data);
+        write_csv_row(&[Value::Int(2), Value::Nil], &mut data);
+        let types = vec![types::TEXT, types::TEXT, types::INT4, types::TEXT];
+        assert_eq!(
+            copy_out(&data, &types, CopyFormat::Csv).unwrap().data,
+            vec![
+                vec![
+                    Value::Text("say \"hi\",\nbye".to_string()),
+                    Value::Text(String::new()),
+                    Value::Nil,
+                    Value::Text("{\"a\",\"b c\"}".to_string()),
+                ],
+                vec![Value::Text("2".to_string()), Value::Nil],
+            ]
+        );
+        assert_eq!(
+            read_csv(&data).unwrap(),
+            vec![
+                vec![
+                    Some("say \"hi\",\nbye".to_string()),
+                    Some(String::new()),
+                    None,
+                    Some("{\"a\",\"b c\"}".to_string()),
+                ],
+                vec![Some("2".to_string()), None],
+            ]
+        );
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let types = vec![types::INT4, types::TEXT];
+        let mut data = vec![];
+        write_binary_header(&mut data);
+        write_binary_row(
+            &[Value::Int(42), Value::Text("rust".to_string())],
+            &types,
+            &mut data,
+        )
+        .unwrap();
+        write_binary_row(&[Value::Nil, Value::Nil], &types, &mut data).unwrap();
+        data.extend_from_slice(&(-1i16).to_be_bytes());
+        assert_eq!(
+            copy_out(&data, &types, CopyFormat::Binary).unwrap().data,
+            vec![
+                vec![Value::Int(42), Value::Text("rust".to_string())],
+                vec![Value::Nil, Value::Nil],
+            ]
+        );
+        assert!(copy_out(&data[..data.len() - 2], &types, CopyFormat::Binary).is_err());
+        assert!(write_binary_row(&[Value::Int(1)], &types, &mut data).is_err());
+    }
+
+    #[test]
+    fn integers_are_cast_to_the_column_type() {
+        let types = vec![types::INT8, types::INT2];
+        let mut data = vec![];
+        write_binary_header(&mut data);
+        write_binary_row(&[Value::Int(42), Value::Bigint(7)], &types, &mut data).unwrap();
+        data.extend_from_slice(&(-1i16).to_be_bytes());
+        assert_eq!(
+            copy_out(&data, &types, CopyFormat::Binary).unwrap().data,
+            vec![vec![Value::Bigint(42), Value::Smallint(7)]]
+        );
+        // the integers which do not fit in the column are not truncated
+        assert!(write_binary_row(&[Value::Int(1), Value::Int(70000)], &types, &mut data).is_err());
+    }
+
+    #[test]
+    fn timestamp_with_short_offset() {
+        let ts = from_pg_text(&types::TIMESTAMPTZ, "2020-01-02 03:04:05.5+02").unwrap();
+        assert_eq!(
+            ts,
+            Value::Timestamp(DateTime::from_utc(
+                NaiveDate::from_ymd(2020, 1, 2).and_hms_milli(1, 4, 5, 500),
+                Utc
+            ))
+        );
+    }
+}
@@ -0,0 +1,518 @@
+//! Find the parameter placeholders of an sql statement, skipping the string literals,
+//! quoted identifiers, comments and postgresql casts, bind the named parameters
+//! to the positional placeholders of the database platform and rewrite the positional
+//! placeholders from one platform style to another.
+use crate::{error::DataError, Dao, DbError, Dialect, Value};
+use rustorm_dao::value::Array;
+use std::collections::BTreeSet;
+
+/// A part of an sql statement
+#[derive(Debug, PartialEq)]
+pub(crate) enum Token<'a> {
+    /// sql text, literals, identifiers and comments which are kept as is
+    Sql(&'a str),
+    /// a named parameter without the colon, ie: `actor_id` of `:actor_id`
+    Named(&'a str),
+    /// a numbered placeholder, ie: `2` of `$2`
+    Numbered(usize),
+    /// an anonymous placeholder `?`
+    Anonymous,
+}
+
+/// The style of the positional placeholders a database platform accepts
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum PlaceholderStyle {
+    /// `$1`, `$2`, used by postgresql
+    Numbered,
+    /// `?`, used by mysql
+    Anonymous,
+    /// `?1`, `?2`, used by sqlite which also accepts `?` and `$n`, but binds the `$n`
+    /// placeholders in the order they first appear instead of by their number
+    Indexed,
+}
+
+impl PlaceholderStyle {
+    /// the placeholder style accepted by the database of this dialect
+    pub(crate) fn of(dialect: Dialect) -> Self {
+        match dialect {
+            Dialect::Postgres => PlaceholderStyle::Numbered,
+            Dialect::Sqlite => PlaceholderStyle::Indexed,
+            Dialect::Mysql => PlaceholderStyle::Anonymous,
+        }
+    }
+}
+
+/// the placeholder for the parameter at `index` (1-based) in the sql statement of this dialect
+pub(crate) fn placeholder(dialect: Dialect, index: usize) -> String {
+    match dialect {
+        Dialect::Mysql => "?".to_string(),
+        _ => format!("${}", index),
+    }
+}
+
+/// split the sql statement into the placeholders and the text in between,
+/// the string literals of mysql use backslash escapes and `#` starts a comment.
+/// A `$n` placeholder with a number too large for a usize is an `InvalidPlaceholder`
+pub(crate) fn tokenize(sql: &str, dialect: Dialect) -> Result<Vec<Token<'_>>, DbError> {
+    let mysql = dialect == Dialect::Mysql;
+    let bytes = sql.as_bytes();
+    let mut tokens = vec![];
+    // the start of the sql text not yet pushed as a token
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let next = bytes.get(i + 1).copied();
+        i = match bytes[i] {
+            b'\'' => skip_quoted(bytes, i, mysql || is_escape_string(bytes, i)),
+            b'"' => skip_quoted(bytes, i, mysql),
+            b'`' => skip_quoted(bytes, i, false),
+            b'-' if next == Some(b'-') => skip_line_comment(bytes, i),
+            b'#' if mysql => skip_line_comment(bytes, i),
+            b'/' if next == Some(b'*') => skip_block_comment(bytes, i),
+            b'$' if matches!(next, Some(c) if c.is_ascii_digit()) && !follows_ident(bytes, i) => {
+                let digits = bytes[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit())
+                    .count();
+                let number = &sql[i..i + 1 + digits];
+                let number = number[1..].parse().map_err(|_| {
+                    DbError::DataError(DataError::InvalidPlaceholder(format!(
+                        "{} is out of range",
+                        number
+                    )))
+                })?;
+                push_token(
+                    &mut tokens,
+                    sql,
+                    &mut start,
+                    i,
+                    i + 1 + digits,
+                    Token::Numbered(number),
+                )
+            }
+            b'$' => skip_dollar_quoted(bytes, i),
+            // not the postgresql jsonb operators ?| and ?&
+            b'?' if !matches!(next, Some(b'|') | Some(b'&')) => {
+                push_token(&mut tokens, sql, &mut start, i, i + 1, Token::Anonymous)
+            }
+            // a cast, ie: '1'::int
+            b':' if next == Some(b':') => i + 2,
+            // not an array slice, ie: arr[lower:upper]
+            b':' if matches!(next, Some(c) if is_ident_start(c)) && !follows_ident(bytes, i) => {
+                let end = i + 1 + count_ident_chars(&bytes[i + 1..]);
+                let name = Token::Named(&sql[i + 1..end]);
+                push_token(&mut tokens, sql, &mut start, i, end, name)
+            }
+            _ => i + 1,
+        };
+    }
+    if start < sql.len() {
+        tokens.push(Token::Sql(&sql[start..]));
+    }
+    Ok(tokens)
+}
+
+/// push the sql text before the placeholder at `i..end` followed by the placeholder,
+/// returning the position after the placeholder
+fn push_token<'a>(
+    tokens: &mut Vec<Token<'a>>,
+    sql: &'a str,
+    start: &mut usize,
+    i: usize,
+    end: usize,
+    token: Token<'a>,
+) -> usize {
+    if *start < i {
+        tokens.push(Token::Sql(&sql[*start..i]));
+    }
+    tokens.push(token);
+    *start = end;
+    end
+}
+
+/// rewrite the positional placeholders of the sql to the style of the platform,
+/// returning the sql and the index of the parameter bound to each placeholder.
+/// None is returned when the sql can be used as is.
+///
+/// `$n` placeholders are rewritten to `?` with the parameters reordered or repeated
+/// to match, or to `?n` for sqlite. `?` placeholders are rewritten to `$n` only when there
+/// are as many of them as there are parameters, so the postgresql jsonb `?` operator is
+/// left alone.
+pub(crate) fn rewrite_positional(
+    sql: &str,
+    dialect: Dialect,
+    param_count: usize,
+) -> Result<Option<(String, Vec<usize>)>, DbError> {
+    let style = PlaceholderStyle::of(dialect);
+    let tokens = tokenize(sql, dialect)?;
+    let numbered = tokens
+        .iter()
+        .filter(|t| matches!(t, Token::Numbered(_)))
+        .count();
+    let anonymous = tokens
+        .iter()
+        .filter(|t| matches!(t, Token::Anonymous))
+        .count();
+    let rewrite = match style {
+        PlaceholderStyle::Anonymous | PlaceholderStyle::Indexed => numbered > 0,
+        PlaceholderStyle::Numbered => numbered == 0 && anonymous > 0 && anonymous == param_count,
+    };
+    if !rewrite {
+        return Ok(None);
+    }
+    if numbered > 0 && anonymous > 0 {
+        return Err(DbError::DataError(DataError::InvalidPlaceholder(
+            "`?` and `$n` placeholders can not be mixed".to_string(),
+        )));
+    }
+    let mut rewritten = String::with_capacity(sql.len());
+    let mut order = vec![];
+    for token in tokens {
+        match token {
+            Token::Sql(text) => rewritten.push_str(text),
+            Token::Named(name) => {
+                rewritten.push(':');
+                rewritten.push_str(name);
+            }
+            Token::Numbered(number) => {
+                if number == 0 || number > param_count {
+                    return Err(DbError::DataError(DataError::InvalidPlaceholder(format!(
+                        "${} has no parameter, there are {} parameters",
+                        number, param_count
+                    ))));
+                }
+                order.push(number - 1);
+                rewritten.push('?');
+                if style == PlaceholderStyle::Indexed {
+                    rewritten.push_str(&number.to_string());
+                }
+            }
+            Token::Anonymous => {
+                order.push(order.len());
+                rewritten.push_str(&format!("${}", order.len()));
+            }
+        }
+    }
+    if style == PlaceholderStyle::Indexed {
+        // the `?n` placeholders are bound by their number
+        order = (0..param_count).collect();
+    }
+    Ok(Some((rewritten, order)))
+}
+
+/// replace the named parameters with the placeholders of the platform and return the values
+/// in the order of the placeholders.
+/// An array bound in an IN list is expanded to a placeholder for each of its elements.
+pub(crate) fn bind_named(
+    sql: &str,
+    dialect: Dialect,
+    params: &Dao,
+) -> Result<(String, Vec<Value>), DbError> {
+    let mut bound_sql = String::with_capacity(sql.len());
+    let mut values = vec![];
+    let mut used = BTreeSet::new();
+    for token in tokenize(sql, dialect)? {
+        match token {
+            Token::Sql(text) => bound_sql.push_str(text),
+            Token::Named(name) => {
+                let value = params.get_value(name).ok_or_else(|| {
+                    DbError::DataError(DataError::MissingNamedParameter(name.to_string()))
+                })?;
+                used.insert(name);
+                let elements = match value {
+                    Value::Array(array) if is_in_list(&bound_sql) => array_elements(array),
+                    _ => vec![value.clone()],
+                };
+                if elements.is_empty() {
+                    // an empty IN list is not valid sql, while `IN (NULL)` matches nothing
+                    bound_sql.push_str("NULL");
+                }
+                let placeholders: Vec<String> = elements
+                    .into_iter()
+                    .map(|element| {
+                        values.push(element);
+                        placeholder(dialect, values.len())
+                    })
+                    .collect();
+                bound_sql.push_str(&placeholders.join(", "));
+            }
+            Token::Numbered(number) => bound_sql.push_str(&format!("${}", number)),
+            Token::Anonymous => bound_sql.push('?'),
+        }
+    }
+    match params.0.keys().find(|name| !used.contains(name.as_str())) {
+        Some(unused) => Err(DbError::DataError(DataError::UnusedNamedParameter(
+            unused.to_string(),
+        ))),
+        None => Ok((bound_sql, values)),
+    }
+}
+
+/// the placeholder is the start of an IN list, ie: `actor_id IN (:actor_ids)`
+fn is_in_list(preceding: &str) -> bool {
+    let preceding = preceding.trim_end();
+    if !preceding.ends_with('(') {
+        return false;
+    }
+    let keyword = preceding[..preceding.len() - 1].trim_end().as_bytes();
+    keyword.len() >= 2
+        && keyword[keyword.len() - 2..].eq_ignore_ascii_case(b"in")
+        && !follows_ident(keyword, keyword.len() - 2)
+}
+
+fn array_elements(array: &Array) -> Vec<Value> {
+    match array {
+        Array::Int(ints) => ints.iter().map(|v| Value::Int(*v)).collect(),
+        Array::Float(floats) => floats.iter().map(|v| Value::Float(*v)).collect(),
+        Array::Text(texts) => texts.iter().map(|v| Value::Text(v.to_string())).collect(),
+    }
+}
+
+fn is_ident_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+fn is_ident_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+fn count_ident_chars(bytes: &[u8]) -> usize {
+    bytes.iter().take_while(|c| is_ident_char(**c)).count()
+}
+
+/// the character at this position is right after an identifier or a number
+fn follows_ident(bytes: &[u8], i: usize) -> bool {
+    i > 0 && is_ident_char(bytes[i - 1])
+}
+
+/// the string literal at this position uses backslash escapes, ie: E'it\'s'
+fn is_escape_string(bytes: &[u8], i: usize) -> bool {
+    i > 0 && (bytes[i - 1] == b'E' || bytes[i - 1] == b'e') && !follows_ident(bytes, i - 1)
+}
+
+/// the position after the closing quote of the literal or identifier that starts at `i`,
+/// a quote is escaped by doubling it
+fn skip_quoted(bytes: &[u8], i: usize, backslash_escapes: bool) -> usize {
+    let quote = bytes[i];
+    let mut j = i + 1;
+    while j < bytes.len() {
+        if backslash_escapes && bytes[j] == b'\\' {
+            j += 2;
+        } else if bytes[j] == quote {
+            if bytes.get(j + 1) == Some(&quote) {
+                j += 2;
+            } else {
+                return j + 1;
+            }
+        } else {
+            j += 1;
+        }
+    }
+    bytes.len()
+}
+
+fn skip_line_comment(bytes: &[u8], i: usize) -> usize {
+    match bytes[i..].iter().position(|c| *c == b'\n') {
+        Some(end) => i + end + 1,
+        None => bytes.len(),
+    }
+}
+
+/// block comments can be nested in postgresql
+fn skip_block_comment(bytes: &[u8], i: usize) -> usize {
+    let mut depth = 0;
+    let mut j = i;
+    while j + 1 < bytes.len() {
+        match (bytes[j], bytes[j + 1]) {
+            (b'/', b'*') => {
+                depth += 1;
+                j += 2;
+            }
+            (b'*', b'/') => {
+                depth -= 1;
+                j += 2;
+                if depth == 0 {
+                    return j;
+                }
+            }
+            _ => j += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// the position after a postgresql dollar quoted string, ie: $$text$$ or $body$text$body$,
+/// anything else that starts with `$` is skipped by one character
+fn skip_dollar_quoted(bytes: &[u8], i: usize) -> usize {
+    // part of an identifier, ie: a$1
+    if matches!(bytes.get(i + 1), Some(c) if c.is_ascii_digit()) {
+        return i + 1;
+    }
+    let tag_len = count_ident_chars(&bytes[i + 1..]);
+    if bytes.get(i + 1 + tag_len) != Some(&b'$') {
+        return i + 1;
+    }
+    let tag = &bytes[i..i + tag_len + 2];
+    let body = i + tag.len();
+    match bytes[body..].windows(tag.len()).position(|w| w == tag) {
+        Some(end) => body + end + tag.len(),
+        None => bytes.len(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn named(sql: &str, dialect: Dialect) -> Vec<&str> {
+        tokenize(sql, dialect)
+            .expect("must tokenize")
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::Named(name) => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn skip_literals_and_comments() {
+        let pg = Dialect::Postgres;
+        assert_eq!(
+            named(
+                "SELECT ':a', \":b\", `:c` FROM t -- :d\n WHERE x = :e /* :f /* :g */ */",
+                pg
+            ),
+            vec!["e"]
+        );
+        assert_eq!(
+            named("SELECT 'it''s :a', E'\\':b' WHERE y = :c", pg),
+            vec!["c"]
+        );
+        assert_eq!(named("SELECT $$ :a $$, $tag$ :b $tag$, :c", pg), vec!["c"]);
+        assert_eq!(
+            named("SELECT '1'::int, arr[lo:hi], now()::date WHERE id=:id", pg),
+            vec!["id"]
+        );
+        // a backslash is a plain character in postgresql strings
+        assert_eq!(named("SELECT 'C:\\' WHERE y = :c", pg), vec!["c"]);
+        // while mysql strings use backslash escapes, and `#` starts a comment
+        let my = Dialect::Mysql;
+        assert_eq!(
+            named("SELECT 'it\\'s :a', \"say \\\":b\" WHERE y = :c # :d", my),
+            vec!["c"]
+        );
+        assert_eq!(named("SELECT 'C:\\\\' WHERE y = :c", my), vec!["c"]);
+        assert_eq!(named("SELECT `a\\` WHERE y = :c", my), vec!["c"]);
+    }
+
+    #[test]
+    fn bind_to_placeholders() {
+        let mut params = Dao::new();
+        params.insert("first_name", "JOHN");
+        params.insert_value("actor_ids", &Value::Array(Array::Int(vec![1, 2, 3])));
+        let (sql, values) = bind_named(
+            "SELECT * FROM actor WHERE first_name = :first_name AND actor_id IN (:actor_ids)",
+            Dialect::Postgres,
+            &params,
+        )
+        .expect("must bind");
+        assert_eq!(
+            sql,
+            "SELECT * FROM actor WHERE first_name = $1 AND actor_id IN ($2, $3, $4)"
+        );
+        assert_eq!(
+            values,
+            vec![
+                Value::Text("JOHN".to_string()),
+                Value::Int(1),
+                Value::Int(2),
+                Value::Int(3)
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_and_unused_params() {
+        let mut params = Dao::new();
+        params.insert("actor_id", 1);
+        let missing = bind_named("SELECT :first_name", Dialect::Mysql, &params);
+        assert!(matches!(
+            missing,
+            Err(DbError::DataError(DataError::MissingNamedParameter(ref name))) if name == "first_name"
+        ));
+        let unused = bind_named("SELECT 1", Dialect::Mysql, &params);
+        assert!(matches!(
+            unused,
+            Err(DbError::DataError(DataError::UnusedNamedParameter(ref name))) if name == "actor_id"
+        ));
+    }
+
+    #[test]
+    fn numbered_to_anonymous() {
+        let (sql, order) = rewrite_positional(
+            "SELECT '$1', \"$2\" FROM t -- $1\n WHERE a = $2 AND b = $1 AND c = $2::int",
+            Dialect::Mysql,
+            2,
+        )
+        .expect("must be ok")
+        .expect("must be rewritten");
+        assert_eq!(
+            sql,
+            "SELECT '$1', \"$2\" FROM t -- $1\n WHERE a = ? AND b = ? AND c = ?::int"
+        );
+        assert_eq!(order, vec![1, 0, 1]);
+        let out_of_range = rewrite_positional("SELECT $3", Dialect::Mysql, 2);
+        assert!(matches!(
+            out_of_range,
+            Err(DbError::DataError(DataError::InvalidPlaceholder(_)))
+        ));
+        let overflow = rewrite_positional("SELECT $99999999999999999999999", Dialect::Mysql, 2);
+        assert!(matches!(
+            overflow,
+            Err(DbError::DataError(DataError::InvalidPlaceholder(_)))
+        ));
+    }
+
+    #[test]
+    fn anonymous_to_numbered() {
+        let (sql, order) = rewrite_positional(
+            "SELECT * FROM actor WHERE first_name = ? AND last_name = '?' AND actor_id = ?",
+            Dialect::Postgres,
+            2,
+        )
+        .expect("must be ok")
+        .expect("must be rewritten");
+        assert_eq!(
+            sql,
+            "SELECT * FROM actor WHERE first_name = $1 AND last_name = '?' AND actor_id = $2"
+        );
+        assert_eq!(order, vec![0, 1]);
+        // the jsonb operators are kept
+        let jsonb = rewrite_positional(
+            "SELECT * FROM t WHERE data ? 'a' AND data ?| array['b'] AND id = $1",
+            Dialect::Postgres,
+            1,
+        )
+        .expect("must be ok");
+        assert_eq!(jsonb, None);
+        let unchanged = rewrite_positional("SELECT ?", Dialect::Sqlite, 1).expect("ok");
+        assert_eq!(unchanged, None);
+    }
+
+    #[test]
+    fn numbered_to_indexed() {
+        let (sql, order) = rewrite_positional(
+            "SELECT * FROM actor WHERE last_name = $2 AND first_name = $1 OR actor_id = $2",
+            Dialect::Sqlite,
+            2,
+        )
+        .expect("must be ok")
+        .expect("must be rewritten");
+        assert_eq!(
+            sql,
+            "SELECT * FROM actor WHERE last_name = ?2 AND first_name = ?1 OR actor_id = ?2"
+        );
+        assert_eq!(order, vec![0, 1]);
+    }
+}
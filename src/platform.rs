@@ -1,7 +1,6 @@
-use crate::{error::ParseError, Database};
+use crate::{placeholder, Database, DbError, Dialect, Rows, Value};
 use cfg_if::cfg_if;
-use std::{convert::TryFrom, ops::Deref};
-use url::Url;
+use std::ops::Deref;
 
 cfg_if! {if #[cfg(feature = "with-postgres")]{
     use crate::pg::PostgresDB;
@@ -15,6 +14,10 @@ cfg_if! {if #[cfg(feature = "with-mysql")]{
     use crate::my::MysqlDB;
 }}
 
+cfg_if! {if #[cfg(feature = "with-duckdb")]{
+    use crate::duck::DuckdbDB;
+}}
+
 pub enum DBPlatform {
     #[cfg(feature = "with-postgres")]
     Postgres(Box<PostgresDB>),
@@ -22,6 +25,48 @@ pub enum DBPlatform {
     Sqlite(Box<SqliteDB>),
     #[cfg(feature = "with-mysql")]
     Mysql(Box<MysqlDB>),
+    #[cfg(feature = "with-duckdb")]
+    Duckdb(Box<DuckdbDB>),
+    /// the database of a backend registered with `register_backend`
+    Other(Box<dyn Database>),
+}
+
+impl DBPlatform {
+    /// the parameter placeholder for the parameter at `index` (1-based) in the sql statement
+    pub(crate) fn placeholder(&self, index: usize) -> String {
+        placeholder::placeholder(self.dialect(), index)
+    }
+
+    /// the sql dialect used for quoting identifiers and literals
+    pub fn dialect(&self) -> Dialect {
+        match *self {
+            #[cfg(feature = "with-postgres")]
+            DBPlatform::Postgres(_) => Dialect::Postgres,
+            #[cfg(feature = "with-sqlite")]
+            DBPlatform::Sqlite(_) => Dialect::Sqlite,
+            #[cfg(feature = "with-mysql")]
+            DBPlatform::Mysql(_) => Dialect::Mysql,
+            #[cfg(feature = "with-duckdb")]
+            DBPlatform::Duckdb(ref dk) => dk.dialect(),
+            DBPlatform::Other(ref db) => db.dialect(),
+        }
+    }
+
+    /// execute the sql after rewriting its `$n` or `?` placeholders to the style of this platform,
+    /// so the same sql can be used on every platform
+    pub(crate) fn execute_portable_sql(
+        &mut self,
+        sql: &str,
+        params: &[&Value],
+    ) -> Result<Rows, DbError> {
+        match placeholder::rewrite_positional(sql, self.dialect(), params.len())? {
+            Some((sql, order)) => {
+                let params: Vec<&Value> = order.into_iter().map(|i| params[i]).collect();
+                self.execute_sql_with_return(&sql, &params)
+            }
+            None => self.execute_sql_with_return(sql, params),
+        }
+    }
 }
 
 impl Deref for DBPlatform {
@@ -35,6 +80,9 @@ impl Deref for DBPlatform {
             DBPlatform::Sqlite(ref sq) => sq.deref(),
             #[cfg(feature = "with-mysql")]
             DBPlatform::Mysql(ref my) => my.deref(),
+            #[cfg(feature = "with-duckdb")]
+            DBPlatform::Duckdb(ref dk) => dk.deref(),
+            DBPlatform::Other(ref db) => db.deref(),
         }
     }
 }
@@ -48,45 +96,18 @@ impl std::ops::DerefMut for DBPlatform {
             DBPlatform::Sqlite(ref mut sq) => sq.deref_mut(),
             #[cfg(feature = "with-mysql")]
             DBPlatform::Mysql(ref mut my) => my.deref_mut(),
+            #[cfg(feature = "with-duckdb")]
+            DBPlatform::Duckdb(ref mut dk) => dk.deref_mut(),
+            DBPlatform::Other(ref mut db) => db.deref_mut(),
         }
     }
 }
 
-pub(crate) enum Platform {
-    #[cfg(feature = "with-postgres")]
-    Postgres,
-    #[cfg(feature = "with-sqlite")]
-    Sqlite(String),
-    #[cfg(feature = "with-mysql")]
-    Mysql,
-    Unsupported(String),
-}
-
-impl<'a> TryFrom<&'a str> for Platform {
-    type Error = ParseError;
-
-    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
-        let url = Url::parse(s);
-        match url {
-            Ok(url) => {
-                let scheme = url.scheme();
-                match scheme {
-                    #[cfg(feature = "with-postgres")]
-                    "postgres" => Ok(Platform::Postgres),
-                    #[cfg(feature = "with-sqlite")]
-                    "sqlite" => {
-                        let host = url.host_str().unwrap();
-                        let path = url.path();
-                        let path = if path == "/" { "" } else { path };
-                        let db_file = format!("{}{}", host, path);
-                        Ok(Platform::Sqlite(db_file))
-                    }
-                    #[cfg(feature = "with-mysql")]
-                    "mysql" => Ok(Platform::Mysql),
-                    _ => Ok(Platform::Unsupported(scheme.to_string())),
-                }
-            }
-            Err(e) => Err(ParseError::DbUrlParseError(e)),
-        }
-    }
+/// the database file of an sqlite or duckdb db_url, ie: `sakila.db` of `sqlite://sakila.db`
+#[cfg(any(feature = "with-sqlite", feature = "with-duckdb"))]
+pub(crate) fn database_file(url: &url::Url) -> String {
+    let host = url.host_str().unwrap();
+    let path = url.path();
+    let path = if path == "/" { "" } else { path };
+    format!("{}{}", host, path)
 }
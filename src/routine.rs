@@ -0,0 +1,211 @@
+use crate::{
+    error::{DataError, DbError},
+    types::SqlType,
+    Dao, Rows, TableName, Value,
+};
+
+/// A stored function or procedure
+#[derive(Debug, PartialEq, Clone)]
+pub struct Routine {
+    /// the schema qualified name of the routine
+    pub name: TableName,
+    pub kind: RoutineKind,
+    /// all the arguments in the order they are declared, including the OUT arguments
+    pub arguments: Vec<RoutineArgument>,
+    /// the data type of the returned value, None for procedures
+    pub return_type: Option<String>,
+    /// the function returns a set of rows, postgresql only
+    pub returns_set: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RoutineKind {
+    Function,
+    Procedure,
+    Aggregate,
+    Window,
+}
+
+/// An argument of a stored function or procedure
+#[derive(Debug, PartialEq, Clone)]
+pub struct RoutineArgument {
+    /// None for unnamed arguments
+    pub name: Option<String>,
+    pub mode: ArgumentMode,
+    pub data_type: String,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ArgumentMode {
+    In,
+    Out,
+    InOut,
+    Variadic,
+    /// a column of the result of a `RETURNS TABLE` function, postgresql only
+    Table,
+}
+
+impl ArgumentMode {
+    /// a value is passed for this argument when the routine is called
+    pub fn is_input(&self) -> bool {
+        match *self {
+            ArgumentMode::In | ArgumentMode::InOut | ArgumentMode::Variadic => true,
+            ArgumentMode::Out | ArgumentMode::Table => false,
+        }
+    }
+
+    /// a value is returned for this argument after the routine is called
+    pub fn is_output(&self) -> bool {
+        match *self {
+            ArgumentMode::Out | ArgumentMode::InOut | ArgumentMode::Table => true,
+            ArgumentMode::In | ArgumentMode::Variadic => false,
+        }
+    }
+}
+
+impl Routine {
+    /// the arguments which are passed when calling the routine
+    pub fn in_arguments(&self) -> Vec<&RoutineArgument> {
+        self.arguments
+            .iter()
+            .filter(|arg| arg.mode.is_input())
+            .collect()
+    }
+
+    /// the arguments which are returned after calling the routine
+    pub fn out_arguments(&self) -> Vec<&RoutineArgument> {
+        self.arguments
+            .iter()
+            .filter(|arg| arg.mode.is_output())
+            .collect()
+    }
+
+    /// the values can be passed to the IN and INOUT arguments of this routine, the arguments
+    /// with a data type which is not known here accept any value
+    fn accepts(&self, args: &[&Value], widen: bool) -> bool {
+        let in_arguments = self.in_arguments();
+        in_arguments.len() == args.len()
+            && in_arguments.iter().zip(args).all(|(argument, value)| {
+                SqlType::from_type_name(&argument.data_type)
+                    .map(|sql_type| sql_type.accepts(value, widen))
+                    .unwrap_or(true)
+            })
+    }
+}
+
+/// pick the overload of the routine the values are meant for, out of the overloads with the
+/// same number of arguments the one which accepts the type of the values is chosen, integer
+/// values are only passed to decimal arguments when no overload takes them as they are
+pub(crate) fn find_overload(
+    routine_name: &TableName,
+    routines: Vec<Routine>,
+    args: &[&Value],
+) -> Result<Routine, DbError> {
+    let mut candidates: Vec<Routine> = routines
+        .into_iter()
+        .filter(|routine| routine.in_arguments().len() == args.len())
+        .collect();
+    if candidates.len() > 1 {
+        let overloads = candidates.len();
+        let mut matching: Vec<Routine> = candidates
+            .iter()
+            .filter(|routine| routine.accepts(args, false))
+            .cloned()
+            .collect();
+        if matching.is_empty() {
+            matching = candidates
+                .into_iter()
+                .filter(|routine| routine.accepts(args, true))
+                .collect();
+        }
+        if matching.len() != 1 {
+            return Err(DbError::DataError(DataError::AmbiguousRoutine(
+                routine_name.complete_name(),
+                overloads,
+                args.len(),
+            )));
+        }
+        candidates = matching;
+    }
+    candidates.pop().ok_or_else(|| {
+        DbError::DataError(DataError::RoutineNotFound(
+            routine_name.complete_name(),
+            args.len(),
+        ))
+    })
+}
+
+/// The values returned by calling a stored function or procedure
+#[derive(Debug)]
+pub struct RoutineResult {
+    /// the values of the OUT and INOUT arguments, keyed by the argument name
+    pub out_params: Dao,
+    /// the rows returned by the routine, mysql procedures can return more than one result set
+    pub result_sets: Vec<Rows>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn routine(data_types: &[&str]) -> Routine {
+        Routine {
+            name: TableName::from("public.area"),
+            kind: RoutineKind::Function,
+            arguments: data_types
+                .iter()
+                .map(|data_type| RoutineArgument {
+                    name: None,
+                    mode: ArgumentMode::In,
+                    data_type: data_type.to_string(),
+                })
+                .collect(),
+            return_type: Some("numeric".to_string()),
+            returns_set: false,
+        }
+    }
+
+    #[test]
+    fn overload_by_argument_types() {
+        let name = TableName::from("public.area");
+        let overloads = || {
+            vec![
+                routine(&["integer"]),
+                routine(&["text"]),
+                routine(&["numeric(10,2)", "numeric(10,2)"]),
+            ]
+        };
+        let text = Value::Text("circle".to_string());
+        let chosen = find_overload(&name, overloads(), &[&text]).expect("must be ok");
+        assert_eq!(chosen, routine(&["text"]));
+
+        let int = Value::Int(1);
+        let chosen = find_overload(&name, overloads(), &[&int]).expect("must be ok");
+        assert_eq!(chosen, routine(&["integer"]));
+
+        // the only overload with 2 arguments is called, even with integer values
+        let chosen = find_overload(&name, overloads(), &[&int, &int]).expect("must be ok");
+        assert_eq!(chosen, routine(&["numeric(10,2)", "numeric(10,2)"]));
+
+        let result = find_overload(&name, overloads(), &[&int, &int, &int]);
+        assert!(matches!(
+            result,
+            Err(DbError::DataError(DataError::RoutineNotFound(_, 3)))
+        ));
+    }
+
+    #[test]
+    fn ambiguous_overload() {
+        let name = TableName::from("public.area");
+        let overloads = vec![routine(&["integer"]), routine(&["bigint"])];
+        let result = find_overload(&name, overloads.clone(), &[&Value::Int(1)]);
+        assert!(matches!(
+            result,
+            Err(DbError::DataError(DataError::AmbiguousRoutine(_, 2, 1)))
+        ));
+        // an integer is widened to a decimal when no overload takes it as is
+        let overloads = vec![routine(&["numeric"]), routine(&["text"])];
+        let chosen = find_overload(&name, overloads, &[&Value::Int(1)]).expect("must be ok");
+        assert_eq!(chosen, routine(&["numeric"]));
+    }
+}
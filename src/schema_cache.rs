@@ -0,0 +1,208 @@
+//! An opt-in cache of the table definitions of a database.
+//!
+//! The cache is enabled per database url with `Pool::enable_schema_cache` and is shared by all the
+//! `EntityManager`s and `DaoManager`s created from that pool for that url.
+//! The cached tables are dropped when:
+//! - they are older than the configured time to live
+//! - a DDL statement is executed through the `EntityManager` or `DaoManager`
+//! - `SchemaCache::invalidate` is called
+//! - polling is enabled and the schema version of the database has changed, which catches the
+//!   changes made outside of rustorm
+use crate::{Database, DbError, TableDef, TableName};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+/// The settings of the schema cache
+#[derive(Debug, Clone, Default)]
+pub struct SchemaCacheConfig {
+    /// how long a table definition is used before it is loaded again, None to keep it until
+    /// it is invalidated
+    pub ttl: Option<Duration>,
+    /// how often the database is asked for its schema version to detect the changes made
+    /// outside of rustorm, None to never ask
+    pub poll_interval: Option<Duration>,
+}
+
+/// A handle to the cached table definitions of a database, cloning it shares the same cache
+#[derive(Clone)]
+pub struct SchemaCache(Arc<Mutex<CacheState>>);
+
+struct CacheState {
+    config: SchemaCacheConfig,
+    /// the tables keyed by their complete name, None for tables that doesn't exist
+    tables: HashMap<String, Cached<Option<TableDef>>>,
+    all_tables: Option<Cached<Vec<TableDef>>>,
+    schema_version: Option<String>,
+    last_poll: Option<Instant>,
+}
+
+struct Cached<T> {
+    value: T,
+    loaded_at: Instant,
+}
+
+impl<T> Cached<T> {
+    fn new(value: T) -> Self {
+        Cached {
+            value,
+            loaded_at: Instant::now(),
+        }
+    }
+
+    fn is_fresh(&self, ttl: Option<Duration>) -> bool {
+        match ttl {
+            Some(ttl) => self.loaded_at.elapsed() < ttl,
+            None => true,
+        }
+    }
+}
+
+impl SchemaCache {
+    pub fn new(config: SchemaCacheConfig) -> Self {
+        SchemaCache(Arc::new(Mutex::new(CacheState {
+            config,
+            tables: HashMap::new(),
+            all_tables: None,
+            schema_version: None,
+            last_poll: None,
+        })))
+    }
+
+    /// drop all the cached tables
+    pub fn invalidate(&self) {
+        let mut state = self.lock();
+        state.tables.clear();
+        state.all_tables = None;
+    }
+
+    /// drop the cached definition of this table
+    pub fn invalidate_table(&self, table_name: &TableName) {
+        let mut state = self.lock();
+        state.tables.remove(&table_name.complete_name());
+        state.all_tables = None;
+    }
+
+    /// get the table from the cache, loading it from the database when it is not cached yet
+    /// or has expired
+    pub fn get_table(
+        &self,
+        db: &mut dyn Database,
+        table_name: &TableName,
+    ) -> Result<Option<TableDef>, DbError> {
+        self.poll(db)?;
+        let key = table_name.complete_name();
+        {
+            let state = self.lock();
+            let ttl = state.config.ttl;
+            if let Some(cached) = state.tables.get(&key).filter(|c| c.is_fresh(ttl)) {
+                return Ok(cached.value.clone());
+            }
+        }
+        let table = db.get_table(table_name)?;
+        self.lock().tables.insert(key, Cached::new(table.clone()));
+        Ok(table)
+    }
+
+    /// get all the tables from the cache, loading them from the database when they are not
+    /// cached yet or have expired
+    pub fn get_all_tables(&self, db: &mut dyn Database) -> Result<Vec<TableDef>, DbError> {
+        self.poll(db)?;
+        {
+            let state = self.lock();
+            let ttl = state.config.ttl;
+            if let Some(cached) = state.all_tables.as_ref().filter(|c| c.is_fresh(ttl)) {
+                return Ok(cached.value.clone());
+            }
+        }
+        let tables = db.get_all_tables()?;
+        let mut state = self.lock();
+        for table in tables.iter() {
+            state
+                .tables
+                .insert(table.name.complete_name(), Cached::new(Some(table.clone())));
+        }
+        state.all_tables = Some(Cached::new(tables.clone()));
+        Ok(tables)
+    }
+
+    /// drop all the cached tables when this statement changes the schema
+    pub(crate) fn invalidate_if_ddl(&self, sql: &str) {
+        if is_ddl(sql) {
+            info!("schema changed by: {}", sql);
+            self.invalidate();
+        }
+    }
+
+    /// ask the database for its schema version when the poll interval has elapsed,
+    /// and drop all the cached tables when it has changed
+    fn poll(&self, db: &mut dyn Database) -> Result<(), DbError> {
+        let is_due = {
+            let state = self.lock();
+            match (state.config.poll_interval, state.last_poll) {
+                (None, _) => false,
+                (Some(_), None) => true,
+                (Some(interval), Some(last_poll)) => last_poll.elapsed() >= interval,
+            }
+        };
+        if is_due {
+            let schema_version = db.get_schema_version()?;
+            let mut state = self.lock();
+            if state.last_poll.is_some() && state.schema_version != schema_version {
+                info!("schema version changed to: {:?}", schema_version);
+                state.tables.clear();
+                state.all_tables = None;
+            }
+            state.schema_version = schema_version;
+            state.last_poll = Some(Instant::now());
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<CacheState> {
+        // the state is always left consistent, so it is still usable after a panic
+        match self.0.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// whether the sql statement creates, alters or drops objects of the database
+pub fn is_ddl(sql: &str) -> bool {
+    let keyword = sql
+        .trim_start_matches(|c: char| c.is_whitespace() || c == '(')
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or("");
+    ["CREATE", "ALTER", "DROP", "RENAME", "COMMENT"]
+        .iter()
+        .any(|ddl| keyword.eq_ignore_ascii_case(ddl))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ddl_statements() {
+        assert!(is_ddl("CREATE TABLE actor (actor_id int)"));
+        assert!(is_ddl("  alter table actor add column age int"));
+        assert!(is_ddl("\nDROP VIEW actor_info"));
+        assert!(is_ddl("COMMENT ON TABLE actor IS 'actors'"));
+        assert!(!is_ddl("SELECT * FROM actor"));
+        assert!(!is_ddl("INSERT INTO created (id) VALUES (1)"));
+        assert!(!is_ddl("UPDATE actor SET last_name = 'DROP'"));
+        assert!(!is_ddl(""));
+    }
+
+    #[test]
+    fn expired_entries() {
+        let cached = Cached::new(1);
+        assert!(cached.is_fresh(None));
+        assert!(cached.is_fresh(Some(Duration::from_secs(60))));
+        assert!(!cached.is_fresh(Some(Duration::from_secs(0))));
+    }
+}
@@ -0,0 +1,475 @@
+//! The dependency graph of the tables in the schema, derived from the foreign keys.
+//!
+//! An edge goes from the referring table to the referred table.
+//! example using the sakila database:
+//!     film_actor -> film
+//!     film_actor -> actor
+//!     film -> language
+//!
+//! The topological order lists the referred tables first, so inserting the records in that order
+//! will not violate the foreign key constraints. Deleting or truncating the tables should be done
+//! in the reverse order.
+use crate::{
+    table::{ForeignKey, ReferentialAction, TableDef},
+    TableName,
+};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A foreign key of `table` that refers to `foreign_table`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reference {
+    /// the table which contains the foreign key
+    pub table: TableName,
+    /// the table which is referred by the foreign key
+    pub foreign_table: TableName,
+    pub foreign_key: ForeignKey,
+}
+
+impl Reference {
+    /// the foreign key refers to its own table, such as a `parent_id` column
+    pub fn is_self_referencing(&self) -> bool {
+        self.table.complete_name() == self.foreign_table.complete_name()
+    }
+}
+
+/// The order in which the tables can be created or loaded.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TopologicalOrder {
+    /// the referred tables comes first before the tables that refers to them
+    pub tables: Vec<TableName>,
+    /// the references which has to be deferred in order to break the cycles,
+    /// ie: the foreign key columns has to be set to null first and updated after all the records
+    /// are inserted, or the constraint has to be checked at the end of the transaction.
+    /// Self referencing foreign keys are always included here.
+    pub deferred: Vec<Reference>,
+}
+
+impl TopologicalOrder {
+    /// the order in which the tables can be safely deleted or truncated
+    pub fn reversed(&self) -> Vec<TableName> {
+        self.tables.iter().rev().cloned().collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SchemaGraph {
+    tables: Vec<TableDef>,
+    /// the index of the table in `tables`, keyed by its complete name
+    index: BTreeMap<String, usize>,
+    /// all the foreign keys of the tables, including the ones that refers to tables which are not
+    /// in this graph
+    references: Vec<Reference>,
+}
+
+impl SchemaGraph {
+    pub fn new(tables: Vec<TableDef>) -> Self {
+        let index = tables
+            .iter()
+            .enumerate()
+            .map(|(i, table)| (table.complete_name(), i))
+            .collect();
+        let references = tables
+            .iter()
+            .flat_map(|table| {
+                table
+                    .get_foreign_keys()
+                    .into_iter()
+                    .map(move |fk| Reference {
+                        table: table.name.clone(),
+                        foreign_table: fk.foreign_table.clone(),
+                        foreign_key: fk.clone(),
+                    })
+            })
+            .collect();
+        SchemaGraph {
+            tables,
+            index,
+            references,
+        }
+    }
+
+    pub fn tables(&self) -> &[TableDef] {
+        &self.tables
+    }
+
+    pub fn get_table(&self, table_name: &TableName) -> Option<&TableDef> {
+        self.index
+            .get(&table_name.complete_name())
+            .map(|i| &self.tables[*i])
+    }
+
+    /// the foreign keys of this table
+    pub fn get_outgoing(&self, table_name: &TableName) -> Vec<&Reference> {
+        let name = table_name.complete_name();
+        self.references
+            .iter()
+            .filter(|r| r.table.complete_name() == name)
+            .collect()
+    }
+
+    /// the foreign keys of the other tables which refers to this table
+    pub fn get_incoming(&self, table_name: &TableName) -> Vec<&Reference> {
+        let name = table_name.complete_name();
+        self.references
+            .iter()
+            .filter(|r| r.foreign_table.complete_name() == name)
+            .collect()
+    }
+
+    /// the tables which has a foreign key to this table
+    pub fn get_referencing_tables(&self, table_name: &TableName) -> Vec<&TableName> {
+        let mut tables: Vec<&TableName> = vec![];
+        for reference in self.get_incoming(table_name) {
+            if !tables.contains(&&reference.table) {
+                tables.push(&reference.table);
+            }
+        }
+        tables
+    }
+
+    /// the foreign keys affected when a record of this table is deleted.
+    /// The `ON DELETE CASCADE` foreign keys are followed since the referring records are deleted
+    /// as well, the `SET NULL` and `SET DEFAULT` foreign keys are listed but not followed.
+    pub fn get_cascading_deletes(&self, table_name: &TableName) -> Vec<&Reference> {
+        let mut affected: Vec<&Reference> = vec![];
+        let mut visited = BTreeSet::new();
+        visited.insert(table_name.complete_name());
+        let mut pending = vec![table_name.clone()];
+        while let Some(current) = pending.pop() {
+            for reference in self.get_incoming(&current) {
+                match reference.foreign_key.on_delete {
+                    ReferentialAction::Cascade => {
+                        if visited.insert(reference.table.complete_name()) {
+                            pending.push(reference.table.clone());
+                        }
+                        affected.push(reference);
+                    }
+                    ReferentialAction::SetNull | ReferentialAction::SetDefault => {
+                        affected.push(reference)
+                    }
+                    ReferentialAction::NoAction | ReferentialAction::Restrict => (),
+                }
+            }
+        }
+        affected
+    }
+
+    /// the tables which has a foreign key to itself
+    pub fn get_self_referencing_tables(&self) -> Vec<&TableName> {
+        self.tables
+            .iter()
+            .map(|table| &table.name)
+            .filter(|table_name| self.is_self_referencing(table_name))
+            .collect()
+    }
+
+    pub fn is_self_referencing(&self, table_name: &TableName) -> bool {
+        self.get_outgoing(table_name)
+            .iter()
+            .any(|r| r.is_self_referencing())
+    }
+
+    /// the references which points to a table in this graph, excluding the self references
+    fn dependencies(&self) -> Vec<(usize, usize, &Reference)> {
+        self.references
+            .iter()
+            .filter(|r| !r.is_self_referencing())
+            .filter_map(|r| {
+                let from = self.index.get(&r.table.complete_name())?;
+                let to = self.index.get(&r.foreign_table.complete_name())?;
+                Some((*from, *to, r))
+            })
+            .collect()
+    }
+
+    /// group of tables which refers to each other in a cycle,
+    /// a self referencing table is a cycle of 1 table.
+    pub fn find_cycles(&self) -> Vec<Vec<TableName>> {
+        let mut adjacency: Vec<Vec<usize>> = vec![vec![]; self.tables.len()];
+        for (from, to, _) in self.dependencies() {
+            adjacency[from].push(to);
+        }
+        let mut tarjan = Tarjan::new(&adjacency);
+        for node in 0..self.tables.len() {
+            if tarjan.indexes[node].is_none() {
+                tarjan.connect(node);
+            }
+        }
+        let mut cycles = vec![];
+        for mut component in tarjan.components {
+            if component.len() > 1 || self.is_self_referencing(&self.tables[component[0]].name) {
+                component.sort();
+                cycles.push(
+                    component
+                        .iter()
+                        .map(|i| self.tables[*i].name.clone())
+                        .collect(),
+                );
+            }
+        }
+        cycles
+    }
+
+    pub fn has_cycle(&self) -> bool {
+        !self.find_cycles().is_empty()
+    }
+
+    /// order the tables such that the referred tables comes before the tables referring to it.
+    /// When there is a cycle, the table with the least unresolved foreign keys is taken first and
+    /// its unresolved foreign keys are deferred.
+    pub fn topological_order(&self) -> TopologicalOrder {
+        let len = self.tables.len();
+        let mut outgoing: Vec<Vec<(usize, &Reference)>> = vec![vec![]; len];
+        let mut incoming: Vec<Vec<usize>> = vec![vec![]; len];
+        let mut pending: Vec<usize> = vec![0; len];
+        for (from, to, reference) in self.dependencies() {
+            outgoing[from].push((to, reference));
+            incoming[to].push(from);
+            pending[from] += 1;
+        }
+        let mut deferred: Vec<Reference> = self
+            .references
+            .iter()
+            .filter(|r| r.is_self_referencing())
+            .cloned()
+            .collect();
+        // the remaining tables ordered by their number of unresolved foreign keys, then by the
+        // order they are listed to have a stable result
+        let mut remaining: BTreeSet<(usize, usize)> = (0..len).map(|i| (pending[i], i)).collect();
+        let mut done = vec![false; len];
+        let mut tables = Vec::with_capacity(len);
+        while let Some(&(count, next)) = remaining.iter().next() {
+            remaining.remove(&(count, next));
+            done[next] = true;
+            for (to, reference) in outgoing[next].iter() {
+                if !done[*to] {
+                    deferred.push((*reference).clone());
+                }
+            }
+            for from in incoming[next].iter() {
+                if !done[*from] {
+                    remaining.remove(&(pending[*from], *from));
+                    pending[*from] -= 1;
+                    remaining.insert((pending[*from], *from));
+                }
+            }
+            tables.push(self.tables[next].name.clone());
+        }
+        TopologicalOrder { tables, deferred }
+    }
+}
+
+/// Tarjan's strongly connected components
+struct Tarjan<'a> {
+    adjacency: &'a [Vec<usize>],
+    counter: usize,
+    indexes: Vec<Option<usize>>,
+    lowlinks: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    components: Vec<Vec<usize>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn new(adjacency: &'a [Vec<usize>]) -> Self {
+        let len = adjacency.len();
+        Tarjan {
+            adjacency,
+            counter: 0,
+            indexes: vec![None; len],
+            lowlinks: vec![0; len],
+            on_stack: vec![false; len],
+            stack: vec![],
+            components: vec![],
+        }
+    }
+
+    fn connect(&mut self, node: usize) {
+        self.indexes[node] = Some(self.counter);
+        self.lowlinks[node] = self.counter;
+        self.counter += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for next in self.adjacency[node].iter() {
+            match self.indexes[*next] {
+                None => {
+                    self.connect(*next);
+                    self.lowlinks[node] = self.lowlinks[node].min(self.lowlinks[*next]);
+                }
+                Some(index) if self.on_stack[*next] => {
+                    self.lowlinks[node] = self.lowlinks[node].min(index);
+                }
+                Some(_) => (),
+            }
+        }
+
+        if Some(self.lowlinks[node]) == self.indexes[node] {
+            let mut component = vec![];
+            while let Some(top) = self.stack.pop() {
+                self.on_stack[top] = false;
+                component.push(top);
+                if top == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        table::{MatchType, ReferentialAction, TableKey},
+        ColumnName,
+    };
+
+    fn table(name: &str, foreign: &[(&str, &str)]) -> TableDef {
+        TableDef {
+            name: TableName::from(name),
+            comment: None,
+            columns: vec![],
+            is_view: false,
+            table_key: foreign
+                .iter()
+                .map(|(column, foreign_table)| {
+                    TableKey::ForeignKey(ForeignKey {
+                        name: None,
+                        columns: vec![ColumnName::from(column)],
+                        foreign_table: TableName::from(foreign_table),
+                        referred_columns: vec![ColumnName::from("id")],
+                        on_delete: ReferentialAction::NoAction,
+                        on_update: ReferentialAction::NoAction,
+                        match_type: MatchType::Simple,
+                        is_deferrable: false,
+                        is_initially_deferred: false,
+                    })
+                })
+                .collect(),
+            indexes: vec![],
+        }
+    }
+
+    fn names(tables: &[TableName]) -> Vec<String> {
+        tables.iter().map(|t| t.complete_name()).collect()
+    }
+
+    fn sakila() -> SchemaGraph {
+        SchemaGraph::new(vec![
+            table("film_actor", &[("actor_id", "actor"), ("film_id", "film")]),
+            table("film", &[("language_id", "language")]),
+            table("actor", &[]),
+            table("language", &[]),
+        ])
+    }
+
+    #[test]
+    fn incoming_and_outgoing() {
+        let graph = sakila();
+        let outgoing = graph.get_outgoing(&TableName::from("film_actor"));
+        assert_eq!(outgoing.len(), 2);
+        let referencing = graph.get_referencing_tables(&TableName::from("film"));
+        assert_eq!(referencing, vec![&TableName::from("film_actor")]);
+        assert!(graph
+            .get_incoming(&TableName::from("film_actor"))
+            .is_empty());
+    }
+
+    #[test]
+    fn topological_order_without_cycle() {
+        let graph = sakila();
+        let order = graph.topological_order();
+        assert_eq!(
+            names(&order.tables),
+            vec!["actor", "language", "film", "film_actor"]
+        );
+        assert!(order.deferred.is_empty());
+        assert_eq!(
+            names(&order.reversed()),
+            vec!["film_actor", "film", "language", "actor"]
+        );
+        assert!(!graph.has_cycle());
+    }
+
+    #[test]
+    fn self_referencing() {
+        let graph = SchemaGraph::new(vec![
+            table(
+                "employee",
+                &[("manager_id", "employee"), ("dept_id", "dept")],
+            ),
+            table("dept", &[]),
+        ]);
+        assert_eq!(
+            graph.get_self_referencing_tables(),
+            vec![&TableName::from("employee")]
+        );
+        let order = graph.topological_order();
+        assert_eq!(names(&order.tables), vec!["dept", "employee"]);
+        assert_eq!(order.deferred.len(), 1);
+        assert!(order.deferred[0].is_self_referencing());
+        assert_eq!(graph.find_cycles(), vec![vec![TableName::from("employee")]]);
+    }
+
+    #[test]
+    fn cycle_is_broken_with_deferred_edges() {
+        let graph = SchemaGraph::new(vec![
+            table("a", &[("b_id", "b")]),
+            table("b", &[("c_id", "c")]),
+            table("c", &[("a_id", "a")]),
+            table("d", &[("a_id", "a")]),
+        ]);
+        let cycles = graph.find_cycles();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(names(&cycles[0]), vec!["a", "b", "c"]);
+        let order = graph.topological_order();
+        assert_eq!(names(&order.tables), vec!["a", "c", "b", "d"]);
+        assert_eq!(order.deferred.len(), 1);
+        assert_eq!(order.deferred[0].table.complete_name(), "a");
+        assert_eq!(order.deferred[0].foreign_table.complete_name(), "b");
+    }
+
+    #[test]
+    fn cascading_deletes() {
+        let mut tables = vec![
+            table("film_actor", &[("actor_id", "actor"), ("film_id", "film")]),
+            table("film_category", &[("film_id", "film")]),
+            table("inventory", &[("film_id", "film")]),
+            table("film", &[("language_id", "language")]),
+            table("actor", &[]),
+            table("language", &[]),
+        ];
+        let on_delete = |table: &mut TableDef, action| {
+            for key in table.table_key.iter_mut() {
+                if let TableKey::ForeignKey(fk) = key {
+                    fk.on_delete = action;
+                }
+            }
+        };
+        on_delete(&mut tables[0], ReferentialAction::Cascade);
+        on_delete(&mut tables[1], ReferentialAction::SetNull);
+        on_delete(&mut tables[3], ReferentialAction::Cascade);
+        let graph = SchemaGraph::new(tables);
+        let affected: Vec<String> = graph
+            .get_cascading_deletes(&TableName::from("language"))
+            .iter()
+            .map(|r| r.table.complete_name())
+            .collect();
+        assert_eq!(affected, vec!["film", "film_actor", "film_category"]);
+        assert_eq!(
+            graph.get_cascading_deletes(&TableName::from("actor")).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn reference_to_table_outside_the_graph_is_ignored() {
+        let graph = SchemaGraph::new(vec![table("film", &[("language_id", "language")])]);
+        let order = graph.topological_order();
+        assert_eq!(names(&order.tables), vec!["film"]);
+        assert!(order.deferred.is_empty());
+        assert_eq!(graph.get_outgoing(&TableName::from("film")).len(), 1);
+    }
+}
@@ -0,0 +1,92 @@
+//! The async sqlite driver.
+//!
+//! rusqlite is blocking, so each connection is owned by a dedicated thread which executes the
+//! statements sent to it with the same `SqliteDB` of the synchronous driver.
+use super::SqliteDB;
+use crate::{error::ConnectError, AsyncDatabase, Database, DbError, Rows, Value};
+use async_trait::async_trait;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::{sync::mpsc, thread};
+use tokio::sync::oneshot;
+
+/// a statement to be executed in the thread of the connection
+type Job = Box<dyn FnOnce(&mut SqliteDB) + Send>;
+
+/// the sender of the statements to the thread of the connection and the maximum number of
+/// parameters of the connection, the thread stops and returns the connection to the pool when
+/// this is dropped
+pub struct AsyncSqliteDB(mpsc::Sender<Job>, usize);
+
+impl AsyncSqliteDB {
+    /// get a connection from the pool in a new thread, which then waits for the statements
+    pub async fn connect(pool: r2d2::Pool<SqliteConnectionManager>) -> Result<Self, DbError> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let (connected, is_connected) = oneshot::channel();
+        thread::spawn(move || match pool.get() {
+            Ok(conn) => {
+                let mut db = SqliteDB(conn);
+                let _ = connected.send(Ok(db.max_params()));
+                for job in receiver {
+                    job(&mut db);
+                }
+            }
+            Err(e) => {
+                let _ = connected.send(Err(e));
+            }
+        });
+        let max_params = is_connected
+            .await
+            .map_err(|_| ConnectError::ConnectionClosed)?
+            .map_err(ConnectError::R2d2Error)?;
+        Ok(AsyncSqliteDB(sender, max_params))
+    }
+
+    /// the maximum number of parameters of a statement, read from the connection when connecting
+    pub fn max_params(&self) -> usize {
+        self.1
+    }
+
+    /// run the closure with the connection in its thread and wait for the result
+    pub(crate) async fn run<F, T>(&mut self, f: F) -> Result<T, DbError>
+    where
+        F: FnOnce(&mut dyn Database) -> Result<T, DbError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        self.0
+            .send(Box::new(move |db: &mut SqliteDB| {
+                let _ = sender.send(f(db));
+            }))
+            .map_err(|_| ConnectError::ConnectionClosed)?;
+        receiver.await.map_err(|_| ConnectError::ConnectionClosed)?
+    }
+}
+
+#[async_trait]
+impl AsyncDatabase for AsyncSqliteDB {
+    async fn begin_transaction(&mut self) -> Result<(), DbError> {
+        self.run(|db| db.begin_transaction()).await
+    }
+
+    async fn commit_transaction(&mut self) -> Result<(), DbError> {
+        self.run(|db| db.commit_transaction()).await
+    }
+
+    async fn rollback_transaction(&mut self) -> Result<(), DbError> {
+        self.run(|db| db.rollback_transaction()).await
+    }
+
+    async fn execute_sql_with_return(
+        &mut self,
+        sql: &str,
+        param: &[&Value],
+    ) -> Result<Rows, DbError> {
+        let sql = sql.to_string();
+        let param: Vec<Value> = param.iter().map(|v| (*v).clone()).collect();
+        self.run(move |db| {
+            let param: Vec<&Value> = param.iter().collect();
+            db.execute_sql_with_return(&sql, &param)
+        })
+        .await
+    }
+}
@@ -1,23 +1,41 @@
 #[cfg(feature = "db-auth")]
 use crate::db_auth::{Role, User};
 use crate::{
-    column::{Capacity, ColumnConstraint, ColumnDef, ColumnSpecification, Literal},
+    backend::{Backend, BackendPool},
+    column::{
+        Capacity, ColumnConstraint, ColumnDef, ColumnSpecification, ColumnStat, Literal,
+        MostCommonValue,
+    },
     common,
+    error::ConnectError,
+    error::ConvertError,
     error::DataOpError,
     error::PlatformError,
-    table::{ForeignKey, Key, SchemaContent, TableKey},
+    platform,
+    routine::{Routine, RoutineResult},
+    table::{
+        Check, ForeignKey, Index, IndexColumn, Key, MatchType, ReferentialAction, SchemaContent,
+        TableKey, TableStat,
+    },
+    trigger::{Trigger, TriggerEvent, TriggerTiming},
     types::SqlType,
-    util, ColumnName, Database, DatabaseName, DbError, FromDao, Rows, TableDef, TableName, ToValue,
-    Value,
+    util::{self, extract_index_expressions, extract_index_predicate, extract_view_select},
+    view::ViewDef,
+    ColumnName, DBPlatform, Database, DatabaseName, DbError, Dialect, FromDao, Rows, TableDef,
+    TableName, ToValue, Value,
 };
 
 use log::*;
 use r2d2::{self, ManageConnection};
 use r2d2_sqlite;
-use rusqlite;
+use rusqlite::{self, limits::Limit};
+use std::{collections::HashMap, sync::Arc};
 use thiserror::Error;
 use uuid::Uuid;
 
+#[cfg(feature = "with-sqlite-async")]
+pub(crate) mod async_sqlite;
+
 pub fn init_pool(
     db_url: &str,
 ) -> Result<r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, SqliteError> {
@@ -34,11 +52,49 @@ pub fn test_connection(db_url: &str) -> Result<(), SqliteError> {
     Ok(())
 }
 
+/// the linked sqlite library supports `INSERT ... RETURNING` since 3.35.0
+const RETURNING_VERSION: i32 = 3_035_000;
+
+/// whether the sqlite library linked at runtime supports `RETURNING`, which may be an older
+/// system library than the one the bindings were generated for
+pub(crate) fn supports_returning() -> bool {
+    rusqlite::version_number() >= RETURNING_VERSION
+}
+
+/// the built-in sqlite backend, the db_url is the path of the database file, ie: `sqlite://sakila.db`
+pub struct SqliteBackend;
+
+impl SqliteBackend {
+    fn db_file(db_url: &str) -> Result<String, DbError> {
+        let url = url::Url::parse(db_url).map_err(|e| ConnectError::ParseError(e.into()))?;
+        Ok(platform::database_file(&url))
+    }
+}
+
+impl Backend for SqliteBackend {
+    fn init_pool(&self, db_url: &str) -> Result<Arc<dyn BackendPool>, DbError> {
+        Ok(Arc::new(init_pool(&Self::db_file(db_url)?)?))
+    }
+
+    fn test_connection(&self, db_url: &str) -> Result<(), DbError> {
+        Ok(test_connection(&Self::db_file(db_url)?)?)
+    }
+}
+
+impl BackendPool for r2d2::Pool<r2d2_sqlite::SqliteConnectionManager> {
+    fn connect(&self) -> Result<DBPlatform, DbError> {
+        let conn = self.get().map_err(ConnectError::R2d2Error)?;
+        Ok(DBPlatform::Sqlite(Box::new(SqliteDB(conn))))
+    }
+}
+
 pub struct SqliteDB(pub r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager>);
 
-fn to_sq_value(val: &Value) -> rusqlite::types::Value {
+fn to_sq_value(val: &Value) -> Result<rusqlite::types::Value, DbError> {
     use num_traits::ToPrimitive;
-    match *val {
+    let unsupported =
+        || DbError::ConvertError(ConvertError::UnsupportedDataType(format!("{:?}", val)));
+    let value = match *val {
         Value::Text(ref v) => rusqlite::types::Value::Text(v.to_owned()),
         Value::Bool(v) => rusqlite::types::Value::Integer(if v { 1 } else { 0 }),
         Value::Tinyint(v) => rusqlite::types::Value::Integer(i64::from(v)),
@@ -50,7 +106,7 @@ fn to_sq_value(val: &Value) -> rusqlite::types::Value {
         Value::Double(v) => rusqlite::types::Value::Real(v),
         Value::BigDecimal(ref v) => match v.to_f64() {
             Some(v) => rusqlite::types::Value::Real(v as f64),
-            None => panic!("unable to convert bigdecimal"),
+            None => return Err(unsupported()),
         },
         Value::Blob(ref v) => rusqlite::types::Value::Blob(v.clone()),
         Value::Char(v) => rusqlite::types::Value::Text(format!("{}", v)),
@@ -59,20 +115,30 @@ fn to_sq_value(val: &Value) -> rusqlite::types::Value {
         Value::Date(ref v) => rusqlite::types::Value::Text(v.to_string()),
         Value::DateTime(ref v) => rusqlite::types::Value::Text(v.to_string()),
         Value::Nil => rusqlite::types::Value::Null,
-        _ => panic!("not yet handled: {:?}", val),
-    }
+        _ => return Err(unsupported()),
+    };
+    Ok(value)
 }
 
-fn to_sq_values(params: &[&Value]) -> Vec<rusqlite::types::Value> {
-    let mut sql_values = Vec::with_capacity(params.len());
-    for param in params {
-        let sq_val = to_sq_value(param);
-        sql_values.push(sq_val);
-    }
-    sql_values
+fn to_sq_values(params: &[&Value]) -> Result<Vec<rusqlite::types::Value>, DbError> {
+    params.iter().map(|param| to_sq_value(param)).collect()
 }
 
 impl Database for SqliteDB {
+    fn dialect(&self) -> Dialect {
+        Dialect::Sqlite
+    }
+
+    /// the `SQLITE_LIMIT_VARIABLE_NUMBER` of the connection, which depends on how the sqlite
+    /// library was compiled and can be lowered at runtime
+    fn max_params(&self) -> usize {
+        self.0.limit(Limit::SQLITE_LIMIT_VARIABLE_NUMBER) as usize
+    }
+
+    fn supports_returning(&self) -> bool {
+        supports_returning()
+    }
+
     fn begin_transaction(&mut self) -> Result<(), DbError> {
         self.execute_sql_with_return("BEGIN TRANSACTION", &[])?;
         Ok(())
@@ -90,9 +156,7 @@ impl Database for SqliteDB {
 
     fn execute_sql_with_return(&mut self, sql: &str, params: &[&Value]) -> Result<Rows, DbError> {
         info!("executing sql: {}", sql);
-        println!("executing sql: {}", sql);
         info!("params: {:?}", params);
-        println!("params: {:?}", params);
         let stmt = self.0.prepare(&sql);
 
         let column_names = if let Ok(ref stmt) = stmt {
@@ -104,7 +168,7 @@ impl Database for SqliteDB {
 
         match stmt {
             Ok(mut stmt) => {
-                let sq_values = to_sq_values(params);
+                let sq_values = to_sq_values(params)?;
                 let column_count = stmt.column_count();
                 let mut records = Rows::new(column_names);
                 if let Ok(mut rows) = stmt.query(sq_values) {
@@ -135,230 +199,9 @@ impl Database for SqliteDB {
         }
     }
 
-    #[allow(unused_variables)]
     fn get_table(&mut self, table_name: &TableName) -> Result<Option<TableDef>, DbError> {
-        #[derive(Debug)]
-        struct ColumnSimple {
-            name: String,
-            data_type: String,
-            not_null: bool,
-            default: Option<String>,
-            pk: bool,
-        }
-        impl ColumnSimple {
-            fn to_column(&self, table_name: &TableName) -> ColumnDef {
-                ColumnDef {
-                    table: table_name.clone(),
-                    name: ColumnName::from(&self.name),
-                    comment: None,
-                    specification: self.to_column_specification(),
-                    stat: None,
-                }
-            }
-
-            fn to_column_specification(&self) -> ColumnSpecification {
-                let (sql_type, capacity) = self.get_sql_type_capacity();
-                ColumnSpecification {
-                    sql_type,
-                    capacity,
-                    constraints: self.to_column_constraints(),
-                }
-            }
-
-            fn to_column_constraints(&self) -> Vec<ColumnConstraint> {
-                let (sql_type, _) = self.get_sql_type_capacity();
-                let mut constraints = vec![];
-                if self.not_null {
-                    constraints.push(ColumnConstraint::NotNull);
-                }
-                if let Some(ref default) = self.default {
-                    let ic_default = default.to_lowercase();
-                    let constraint = if ic_default == "null" {
-                        ColumnConstraint::DefaultValue(Literal::Null)
-                    } else if ic_default.starts_with("nextval") {
-                        ColumnConstraint::AutoIncrement(None)
-                    } else {
-                        let literal = match sql_type {
-                            SqlType::Bool => {
-                                let v: bool = default.parse().unwrap();
-                                Literal::Bool(v)
-                            }
-                            SqlType::Int
-                            | SqlType::Smallint
-                            | SqlType::Tinyint
-                            | SqlType::Bigint => {
-                                let v: Result<i64, _> = default.parse();
-                                match v {
-                                    Ok(v) => Literal::Integer(v),
-                                    Err(e) => {
-                                        panic!("error parsing to integer: {} error: {}", default, e)
-                                    }
-                                }
-                            }
-                            SqlType::Float | SqlType::Double | SqlType::Real | SqlType::Numeric => {
-                                // some defaults have cast type example: (0)::numeric
-                                let splinters = util::maybe_trim_parenthesis(&default)
-                                    .split("::")
-                                    .collect::<Vec<&str>>();
-                                let default_value = util::maybe_trim_parenthesis(splinters[0]);
-                                if default_value.to_lowercase() == "null" {
-                                    Literal::Null
-                                } else {
-                                    match util::eval_f64(default) {
-                                        Ok(val) => Literal::Double(val),
-                                        Err(e) => {
-                                            panic!(
-                                                "unable to evaluate default value expression: {}, error: {}",
-                                                default, e
-                                            )
-                                        }
-                                    }
-                                }
-                            }
-                            SqlType::Uuid => {
-                                if ic_default == "uuid_generate_v4()" {
-                                    Literal::UuidGenerateV4
-                                } else {
-                                    let v: Result<Uuid, _> = Uuid::parse_str(&default);
-                                    match v {
-                                        Ok(v) => Literal::Uuid(v),
-                                        Err(e) => panic!(
-                                            "error parsing to uuid: {} error: {}",
-                                            default, e
-                                        ),
-                                    }
-                                }
-                            }
-                            SqlType::Timestamp | SqlType::TimestampTz => {
-                                if ic_default == "now()"
-                                    || ic_default == "timezone('utc'::text, now())"
-                                    || ic_default == "current_timestamp"
-                                {
-                                    Literal::CurrentTimestamp
-                                } else {
-                                    panic!(
-                                        "timestamp other than now is not covered, got: {}",
-                                        ic_default
-                                    )
-                                }
-                            }
-                            SqlType::Date => {
-                                // timestamp converted to text then converted to date
-                                // is equivalent to today()
-                                if ic_default == "today()"
-                                    || ic_default == "now()"
-                                    || ic_default == "('now'::text)::date"
-                                {
-                                    Literal::CurrentDate
-                                } else {
-                                    panic!(
-                                        "date other than today, now is not covered in {:?}",
-                                        self
-                                    )
-                                }
-                            }
-                            SqlType::Varchar
-                            | SqlType::Char
-                            | SqlType::Tinytext
-                            | SqlType::Mediumtext
-                            | SqlType::Text => Literal::String(default.to_owned()),
-                            SqlType::Enum(_name, _choices) => Literal::String(default.to_owned()),
-                            _ => panic!("not convered: {:?}", sql_type),
-                        };
-                        ColumnConstraint::DefaultValue(literal)
-                    };
-                    constraints.push(constraint);
-                }
-                constraints
-            }
-
-            fn get_sql_type_capacity(&self) -> (SqlType, Option<Capacity>) {
-                let (dtype, capacity) = common::extract_datatype_with_capacity(&self.data_type);
-                let sql_type = match &*dtype {
-                    "int" | "integer" => SqlType::Int,
-                    "smallint" => SqlType::Smallint,
-                    "varchar" => SqlType::Text,
-                    "character varying" => SqlType::Text,
-                    "decimal" => SqlType::Double,
-                    "timestamp" => SqlType::Timestamp,
-                    "numeric" => SqlType::Numeric,
-                    "char" => match capacity {
-                        None => SqlType::Char,
-                        Some(Capacity::Limit(1)) => SqlType::Char,
-                        Some(_) => SqlType::Varchar,
-                    },
-                    "blob" => SqlType::Blob,
-                    "" => SqlType::Text,
-                    _ => {
-                        if dtype.contains("text") {
-                            SqlType::Text
-                        } else {
-                            panic!("not yet handled: {:?}", dtype)
-                        }
-                    }
-                };
-                (sql_type, capacity)
-            }
-        }
-        macro_rules! unwrap_ok_some {
-            ($var:ident) => {
-                match $var {
-                    Ok($var) => match $var {
-                        Some($var) => $var,
-                        None => panic!("expecting {} to have a value", stringify!($var)),
-                    },
-                    Err(_e) => panic!("expecting {} to be not error", stringify!($var)),
-                }
-            };
-        }
-        let sql = format!("PRAGMA table_info({});", table_name.complete_name());
-        let result = self.execute_sql_with_return(&sql, &[])?;
-        let mut primary_columns = vec![];
-        let mut columns = vec![];
-        for dao in result.iter() {
-            let name: Result<Option<String>, _> = dao.get("name");
-            let name = unwrap_ok_some!(name);
-            let data_type: Result<Option<String>, _> = dao.get("type");
-            let data_type = unwrap_ok_some!(data_type).to_lowercase();
-            let not_null: Result<Option<i64>, _> = dao.get("notnull");
-            let not_null = unwrap_ok_some!(not_null) != 0;
-            let pk: Result<Option<i64>, _> = dao.get("pk");
-            let pk = unwrap_ok_some!(pk) != 0;
-            if pk {
-                primary_columns.push(ColumnName::from(&name));
-            }
-            let default = dao.0.get("dflt_value").map(|v| match *v {
-                Value::Text(ref v) => v.to_owned(),
-                Value::Nil => "null".to_string(),
-                _ => panic!("Expecting a text value, got: {:?}", v),
-            });
-            let simple = ColumnSimple {
-                name,
-                data_type,
-                default,
-                pk,
-                not_null,
-            };
-            columns.push(simple.to_column(table_name));
-        }
-        let primary_key = Key {
-            name: None,
-            columns: primary_columns,
-        };
-        info!("primary key: {:#?}", primary_key);
-        let foreign_keys = get_foreign_keys(&mut *self, table_name)?;
-        let table_key_foreign: Vec<TableKey> =
-            foreign_keys.into_iter().map(TableKey::ForeignKey).collect();
-        let mut table_keys = vec![TableKey::PrimaryKey(primary_key)];
-        table_keys.extend(table_key_foreign);
-        let table = TableDef {
-            name: table_name.clone(),
-            comment: None, // TODO: need to extract comment from the create_sql
-            columns,
-            is_view: false,
-            table_key: table_keys,
-        };
-        Ok(Some(table))
+        let tables = get_scoped_tables(&mut *self, Some(table_name))?;
+        Ok(tables.into_iter().next())
     }
 
     fn get_tablenames(&mut self) -> Result<Vec<TableName>, DbError> {
@@ -382,11 +225,31 @@ impl Database for SqliteDB {
     }
 
     fn get_all_tables(&mut self) -> Result<Vec<TableDef>, DbError> {
-        let tablenames = self.get_tablenames()?;
-        Ok(tablenames
-            .iter()
-            .filter_map(|tablename| self.get_table(tablename).ok().flatten())
-            .collect())
+        match get_scoped_tables(&mut *self, None) {
+            Ok(tables) => Ok(tables),
+            // a view that refers to a missing table fails the pragma of every table,
+            // load the tables one by one to skip the broken views
+            Err(e) => {
+                let broken_views = get_broken_views(&mut *self)?;
+                if broken_views.is_empty() {
+                    return Err(e);
+                }
+                let mut tables = vec![];
+                for tablename in self.get_tablenames()? {
+                    if broken_views.contains(&tablename) {
+                        warn!(
+                            "skipping the view {} which refers to a missing table or column",
+                            tablename.complete_name()
+                        );
+                        continue;
+                    }
+                    if let Some(table) = self.get_table(&tablename)? {
+                        tables.push(table);
+                    }
+                }
+                Ok(tables)
+            }
+        }
     }
 
     fn get_grouped_tables(&mut self) -> Result<Vec<SchemaContent>, DbError> {
@@ -460,6 +323,143 @@ impl Database for SqliteDB {
             Ok(None)
         }
     }
+    /// the row count is taken from `sqlite_stat1` which is only available after `ANALYZE` is run,
+    /// while the sizes are only available when sqlite is compiled with the `dbstat` virtual table
+    fn get_table_stats(&mut self, table_names: &[TableName]) -> Result<Vec<TableStat>, DbError> {
+        let row_counts: Vec<(String, i64)> =
+            if get_create_sql(&mut *self, "table", "sqlite_stat1")?.is_some() {
+                self.execute_sql_with_return("SELECT tbl, stat FROM sqlite_stat1", &[])?
+                    .iter()
+                    .filter_map(|row| {
+                        let tbl: String = row.get("tbl").expect("tbl");
+                        let stat: String = row.get("stat").expect("stat");
+                        let row_count = stat.split_whitespace().next()?.parse().ok()?;
+                        Some((tbl, row_count))
+                    })
+                    .collect()
+            } else {
+                vec![]
+            };
+        let sql = "SELECT sqlite_master.tbl_name AS tbl, \
+                   SUM(CASE WHEN sqlite_master.type = 'table' THEN dbstat.pgsize ELSE 0 END) AS table_size, \
+                   SUM(CASE WHEN sqlite_master.type = 'index' THEN dbstat.pgsize ELSE 0 END) AS index_size \
+                   FROM dbstat \
+                   JOIN sqlite_master ON sqlite_master.name = dbstat.name \
+                   GROUP BY sqlite_master.tbl_name";
+        let sizes: Vec<(String, i64, i64)> = self
+            .execute_sql_with_return(sql, &[])
+            .map(|rows| {
+                rows.iter()
+                    .map(|row| {
+                        (
+                            row.get("tbl").expect("tbl"),
+                            row.get("table_size").expect("table_size"),
+                            row.get("index_size").expect("index_size"),
+                        )
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut stats = Vec::with_capacity(table_names.len());
+        for table_name in table_names {
+            if get_create_sql(&mut *self, "table", &table_name.name)?.is_none() {
+                continue;
+            }
+            let size = sizes.iter().find(|(tbl, _, _)| *tbl == table_name.name);
+            stats.push(TableStat {
+                table: table_name.clone(),
+                row_count: row_counts
+                    .iter()
+                    .find(|(tbl, _)| *tbl == table_name.name)
+                    .map(|(_, row_count)| *row_count),
+                is_exact: false,
+                table_size: size.map(|(_, table_size, _)| *table_size),
+                index_size: size.map(|(_, _, index_size)| *index_size),
+                // sqlite doesn't record when the database is vacuumed or analyzed
+                last_vacuum: None,
+                last_analyze: None,
+            });
+        }
+        Ok(stats)
+    }
+
+    /// sqlite doesn't keep track of the view dependencies,
+    /// the tables and views which names appear in the definition are listed instead
+    fn get_view_definition(&mut self, view_name: &TableName) -> Result<Option<ViewDef>, DbError> {
+        let create_sql = match get_create_sql(&mut *self, "view", &view_name.name)? {
+            Some(create_sql) => create_sql,
+            None => return Ok(None),
+        };
+        let definition = extract_view_select(&create_sql).to_string();
+        let words = util::identifiers(&definition);
+        let dependencies = self
+            .get_tablenames()?
+            .into_iter()
+            .filter(|table| table.name != view_name.name)
+            .filter(|table| words.iter().any(|w| w.eq_ignore_ascii_case(&table.name)))
+            .collect();
+        Ok(Some(ViewDef {
+            name: view_name.clone(),
+            definition,
+            // views in sqlite are read-only, unless there are INSTEAD OF triggers
+            is_updatable: false,
+            is_materialized: false,
+            dependencies,
+        }))
+    }
+
+    fn get_triggers(&mut self, table_name: &TableName) -> Result<Vec<Trigger>, DbError> {
+        let sql = "SELECT name, sql FROM sqlite_master WHERE type = 'trigger' AND tbl_name = $1 \
+                   ORDER BY name";
+        let rows = self.execute_sql_with_return(sql, &[&table_name.name.to_value()])?;
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let name: String = row.get("name").expect("must have a trigger name");
+                let create_sql: String = row.get("sql").expect("must have sql");
+                parse_trigger(name, table_name, create_sql)
+            })
+            .collect())
+    }
+
+    /// sqlite increments the schema version on every change of the schema
+    fn get_schema_version(&mut self) -> Result<Option<String>, DbError> {
+        let rows = self.execute_sql_with_return("PRAGMA schema_version", &[])?;
+        Ok(rows.iter().next().map(|row| {
+            let version: i64 = row.get("schema_version").expect("schema_version");
+            version.to_string()
+        }))
+    }
+
+    fn refresh_materialized_view(
+        &mut self,
+        _view_name: &TableName,
+        _concurrently: bool,
+    ) -> Result<(), DbError> {
+        Err(DbError::UnsupportedOperation(
+            "sqlite doesn't have materialized views".to_string(),
+        ))
+    }
+
+    /// sqlite doesn't have stored functions and procedures
+    fn get_routines(&mut self) -> Result<Vec<Routine>, DbError> {
+        Ok(vec![])
+    }
+
+    fn get_routine(&mut self, _routine_name: &TableName) -> Result<Vec<Routine>, DbError> {
+        Ok(vec![])
+    }
+
+    fn call_routine(
+        &mut self,
+        _routine: &Routine,
+        _args: &[&Value],
+    ) -> Result<RoutineResult, DbError> {
+        Err(DbError::UnsupportedOperation(
+            "sqlite doesn't have stored functions and procedures".to_string(),
+        ))
+    }
 }
 
 fn get_table_names(db: &mut dyn Database, kind: &str) -> Result<Vec<TableName>, DbError> {
@@ -483,26 +483,386 @@ fn get_table_names(db: &mut dyn Database, kind: &str) -> Result<Vec<TableName>,
     Ok(table_names)
 }
 
-/// get the foreign keys of table
-fn get_foreign_keys(db: &mut dyn Database, table: &TableName) -> Result<Vec<ForeignKey>, DbError> {
-    let sql = format!("PRAGMA foreign_key_list({});", table.complete_name());
-    #[derive(Debug, FromDao)]
-    struct ForeignSimple {
-        id: i64,
-        table: String,
-        from: String,
-        to: String,
-    }
-    let result: Vec<ForeignSimple> = db
-        .execute_sql_with_return(&sql, &[])?
+/// the views which can not be compiled, since they refer to a missing table or column
+fn get_broken_views(db: &mut dyn Database) -> Result<Vec<TableName>, DbError> {
+    let mut broken_views = vec![];
+    for view_name in get_table_names(db, "view")? {
+        let sql = format!(
+            "SELECT * FROM {} LIMIT 0",
+            view_name.quoted_complete_name(Dialect::Sqlite)?
+        );
+        if db.execute_sql_with_return(&sql, &[]).is_err() {
+            broken_views.push(view_name);
+        }
+    }
+    Ok(broken_views)
+}
+
+/// the columns of a table from `PRAGMA table_info`
+#[derive(Debug)]
+struct ColumnSimple {
+    name: String,
+    data_type: String,
+    not_null: bool,
+    default: Option<String>,
+    pk: bool,
+}
+impl ColumnSimple {
+    fn to_column(&self, table_name: &TableName) -> ColumnDef {
+        ColumnDef {
+            table: table_name.clone(),
+            name: ColumnName::from(&self.name),
+            comment: None,
+            specification: self.to_column_specification(),
+            stat: None,
+        }
+    }
+
+    fn to_column_specification(&self) -> ColumnSpecification {
+        let (sql_type, capacity) = self.get_sql_type_capacity();
+        ColumnSpecification {
+            sql_type,
+            capacity,
+            constraints: self.to_column_constraints(),
+        }
+    }
+
+    fn to_column_constraints(&self) -> Vec<ColumnConstraint> {
+        let (sql_type, _) = self.get_sql_type_capacity();
+        let mut constraints = vec![];
+        if self.not_null {
+            constraints.push(ColumnConstraint::NotNull);
+        }
+        if let Some(ref default) = self.default {
+            let ic_default = default.to_lowercase();
+            let constraint = if ic_default == "null" {
+                ColumnConstraint::DefaultValue(Literal::Null)
+            } else if ic_default.starts_with("nextval") {
+                ColumnConstraint::AutoIncrement(None)
+            } else {
+                let literal = match sql_type {
+                    SqlType::Bool => {
+                        let v: bool = default.parse().unwrap();
+                        Literal::Bool(v)
+                    }
+                    SqlType::Int | SqlType::Smallint | SqlType::Tinyint | SqlType::Bigint => {
+                        let v: Result<i64, _> = default.parse();
+                        match v {
+                            Ok(v) => Literal::Integer(v),
+                            Err(e) => {
+                                panic!("error parsing to integer: {} error: {}", default, e)
+                            }
+                        }
+                    }
+                    SqlType::Float | SqlType::Double | SqlType::Real | SqlType::Numeric => {
+                        // some defaults have cast type example: (0)::numeric
+                        let splinters = util::maybe_trim_parenthesis(&default)
+                            .split("::")
+                            .collect::<Vec<&str>>();
+                        let default_value = util::maybe_trim_parenthesis(splinters[0]);
+                        if default_value.to_lowercase() == "null" {
+                            Literal::Null
+                        } else {
+                            match util::eval_f64(default) {
+                                Ok(val) => Literal::Double(val),
+                                Err(e) => {
+                                    panic!(
+                                        "unable to evaluate default value expression: {}, error: {}",
+                                        default, e
+                                    )
+                                }
+                            }
+                        }
+                    }
+                    SqlType::Uuid => {
+                        if ic_default == "uuid_generate_v4()" {
+                            Literal::UuidGenerateV4
+                        } else {
+                            let v: Result<Uuid, _> = Uuid::parse_str(&default);
+                            match v {
+                                Ok(v) => Literal::Uuid(v),
+                                Err(e) => panic!("error parsing to uuid: {} error: {}", default, e),
+                            }
+                        }
+                    }
+                    SqlType::Timestamp | SqlType::TimestampTz => {
+                        if ic_default == "now()"
+                            || ic_default == "timezone('utc'::text, now())"
+                            || ic_default == "current_timestamp"
+                        {
+                            Literal::CurrentTimestamp
+                        } else {
+                            panic!(
+                                "timestamp other than now is not covered, got: {}",
+                                ic_default
+                            )
+                        }
+                    }
+                    SqlType::Date => {
+                        // timestamp converted to text then converted to date
+                        // is equivalent to today()
+                        if ic_default == "today()"
+                            || ic_default == "now()"
+                            || ic_default == "('now'::text)::date"
+                        {
+                            Literal::CurrentDate
+                        } else {
+                            panic!("date other than today, now is not covered in {:?}", self)
+                        }
+                    }
+                    SqlType::Varchar
+                    | SqlType::Char
+                    | SqlType::Tinytext
+                    | SqlType::Mediumtext
+                    | SqlType::Text => Literal::String(default.to_owned()),
+                    SqlType::Enum(_name, _choices) => Literal::String(default.to_owned()),
+                    _ => panic!("not convered: {:?}", sql_type),
+                };
+                ColumnConstraint::DefaultValue(literal)
+            };
+            constraints.push(constraint);
+        }
+        constraints
+    }
+
+    fn get_sql_type_capacity(&self) -> (SqlType, Option<Capacity>) {
+        let (dtype, capacity) = common::extract_datatype_with_capacity(&self.data_type);
+        let sql_type = match &*dtype {
+            "int" | "integer" => SqlType::Int,
+            "smallint" => SqlType::Smallint,
+            "varchar" => SqlType::Text,
+            "character varying" => SqlType::Text,
+            "decimal" => SqlType::Double,
+            "timestamp" => SqlType::Timestamp,
+            "numeric" => SqlType::Numeric,
+            "char" => match capacity {
+                None => SqlType::Char,
+                Some(Capacity::Limit(1)) => SqlType::Char,
+                Some(_) => SqlType::Varchar,
+            },
+            "blob" => SqlType::Blob,
+            "" => SqlType::Text,
+            _ => {
+                if dtype.contains("text") {
+                    SqlType::Text
+                } else {
+                    panic!("not yet handled: {:?}", dtype)
+                }
+            }
+        };
+        (sql_type, capacity)
+    }
+}
+macro_rules! unwrap_ok_some {
+    ($var:ident) => {
+        match $var {
+            Ok($var) => match $var {
+                Some($var) => $var,
+                None => panic!("expecting {} to have a value", stringify!($var)),
+            },
+            Err(_e) => panic!("expecting {} to be not error", stringify!($var)),
+        }
+    };
+}
+
+/// The condition for the queries on `sqlite_master` aliased as `m`,
+/// `$1` is the name of the table to restrict the result to, or NULL for all the tables
+const SCOPE: &str = "($1 IS NULL OR m.tbl_name = $1 COLLATE NOCASE)";
+
+fn scope_param(table: Option<&TableName>) -> Value {
+    match table {
+        Some(table) => table.name.to_value(),
+        None => Value::Nil,
+    }
+}
+
+/// Load the tables and views, or only the one named `table`.
+/// The table-valued pragma functions are joined with `sqlite_master` so the columns,
+/// foreign keys and indexes of all the tables are each loaded in a single query,
+/// instead of issuing a set of pragmas for every table.
+fn get_scoped_tables(
+    db: &mut dyn Database,
+    table: Option<&TableName>,
+) -> Result<Vec<TableDef>, DbError> {
+    let scope = scope_param(table);
+    let sql = format!(
+        "SELECT m.type, m.name, m.sql FROM sqlite_master AS m WHERE {}",
+        SCOPE
+    );
+    let objects: Vec<(String, String, Option<String>)> = db
+        .execute_sql_with_return(&sql, &[&scope])?
         .iter()
-        .map(|row| ForeignSimple {
-            id: row.get("id").expect("id"),
-            table: row.get("table").expect("table"),
-            from: row.get("from").expect("from"),
-            to: row.get("to").expect("to"),
+        .map(|row| {
+            (
+                row.get("type").expect("type"),
+                row.get("name").expect("name"),
+                row.get_opt("sql").expect("sql"),
+            )
         })
         .collect();
+    // tables, views and indexes share the same namespace
+    let create_sqls: HashMap<&str, &str> = objects
+        .iter()
+        .filter_map(|(_, name, sql)| sql.as_ref().map(|sql| (name.as_str(), sql.as_str())))
+        .collect();
+
+    let mut table_columns = get_scoped_columns(db, &scope)?;
+    let mut foreign_keys = get_scoped_foreign_keys(db, &scope, &create_sqls)?;
+    let mut indexes = get_scoped_indexes(db, &scope, &create_sqls)?;
+    let (stat1, stat4) = get_stat_rows(db, &scope)?;
+
+    let mut tables = vec![];
+    for (kind, name, create_sql) in objects.iter() {
+        if kind != "table" && kind != "view" {
+            continue;
+        }
+        let table_name = TableName::from(name);
+        let column_simples = table_columns.remove(name).unwrap_or_default();
+        let primary_columns: Vec<ColumnName> = column_simples
+            .iter()
+            .filter(|simple| simple.pk)
+            .map(|simple| ColumnName::from(&simple.name))
+            .collect();
+        let mut columns: Vec<ColumnDef> = column_simples
+            .iter()
+            .map(|simple| simple.to_column(&table_name))
+            .collect();
+        let mut table_keys = vec![TableKey::PrimaryKey(Key {
+            name: None,
+            columns: primary_columns,
+        })];
+        table_keys.extend(
+            foreign_keys
+                .remove(name)
+                .unwrap_or_default()
+                .into_iter()
+                .map(TableKey::ForeignKey),
+        );
+        if kind == "table" {
+            if let Some(create_sql) = create_sql {
+                let column_names: Vec<ColumnName> =
+                    columns.iter().map(|c| c.name.clone()).collect();
+                let checks = extract_checks(create_sql, &column_names);
+                table_keys.extend(checks.into_iter().map(TableKey::Check));
+            }
+        }
+        let indexes = indexes.remove(name).unwrap_or_default();
+        for (column_name, stat) in get_column_stats(&table_name, &indexes, &stat1, &stat4) {
+            if let Some(column) = columns.iter_mut().find(|c| c.name == column_name) {
+                column.stat = Some(stat);
+            }
+        }
+        tables.push(TableDef {
+            name: table_name,
+            comment: None, // TODO: need to extract comment from the create_sql
+            columns,
+            is_view: kind == "view",
+            table_key: table_keys,
+            indexes,
+        });
+    }
+    Ok(tables)
+}
+
+/// get the columns of the tables in the scope using `pragma_table_info`, grouped by table name
+fn get_scoped_columns(
+    db: &mut dyn Database,
+    scope: &Value,
+) -> Result<HashMap<String, Vec<ColumnSimple>>, DbError> {
+    let sql = format!(
+        "SELECT m.name AS table_name, p.name, p.type, p.\"notnull\", p.dflt_value, p.pk \
+         FROM sqlite_master AS m JOIN pragma_table_info(m.name) AS p \
+         WHERE m.type IN ('table', 'view') AND {} ORDER BY m.name, p.cid",
+        SCOPE
+    );
+    let result = db.execute_sql_with_return(&sql, &[scope])?;
+    let mut columns: HashMap<String, Vec<ColumnSimple>> = HashMap::new();
+    for dao in result.iter() {
+        let table_name: String = dao.get("table_name").expect("table_name");
+        let name: Result<Option<String>, _> = dao.get("name");
+        let name = unwrap_ok_some!(name);
+        let data_type: Result<Option<String>, _> = dao.get("type");
+        let data_type = unwrap_ok_some!(data_type).to_lowercase();
+        let not_null: Result<Option<i64>, _> = dao.get("notnull");
+        let not_null = unwrap_ok_some!(not_null) != 0;
+        let pk: Result<Option<i64>, _> = dao.get("pk");
+        let pk = unwrap_ok_some!(pk) != 0;
+        let default = dao.0.get("dflt_value").map(|v| match *v {
+            Value::Text(ref v) => v.to_owned(),
+            Value::Nil => "null".to_string(),
+            _ => panic!("Expecting a text value, got: {:?}", v),
+        });
+        let simple = ColumnSimple {
+            name,
+            data_type,
+            default,
+            pk,
+            not_null,
+        };
+        columns.entry(table_name).or_default().push(simple);
+    }
+    Ok(columns)
+}
+
+/// a row of `PRAGMA foreign_key_list`, one for each column of the foreign key
+#[derive(Debug, FromDao)]
+struct ForeignSimple {
+    id: i64,
+    table: String,
+    from: String,
+    to: String,
+    on_update: String,
+    on_delete: String,
+    match_type: String,
+}
+
+/// get the foreign keys of the tables in the scope using `pragma_foreign_key_list`,
+/// grouped by table name
+fn get_scoped_foreign_keys(
+    db: &mut dyn Database,
+    scope: &Value,
+    create_sqls: &HashMap<&str, &str>,
+) -> Result<HashMap<String, Vec<ForeignKey>>, DbError> {
+    let sql = format!(
+        "SELECT m.name AS table_name, p.id, p.\"table\", p.\"from\", p.\"to\", \
+         p.on_update, p.on_delete, p.\"match\" \
+         FROM sqlite_master AS m JOIN pragma_foreign_key_list(m.name) AS p \
+         WHERE m.type = 'table' AND {} ORDER BY m.name, p.id, p.seq",
+        SCOPE
+    );
+    let mut foreign_simples: HashMap<String, Vec<ForeignSimple>> = HashMap::new();
+    for row in db.execute_sql_with_return(&sql, &[scope])?.iter() {
+        let table_name: String = row.get("table_name").expect("table_name");
+        foreign_simples
+            .entry(table_name)
+            .or_default()
+            .push(ForeignSimple {
+                id: row.get("id").expect("id"),
+                table: row.get("table").expect("table"),
+                from: row.get("from").expect("from"),
+                to: row.get("to").expect("to"),
+                on_update: row.get("on_update").expect("on_update"),
+                on_delete: row.get("on_delete").expect("on_delete"),
+                match_type: row.get("match").expect("match"),
+            });
+    }
+    Ok(foreign_simples
+        .into_iter()
+        .map(|(table_name, result)| {
+            let create_sql = create_sqls.get(table_name.as_str()).cloned();
+            let foreign_keys = to_foreign_keys(&result, create_sql);
+            (table_name, foreign_keys)
+        })
+        .collect())
+}
+
+/// group the rows of `PRAGMA foreign_key_list` of a table into foreign keys,
+/// the deferrability is extracted from the create sql of the table since the pragma doesn't have it
+fn to_foreign_keys(result: &[ForeignSimple], create_sql: Option<&str>) -> Vec<ForeignKey> {
+    let deferrables = match create_sql {
+        Some(create_sql) => extract_foreign_key_deferrables(create_sql),
+        None => vec![],
+    };
     let mut foreign_tables: Vec<(i64, TableName)> = result
         .iter()
         .map(|f| (f.id, TableName::from(&f.table)))
@@ -515,15 +875,611 @@ fn get_foreign_keys(db: &mut dyn Database, table: &TableName) -> Result<Vec<Fore
             .iter()
             .map(|f| (ColumnName::from(&f.from), ColumnName::from(&f.to)))
             .unzip();
+        let (is_deferrable, is_initially_deferred) = deferrables
+            .iter()
+            .find(|(columns, _, _)| {
+                columns.len() == local_columns.len()
+                    && columns
+                        .iter()
+                        .zip(local_columns.iter())
+                        .all(|(a, b)| a.eq_ignore_ascii_case(&b.name))
+            })
+            .map(|(_, deferrable, deferred)| (*deferrable, *deferred))
+            .unwrap_or((false, false));
+        let first = foreigns[0];
         let foreign_key = ForeignKey {
             name: None,
             columns: local_columns,
             foreign_table,
             referred_columns,
+            on_delete: ReferentialAction::from_sql(&first.on_delete),
+            on_update: ReferentialAction::from_sql(&first.on_update),
+            match_type: MatchType::from_sql(&first.match_type),
+            is_deferrable,
+            is_initially_deferred,
         };
         foreign_keys.push(foreign_key);
     }
-    Ok(foreign_keys)
+    foreign_keys
+}
+
+/// get the indexes of the tables in the scope using `pragma_index_list` and `pragma_index_xinfo`,
+/// grouped by table name.
+/// The expressions and the where clause of partial indexes are extracted from the create sql
+/// since the pragmas doesn't provide them.
+fn get_scoped_indexes(
+    db: &mut dyn Database,
+    scope: &Value,
+    create_sqls: &HashMap<&str, &str>,
+) -> Result<HashMap<String, Vec<Index>>, DbError> {
+    #[derive(Debug, FromDao)]
+    struct IndexSimple {
+        name: String,
+        unique: i64,
+        origin: String,
+        partial: i64,
+    }
+    #[derive(Debug, FromDao)]
+    struct IndexColumnSimple {
+        cid: i64,
+        name: Option<String>,
+        key: i64,
+    }
+    let sql = format!(
+        "SELECT m.name AS table_name, il.name AS index_name, il.\"unique\", il.origin, il.partial, \
+         ix.cid, ix.name AS column_name, ix.key \
+         FROM sqlite_master AS m \
+         JOIN pragma_index_list(m.name) AS il \
+         JOIN pragma_index_xinfo(il.name) AS ix \
+         WHERE m.type = 'table' AND {} ORDER BY m.name, il.name, ix.seqno",
+        SCOPE
+    );
+    let mut index_simples: Vec<(String, IndexSimple, Vec<IndexColumnSimple>)> = vec![];
+    for row in db.execute_sql_with_return(&sql, &[scope])?.iter() {
+        let table_name: String = row.get("table_name").expect("table_name");
+        let index_name: String = row.get("index_name").expect("index_name");
+        let column_simple = IndexColumnSimple {
+            cid: row.get("cid").expect("cid"),
+            name: row.get_opt("column_name").expect("column_name"),
+            key: row.get("key").expect("key"),
+        };
+        match index_simples.last_mut() {
+            Some((last_table, simple, column_simples))
+                if *last_table == table_name && simple.name == index_name =>
+            {
+                column_simples.push(column_simple)
+            }
+            _ => index_simples.push((
+                table_name,
+                IndexSimple {
+                    name: index_name,
+                    unique: row.get("unique").expect("unique"),
+                    origin: row.get("origin").expect("origin"),
+                    partial: row.get("partial").expect("partial"),
+                },
+                vec![column_simple],
+            )),
+        }
+    }
+
+    let mut indexes: HashMap<String, Vec<Index>> = HashMap::new();
+    for (table_name, simple, column_simples) in index_simples {
+        // only the indexes created with `CREATE INDEX` has an sql
+        let create_sql = create_sqls.get(simple.name.as_str());
+        let expressions = create_sql
+            .map(|sql| extract_index_expressions(sql))
+            .unwrap_or_default();
+
+        let columns = column_simples
+            .iter()
+            .filter(|c| c.key != 0)
+            .enumerate()
+            .map(|(i, c)| match c.name {
+                // cid of -2 is an expression
+                Some(ref name) if c.cid != -2 => IndexColumn::Column(ColumnName::from(name)),
+                _ => IndexColumn::Expression(expressions.get(i).cloned().unwrap_or_default()),
+            })
+            .collect();
+        let predicate = if simple.partial != 0 {
+            create_sql.and_then(|sql| extract_index_predicate(sql))
+        } else {
+            None
+        };
+        indexes.entry(table_name).or_default().push(Index {
+            name: simple.name,
+            columns,
+            is_unique: simple.unique != 0,
+            is_primary: simple.origin == "pk",
+            method: None,
+            predicate,
+            included_columns: vec![],
+        });
+    }
+    Ok(indexes)
+}
+
+/// the rows of `sqlite_stat1` as (table, index, stat)
+type Stat1Row = (String, String, String);
+/// the rows of `sqlite_stat4` as (table, index, number of rows equal to the sample, sample value)
+type Stat4Row = (String, String, f64, Option<String>);
+
+/// get the rows of `sqlite_stat1` and `sqlite_stat4` of the tables in the scope,
+/// these are only available after `ANALYZE` is run on the database.
+/// `sqlite_stat4` is only available when sqlite is compiled with `SQLITE_ENABLE_STAT4`
+fn get_stat_rows(
+    db: &mut dyn Database,
+    scope: &Value,
+) -> Result<(Vec<Stat1Row>, Vec<Stat4Row>), DbError> {
+    let stat_tables: Vec<String> = db
+        .execute_sql_with_return(
+            "SELECT name FROM sqlite_master WHERE type = 'table' \
+             AND name IN ('sqlite_stat1', 'sqlite_stat4')",
+            &[],
+        )?
+        .iter()
+        .map(|row| row.get("name").expect("name"))
+        .collect();
+    if !stat_tables.iter().any(|t| t == "sqlite_stat1") {
+        return Ok((vec![], vec![]));
+    }
+    let stat1: Vec<Stat1Row> = db
+        .execute_sql_with_return(
+            "SELECT tbl, idx, stat FROM sqlite_stat1 \
+             WHERE idx IS NOT NULL AND ($1 IS NULL OR tbl = $1 COLLATE NOCASE)",
+            &[scope],
+        )?
+        .iter()
+        .filter_map(|row| {
+            let stat: Option<String> = row.get_opt("stat").expect("stat");
+            Some((
+                row.get("tbl").expect("tbl"),
+                row.get("idx").expect("idx"),
+                stat?,
+            ))
+        })
+        .collect();
+    let stat4: Vec<Stat4Row> = if stat_tables.iter().any(|t| t == "sqlite_stat4") {
+        db.execute_sql_with_return(
+            "SELECT tbl, idx, neq, sample FROM sqlite_stat4 \
+             WHERE $1 IS NULL OR tbl = $1 COLLATE NOCASE",
+            &[scope],
+        )?
+        .iter()
+        .filter_map(|row| {
+            let neq: String = row.get("neq").expect("neq");
+            let sample: Vec<u8> = row.get("sample").expect("sample");
+            let neq = neq.split_whitespace().next()?.parse().ok()?;
+            Some((
+                row.get("tbl").expect("tbl"),
+                row.get("idx").expect("idx"),
+                neq,
+                first_record_value(&sample)?,
+            ))
+        })
+        .collect()
+    } else {
+        vec![]
+    };
+    Ok((stat1, stat4))
+}
+
+/// get the statistics of the columns which are the leading column of an index.
+/// `sqlite_stat1` has the number of rows and the average number of rows for each distinct value,
+/// while `sqlite_stat4` has samples of the index entries, which are used for the most common values
+/// and the histogram.
+fn get_column_stats(
+    table: &TableName,
+    indexes: &[Index],
+    stat1: &[Stat1Row],
+    stat4: &[Stat4Row],
+) -> Vec<(ColumnName, ColumnStat)> {
+    let mut stats: Vec<(ColumnName, ColumnStat)> = vec![];
+    for index in indexes {
+        let column_name = match index.columns.first() {
+            Some(IndexColumn::Column(column_name)) => column_name,
+            _ => continue,
+        };
+        if stats.iter().any(|(c, _)| c == column_name) {
+            continue;
+        }
+        let is_index =
+            |tbl: &str, idx: &str| tbl.eq_ignore_ascii_case(&table.name) && idx == index.name;
+        // the stat is the number of rows followed by the average number of rows
+        // for each distinct value of the leading columns, ie: "1000 5 1"
+        let numbers: Vec<f64> = match stat1.iter().find(|(tbl, idx, _)| is_index(tbl, idx)) {
+            Some((_, _, stat1)) => stat1
+                .split_whitespace()
+                .filter_map(|n| n.parse().ok())
+                .collect(),
+            None => continue,
+        };
+        let (row_count, rows_per_value) = match numbers.as_slice() {
+            [row_count, rows_per_value, ..] if *row_count > 0.0 && *rows_per_value > 0.0 => {
+                (*row_count, *rows_per_value)
+            }
+            _ => continue,
+        };
+        let n_distinct = if rows_per_value <= 1.0 {
+            -1.0
+        } else {
+            (row_count / rows_per_value) as f32
+        };
+        let mut stat = ColumnStat {
+            avg_width: None,
+            n_distinct,
+            null_fraction: None,
+            most_common_values: vec![],
+            histogram_bounds: vec![],
+        };
+        let samples: Vec<(f64, Option<String>)> = stat4
+            .iter()
+            .filter(|(tbl, idx, _, _)| is_index(tbl, idx))
+            .map(|(_, _, neq, sample)| (*neq, sample.clone()))
+            .collect();
+        if !samples.is_empty() {
+            apply_samples(&mut stat, row_count, &samples);
+        }
+        stats.push((column_name.clone(), stat));
+    }
+    stats
+}
+
+/// fill in the null fraction, most common values and histogram from the `sqlite_stat4` samples,
+/// the samples are in the order of the index and each has the number of rows with the same value
+fn apply_samples(stat: &mut ColumnStat, row_count: f64, samples: &[(f64, Option<String>)]) {
+    for (neq, value) in samples {
+        match value {
+            None => stat.null_fraction = Some((neq / row_count) as f32),
+            Some(value) => {
+                if !stat.histogram_bounds.contains(value) {
+                    stat.histogram_bounds.push(value.to_string());
+                }
+                let repeated = *neq > 1.0
+                    && !stat
+                        .most_common_values
+                        .iter()
+                        .any(|mcv| mcv.value == *value);
+                if repeated {
+                    stat.most_common_values.push(MostCommonValue {
+                        value: value.to_string(),
+                        frequency: (neq / row_count) as f32,
+                    });
+                }
+            }
+        }
+    }
+    if stat.null_fraction.is_none() && !samples.is_empty() {
+        stat.null_fraction = Some(0.0);
+    }
+    stat.most_common_values.sort_by(|a, b| {
+        b.frequency
+            .partial_cmp(&a.frequency)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+/// read a variable length integer of the sqlite file format
+fn read_varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value: u64 = 0;
+    for (i, byte) in bytes.iter().enumerate().take(9) {
+        if i == 8 {
+            return Some(((value << 8) | u64::from(*byte), 9));
+        }
+        value = (value << 7) | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+/// decode the first value of a record in the sqlite record format, which is the format of the
+/// samples in `sqlite_stat4`. Returns `Some(None)` when the value is null
+fn first_record_value(record: &[u8]) -> Option<Option<String>> {
+    let (header_size, len) = read_varint(record)?;
+    let (serial_type, _) = read_varint(record.get(len..)?)?;
+    let body = record.get(header_size as usize..)?;
+    let integer = |size: usize| -> Option<i64> {
+        let bytes = body.get(..size)?;
+        let mut value: i64 = if bytes[0] & 0x80 != 0 { -1 } else { 0 };
+        for byte in bytes {
+            value = (value << 8) | i64::from(*byte);
+        }
+        Some(value)
+    };
+    let value = match serial_type {
+        0 => return Some(None),
+        1..=4 => integer(serial_type as usize)?.to_string(),
+        5 => integer(6)?.to_string(),
+        6 => integer(8)?.to_string(),
+        7 => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(body.get(..8)?);
+            f64::from_be_bytes(bytes).to_string()
+        }
+        8 => "0".to_string(),
+        9 => "1".to_string(),
+        n if n >= 13 && n % 2 == 1 => {
+            String::from_utf8_lossy(body.get(..((n - 13) / 2) as usize)?).into_owned()
+        }
+        n if n >= 12 => body
+            .get(..((n - 12) / 2) as usize)?
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+        _ => return None,
+    };
+    Some(Some(value))
+}
+
+/// get the create sql of the table, view, index or trigger
+fn get_create_sql(
+    db: &mut dyn Database,
+    kind: &str,
+    name: &str,
+) -> Result<Option<String>, DbError> {
+    let sql = "SELECT sql FROM sqlite_master WHERE type = $1 AND name = $2";
+    let rows = db.execute_sql_with_return(sql, &[&kind.to_value(), &name.to_value()])?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| row.get_opt("sql").expect("sql"))
+        .next())
+}
+
+/// split the column definitions and table constraints of `CREATE TABLE name (..)`,
+/// commas inside the parenthesis and quotes don't split the definition
+fn table_definitions(create_sql: &str) -> Vec<&str> {
+    let start = match create_sql.find('(') {
+        Some(start) => start + 1,
+        None => return vec![],
+    };
+    let mut definitions = vec![];
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut begin = start;
+    for (i, c) in create_sql[start..].char_indices() {
+        let i = start + i;
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '[' => quote = Some(']'),
+                '(' => depth += 1,
+                ')' if depth == 0 => {
+                    definitions.push(create_sql[begin..i].trim());
+                    return definitions;
+                }
+                ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    definitions.push(create_sql[begin..i].trim());
+                    begin = i + 1;
+                }
+                _ => (),
+            },
+        }
+    }
+    definitions
+}
+
+/// find the position of the keyword which is not inside quotes
+fn find_keyword(sql: &str, keyword: &str) -> Option<usize> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut quote: Option<char> = None;
+    let mut prev: Option<char> = None;
+    for (i, c) in sql.char_indices() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '[' => quote = Some(']'),
+                _ => {
+                    let end = i + keyword.len();
+                    if !prev.map(is_word).unwrap_or(false)
+                        && sql.is_char_boundary(end)
+                        && sql[i..end].eq_ignore_ascii_case(keyword)
+                        && !sql[end..].chars().next().map(is_word).unwrap_or(false)
+                    {
+                        return Some(i);
+                    }
+                }
+            },
+        }
+        prev = Some(c);
+    }
+    None
+}
+
+/// the content of the first parenthesis group in the sql
+fn parenthesized(sql: &str) -> Option<&str> {
+    let start = sql.find('(')? + 1;
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    for (i, c) in sql[start..].char_indices() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '[' => quote = Some(']'),
+                '(' => depth += 1,
+                ')' if depth == 0 => return Some(sql[start..start + i].trim()),
+                ')' => depth -= 1,
+                _ => (),
+            },
+        }
+    }
+    None
+}
+
+/// replace the line breaks and indentation outside of quotes with a single space
+fn collapse_whitespace(sql: &str) -> String {
+    let mut collapsed = String::with_capacity(sql.len());
+    let mut quote: Option<char> = None;
+    for c in sql.trim().chars() {
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                collapsed.push(c);
+            }
+            None if c.is_whitespace() => {
+                if !collapsed.ends_with(' ') {
+                    collapsed.push(' ');
+                }
+            }
+            None => {
+                match c {
+                    '\'' | '"' | '`' => quote = Some(c),
+                    '[' => quote = Some(']'),
+                    _ => (),
+                }
+                collapsed.push(c);
+            }
+        }
+    }
+    collapsed
+}
+
+fn trim_quotes(identifier: &str) -> String {
+    identifier
+        .trim_matches(|c| c == '"' || c == '`' || c == '\'' || c == '[' || c == ']')
+        .to_string()
+}
+
+/// extract the check constraints, both the column constraints and table constraints,
+/// from the create sql of the table since there is no pragma for it
+fn extract_checks(create_sql: &str, columns: &[ColumnName]) -> Vec<Check> {
+    let mut checks = vec![];
+    for definition in table_definitions(create_sql) {
+        let mut rest = definition;
+        while let Some(start) = find_keyword(rest, "CHECK") {
+            let expression = match parenthesized(&rest[start..]) {
+                Some(expression) => collapse_whitespace(expression),
+                None => break,
+            };
+            let words: Vec<&str> = rest[..start].split_whitespace().collect();
+            let name = match words.as_slice() {
+                [.., constraint, name] if constraint.eq_ignore_ascii_case("CONSTRAINT") => {
+                    Some(trim_quotes(name))
+                }
+                _ => None,
+            };
+            let used = util::identifiers(&expression);
+            checks.push(Check {
+                name,
+                columns: columns
+                    .iter()
+                    .filter(|column| used.iter().any(|w| w.eq_ignore_ascii_case(&column.name)))
+                    .cloned()
+                    .collect(),
+                expression,
+            });
+            rest = &rest[start + "CHECK".len()..];
+        }
+    }
+    checks
+}
+
+/// the deferrability of the foreign keys in the create sql of the table
+/// returns the local columns, is_deferrable and is_initially_deferred of each foreign key
+fn extract_foreign_key_deferrables(create_sql: &str) -> Vec<(Vec<String>, bool, bool)> {
+    table_definitions(create_sql)
+        .into_iter()
+        .filter_map(|definition| {
+            let references = find_keyword(definition, "REFERENCES")?;
+            let local_columns = match find_keyword(definition, "FOREIGN") {
+                // table constraint: FOREIGN KEY (a, b) REFERENCES ..
+                Some(foreign) if foreign < references => {
+                    parenthesized(&definition[foreign..references])?
+                        .split(',')
+                        .map(|column| trim_quotes(column.trim()))
+                        .collect()
+                }
+                // column constraint: a INTEGER REFERENCES ..
+                _ => vec![trim_quotes(definition.split_whitespace().next()?)],
+            };
+            let clause: Vec<String> = definition[references..]
+                .split_whitespace()
+                .map(str::to_uppercase)
+                .collect();
+            let is_deferrable = clause
+                .iter()
+                .enumerate()
+                .any(|(i, word)| word == "DEFERRABLE" && (i == 0 || clause[i - 1] != "NOT"));
+            let is_initially_deferred = clause
+                .windows(2)
+                .any(|pair| pair[0] == "INITIALLY" && pair[1] == "DEFERRED");
+            Some((
+                local_columns,
+                is_deferrable,
+                is_deferrable && is_initially_deferred,
+            ))
+        })
+        .collect()
+}
+
+/// parse the timing, events and body of `CREATE TRIGGER` statement
+/// example:
+///     CREATE TRIGGER actor_trigger_ai AFTER INSERT ON actor
+///     BEGIN
+///         UPDATE actor SET last_update = DATETIME('NOW') WHERE rowid = new.rowid;
+///     END
+fn parse_trigger(name: String, table_name: &TableName, create_sql: String) -> Trigger {
+    let header_end = util::find_keyword(&create_sql, "ON").unwrap_or(create_sql.len());
+    // skip up to the trigger name, since an unquoted name may be one of the keywords
+    let name_end = util::find_keyword(&create_sql, &name)
+        .map(|start| start + name.len())
+        .filter(|end| *end <= header_end)
+        .unwrap_or(0);
+    let header = &create_sql[name_end..header_end];
+    let has_keyword = |keyword| util::find_keyword(header, keyword).is_some();
+    let timing = if has_keyword("INSTEAD") {
+        TriggerTiming::InsteadOf
+    } else if has_keyword("AFTER") {
+        TriggerTiming::After
+    } else {
+        TriggerTiming::Before
+    };
+    let events = [
+        ("INSERT", TriggerEvent::Insert),
+        ("UPDATE", TriggerEvent::Update),
+        ("DELETE", TriggerEvent::Delete),
+    ]
+    .iter()
+    .filter(|(keyword, _)| has_keyword(keyword))
+    .map(|(_, event)| *event)
+    .collect();
+    let action = match (
+        util::find_keyword(&create_sql, "BEGIN"),
+        util::rfind_keyword(&create_sql, "END"),
+    ) {
+        (Some(begin), Some(end)) if begin < end => {
+            create_sql[begin + "BEGIN".len()..end].trim().to_string()
+        }
+        _ => create_sql.to_string(),
+    };
+    Trigger {
+        name,
+        table: table_name.clone(),
+        timing,
+        events,
+        // sqlite only supports FOR EACH ROW triggers
+        for_each_row: true,
+        action,
+        definition: Some(create_sql),
+    }
 }
 
 #[derive(Debug, Error)]
@@ -548,10 +1504,504 @@ mod test {
         types::SqlType::{Int, Text, Timestamp},
     };
 
+    /// a database file in the temp directory which is unique to the test, it is removed when
+    /// this is dropped
+    struct TempDb(std::path::PathBuf);
+
+    impl TempDb {
+        fn new() -> Self {
+            use std::sync::atomic::{AtomicUsize, Ordering};
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let file = format!(
+                "rustorm_test_{}_{}.db",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::SeqCst)
+            );
+            let path = std::env::temp_dir().join(file);
+            let _ = std::fs::remove_file(&path);
+            TempDb(path)
+        }
+
+        fn url(&self) -> String {
+            format!("sqlite://{}", self.0.display())
+        }
+    }
+
+    impl Drop for TempDb {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// lower the maximum number of parameters of the connection of the entity manager, so the
+    /// bulk inserts are split into chunks without inserting as many rows as the compiled limit
+    fn lower_max_params(em: &mut crate::EntityManager, max_params: i32) {
+        match em.0 {
+            DBPlatform::Sqlite(ref db) => {
+                db.0.set_limit(
+                    rusqlite::limits::Limit::SQLITE_LIMIT_VARIABLE_NUMBER,
+                    max_params,
+                );
+            }
+            _ => panic!("must be sqlite"),
+        }
+        assert_eq!(em.0.max_params(), max_params as usize);
+    }
+
+    /// the foreign keys of a single table
+    fn get_foreign_keys(
+        db: &mut dyn Database,
+        table: &TableName,
+    ) -> Result<Vec<ForeignKey>, DbError> {
+        let table = db.get_table(table)?.expect("must have the table");
+        Ok(table.get_foreign_keys().into_iter().cloned().collect())
+    }
+
+    #[test]
+    fn table_stats() {
+        let db_url = "sqlite://sakila.db";
+        let pool = Pool::new();
+        let mut db = pool.db(db_url).expect("must be ok");
+        let stats = db
+            .get_table_stats(&[TableName::from("actor"), TableName::from("no_such_table")])
+            .expect("must be ok");
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].table, TableName::from("actor"));
+        assert!(!stats[0].is_exact);
+    }
+
+    #[test]
+    fn schema_cache_invalidated_by_ddl() {
+        let temp_db = TempDb::new();
+        let db_url = temp_db.url();
+        let pool = Pool::new();
+        pool.enable_schema_cache(&db_url, Default::default());
+        let mut dm = pool.dm(&db_url).expect("must be ok");
+        dm.execute_sql_with_return("CREATE TABLE pet (pet_id INTEGER PRIMARY KEY)", &[])
+            .expect("must create");
+
+        let mut em = pool.em(&db_url).expect("must be ok");
+        let pet = TableName::from("pet");
+        let table = em.get_table(&pet).expect("must be ok").expect("must exist");
+        assert_eq!(table.columns.len(), 1);
+
+        // changes made outside the pool are not seen until the cache is invalidated
+        let mut db = pool.db(&db_url).expect("must be ok");
+        db.execute_sql_with_return("ALTER TABLE pet ADD COLUMN name TEXT", &[])
+            .expect("must alter");
+        let table = em.get_table(&pet).expect("must be ok").expect("must exist");
+        assert_eq!(table.columns.len(), 1);
+
+        // the cache is shared with the dao manager
+        dm.execute_sql_with_return("DROP TABLE pet", &[])
+            .expect("must drop");
+        assert!(em.get_table(&pet).expect("must be ok").is_none());
+    }
+
+    #[test]
+    fn no_routines() {
+        let db_url = "sqlite://sakila.db";
+        let pool = Pool::new();
+        let mut em = pool.em(db_url).expect("must be ok");
+        assert!(em.get_routines().expect("must be ok").is_empty());
+        let result = em.call_routine(&TableName::from("film_in_stock"), &[&1, &1]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn named_params_in_list() {
+        let db_url = "sqlite://sakila.db";
+        let pool = Pool::new();
+        let mut dm = pool.dm(db_url).expect("must be ok");
+        let mut params = crate::Dao::new();
+        params.insert_value(
+            "actor_ids",
+            &Value::Array(rustorm_dao::Array::Int(vec![1, 2])),
+        );
+        let rows = dm
+            .execute_sql_with_named_params(
+                "SELECT actor_id FROM actor WHERE actor_id IN (:actor_ids) ORDER BY actor_id",
+                &params,
+            )
+            .expect("must be ok");
+        let actor_ids: Vec<i32> = rows
+            .iter()
+            .map(|row| row.get("actor_id").expect("actor_id"))
+            .collect();
+        assert_eq!(actor_ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn reordered_numbered_placeholders() {
+        let db_url = "sqlite://sakila.db";
+        let pool = Pool::new();
+        let mut dm = pool.dm(db_url).expect("must be ok");
+        let rows = dm
+            .execute_sql_with_return(
+                "SELECT $2 AS second, $1 AS first, $2 AS again",
+                &[&Value::Int(1), &Value::Int(2)],
+            )
+            .expect("must be ok");
+        let row = rows.iter().next().expect("must have a row");
+        assert_eq!(row.get::<i32>("second").expect("second"), 2);
+        assert_eq!(row.get::<i32>("first").expect("first"), 1);
+        assert_eq!(row.get::<i32>("again").expect("again"), 2);
+    }
+
+    #[test]
+    fn bulk_insert_with_returning() {
+        use crate::codegen::{FromDao, ToColumnNames, ToDao, ToTableName};
+
+        #[derive(Debug, ToDao, ToColumnNames, ToTableName)]
+        struct Pet {
+            name: String,
+        }
+
+        #[derive(Debug, FromDao, ToColumnNames)]
+        struct RetrievePet {
+            pet_id: i64,
+            name: String,
+        }
+
+        #[derive(Debug, ToDao, ToColumnNames, ToTableName)]
+        struct Tag {
+            code: String,
+            label: String,
+        }
+
+        #[derive(Debug, FromDao, ToColumnNames)]
+        struct RetrieveTag {
+            code: String,
+            label: String,
+        }
+
+        let temp_db = TempDb::new();
+        let db_url = temp_db.url();
+        let pool = Pool::new();
+        let mut db = pool.db(&db_url).expect("must be ok");
+        db.execute_sql_with_return(
+            "CREATE TABLE pet (pet_id INTEGER PRIMARY KEY, name TEXT NOT NULL)",
+            &[],
+        )
+        .expect("must create");
+        db.execute_sql_with_return(
+            "CREATE TABLE tag (code TEXT PRIMARY KEY, label TEXT) WITHOUT ROWID",
+            &[],
+        )
+        .expect("must create");
+
+        let mut em = pool.em(&db_url).expect("must be ok");
+        lower_max_params(&mut em, 999);
+        // more rows than the parameters allowed in a single statement
+        let pets: Vec<Pet> = (0..em.0.max_params() + 1)
+            .map(|i| Pet {
+                name: format!("pet{}", i),
+            })
+            .collect();
+        let pets: Vec<&Pet> = pets.iter().collect();
+        let inserted: Vec<RetrievePet> = em.insert(&pets).expect("must insert");
+        assert_eq!(inserted.len(), pets.len());
+        assert_eq!(inserted[0].pet_id, 1);
+        assert_eq!(inserted[0].name, "pet0");
+        assert_eq!(inserted.last().unwrap().pet_id, pets.len() as i64);
+
+        if supports_returning() {
+            let tag = Tag {
+                code: "rs".to_string(),
+                label: "rust".to_string(),
+            };
+            let inserted: Vec<RetrieveTag> = em.insert(&[&tag]).expect("must insert");
+            assert_eq!(inserted.len(), 1);
+            assert_eq!(inserted[0].code, "rs");
+            assert_eq!(inserted[0].label, "rust");
+        }
+    }
+
+    #[test]
+    fn chunked_insert_in_one_transaction() {
+        use crate::codegen::{FromDao, ToColumnNames, ToDao, ToTableName};
+
+        #[derive(Debug, FromDao, ToDao, ToColumnNames, ToTableName)]
+        struct Tag {
+            name: String,
+        }
+
+        #[derive(Debug, FromDao, ToColumnNames)]
+        struct Count {
+            count: i64,
+        }
+
+        let temp_db = TempDb::new();
+        let db_url = temp_db.url();
+        let pool = Pool::new();
+        let mut db = pool.db(&db_url).expect("must be ok");
+        db.execute_sql_with_return("CREATE TABLE tag (name TEXT NOT NULL UNIQUE)", &[])
+            .expect("must create");
+
+        let mut em = pool.em(&db_url).expect("must be ok");
+        lower_max_params(&mut em, 999);
+        let chunk_size = em.0.max_params();
+        let tags: Vec<Tag> = (0..chunk_size * 2 + 1)
+            .map(|i| Tag {
+                name: format!("tag{}", i),
+            })
+            .collect();
+        let tags: Vec<&Tag> = tags.iter().collect();
+        let mut progress = vec![];
+        let inserted: Vec<Tag> = em
+            .insert_with_progress(&tags[..chunk_size + 1], |done, total| {
+                progress.push((done, total))
+            })
+            .expect("must insert");
+        assert_eq!(inserted.len(), chunk_size + 1);
+        assert_eq!(inserted[chunk_size].name, format!("tag{}", chunk_size));
+        assert_eq!(
+            progress,
+            vec![
+                (chunk_size, chunk_size + 1),
+                (chunk_size + 1, chunk_size + 1)
+            ]
+        );
+
+        // the duplicate in the second chunk rolls back the first chunk too
+        let mut more_tags = tags[chunk_size + 1..].to_vec();
+        more_tags.push(tags[0]);
+        assert!(em.insert::<Tag, Tag>(&more_tags).is_err());
+        let count: Count = em
+            .execute_sql_with_one_return("SELECT COUNT(*) AS count FROM tag", &[])
+            .expect("must count");
+        assert_eq!(count.count, chunk_size as i64 + 1);
+    }
+
+    #[test]
+    fn chunked_insert_inside_a_transaction() {
+        use crate::codegen::{FromDao, ToColumnNames, ToDao, ToTableName};
+
+        #[derive(Debug, FromDao, ToDao, ToColumnNames, ToTableName)]
+        struct Tag {
+            name: String,
+        }
+
+        #[derive(Debug, FromDao, ToColumnNames)]
+        struct Count {
+            count: i64,
+        }
+
+        let temp_db = TempDb::new();
+        let db_url = temp_db.url();
+        let pool = Pool::new();
+        let mut db = pool.db(&db_url).expect("must be ok");
+        db.execute_sql_with_return("CREATE TABLE tag (name TEXT NOT NULL UNIQUE)", &[])
+            .expect("must create");
+
+        let mut em = pool.em(&db_url).expect("must be ok");
+        lower_max_params(&mut em, 999);
+        let chunk_size = em.0.max_params();
+        let tags: Vec<Tag> = (0..chunk_size * 2)
+            .map(|i| Tag {
+                name: format!("tag{}", i),
+            })
+            .collect();
+        let tags: Vec<&Tag> = tags.iter().collect();
+        let count = |em: &mut crate::EntityManager| {
+            let count: Count = em
+                .execute_sql_with_one_return("SELECT COUNT(*) AS count FROM tag", &[])
+                .expect("must count");
+            count.count
+        };
+
+        em.begin_transaction().expect("must begin");
+        em.insert::<Tag, Tag>(&tags[..1]).expect("must insert");
+        // the duplicate in the second chunk rolls back to the savepoint, the transaction is open
+        let mut more_tags = tags[1..chunk_size + 1].to_vec();
+        more_tags.push(tags[0]);
+        assert!(em.insert::<Tag, Tag>(&more_tags).is_err());
+        assert_eq!(count(&mut em), 1);
+        let inserted: Vec<Tag> = em.insert(&tags[1..chunk_size + 2]).expect("must insert");
+        assert_eq!(inserted.len(), chunk_size + 1);
+        assert_eq!(count(&mut em), chunk_size as i64 + 2);
+        // the chunks are not committed with the savepoint
+        em.rollback_transaction().expect("must rollback");
+        assert_eq!(count(&mut em), 0);
+    }
+
+    #[test]
+    fn quote_reserved_names() {
+        let temp_db = TempDb::new();
+        let db_url = temp_db.url();
+        let pool = Pool::new();
+        let mut dm = pool.dm(&db_url).expect("must be ok");
+        dm.execute_sql_with_return(
+            "CREATE TABLE \"order\" (\"key\" INTEGER PRIMARY KEY, \"group\" TEXT)",
+            &[],
+        )
+        .expect("must create");
+        let mut dao = crate::Dao::new();
+        dao.insert("group", "rust");
+        let order = TableName::from("order");
+        let inserted = dm.insert_dao(&order, &[dao]).expect("must insert");
+        assert_eq!(inserted[0].get::<String>("group").expect("group"), "rust");
+
+        let mut em = pool.em(&db_url).expect("must be ok");
+        assert_eq!(em.get_total_records(&order).expect("must count"), 1);
+        let unsafe_name = em.get_total_records(&TableName::from(""));
+        assert!(matches!(unsafe_name, Err(DbError::SqlInjectionAttempt(_))));
+    }
+
+    #[test]
+    fn stat4_samples() {
+        // header size 3, serial types: text of 3 bytes, 1 byte integer
+        let sample = [3, 19, 1, b'a', b'b', b'c', 7];
+        assert_eq!(first_record_value(&sample), Some(Some("abc".to_string())));
+        assert_eq!(first_record_value(&[2, 0]), Some(None));
+        assert_eq!(
+            first_record_value(&[2, 2, 0xff, 0x38]),
+            Some(Some("-200".to_string()))
+        );
+
+        let mut stat = ColumnStat {
+            avg_width: None,
+            n_distinct: 3.0,
+            null_fraction: None,
+            most_common_values: vec![],
+            histogram_bounds: vec![],
+        };
+        let samples = vec![
+            (10.0, None),
+            (20.0, Some("G".to_string())),
+            (60.0, Some("PG".to_string())),
+            (60.0, Some("PG".to_string())),
+            (1.0, Some("R".to_string())),
+        ];
+        apply_samples(&mut stat, 100.0, &samples);
+        assert_eq!(stat.null_fraction, Some(0.1));
+        assert_eq!(
+            stat.most_common_values,
+            vec![
+                MostCommonValue {
+                    value: "PG".into(),
+                    frequency: 0.6
+                },
+                MostCommonValue {
+                    value: "G".into(),
+                    frequency: 0.2
+                },
+            ]
+        );
+        assert_eq!(stat.histogram_bounds, vec!["G", "PG", "R"]);
+        assert!(stat.is_low_cardinality(10.0));
+    }
+
+    #[test]
+    fn checks_and_deferrable_foreign_keys() {
+        let sql = r#"CREATE TABLE payment (
+            payment_id INTEGER PRIMARY KEY,
+            "amount" DECIMAL(5,2) NOT NULL CHECK (amount >= 0),
+            customer_id INTEGER REFERENCES customer (customer_id) DEFERRABLE INITIALLY DEFERRED,
+            staff_id INTEGER NOT NULL,
+            note TEXT DEFAULT 'check, (not a constraint)',
+            CONSTRAINT payment_staff_fkey FOREIGN KEY (staff_id) REFERENCES staff (staff_id)
+                NOT DEFERRABLE,
+            CONSTRAINT "positive_ids" CHECK (payment_id > 0 AND
+                staff_id > 0)
+        )"#;
+        let columns: Vec<ColumnName> = ["payment_id", "amount", "customer_id", "staff_id", "note"]
+            .iter()
+            .map(|column| ColumnName::from(column))
+            .collect();
+        assert_eq!(
+            extract_checks(sql, &columns),
+            vec![
+                Check {
+                    name: None,
+                    expression: "amount >= 0".into(),
+                    columns: vec![ColumnName::from("amount")],
+                },
+                Check {
+                    name: Some("positive_ids".into()),
+                    expression: "payment_id > 0 AND staff_id > 0".into(),
+                    columns: vec![ColumnName::from("payment_id"), ColumnName::from("staff_id")],
+                },
+            ]
+        );
+        assert_eq!(
+            extract_foreign_key_deferrables(sql),
+            vec![
+                (vec!["customer_id".to_string()], true, true),
+                (vec!["staff_id".to_string()], false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_create_trigger() {
+        let sql = "CREATE TRIGGER film_insert_update AFTER INSERT ON film\n\
+                   BEGIN\n    UPDATE film SET last_update = DATETIME('NOW') WHERE rowid = new.rowid;\n\
+                   END";
+        let trigger = parse_trigger(
+            "film_insert_update".to_string(),
+            &TableName::from("film"),
+            sql.to_string(),
+        );
+        assert_eq!(trigger.timing, TriggerTiming::After);
+        assert_eq!(trigger.events, vec![TriggerEvent::Insert]);
+        assert_eq!(
+            trigger.action,
+            "UPDATE film SET last_update = DATETIME('NOW') WHERE rowid = new.rowid;"
+        );
+    }
+
+    #[test]
+    fn parse_trigger_with_non_ascii_names() {
+        // the uppercase of `ſ` is `S`, which has a different length in utf-8
+        let sql = "create trigger ſé_after before update of begin_date on ſé\n\
+                   begin\n    insert into backend (ſé) values ('END');\n\
+                   end";
+        let trigger = parse_trigger(
+            "ſé_after".to_string(),
+            &TableName::from("ſé"),
+            sql.to_string(),
+        );
+        assert_eq!(trigger.timing, TriggerTiming::Before);
+        assert_eq!(trigger.events, vec![TriggerEvent::Update]);
+        assert_eq!(trigger.action, "insert into backend (ſé) values ('END');");
+    }
+
+    #[test]
+    fn view_select() {
+        let sql = "CREATE VIEW customer_list\nAS\nSELECT cu.customer_id AS ID FROM customer AS cu";
+        assert_eq!(
+            extract_view_select(sql),
+            "SELECT cu.customer_id AS ID FROM customer AS cu"
+        );
+    }
+
+    #[test]
+    fn index_expressions_and_predicate() {
+        let sql = "CREATE UNIQUE INDEX idx_title ON film (lower(title) COLLATE NOCASE DESC, \
+                   substr(description, 1, 10), \"rating\") WHERE rating IN ('G', 'PG')";
+        assert_eq!(
+            extract_index_expressions(sql),
+            vec![
+                "lower(title)".to_string(),
+                "substr(description, 1, 10)".to_string(),
+                "\"rating\"".to_string()
+            ]
+        );
+        assert_eq!(
+            extract_index_predicate(sql),
+            Some("rating IN ('G', 'PG')".to_string())
+        );
+        assert_eq!(
+            extract_index_predicate("CREATE INDEX idx_actor_last_name ON actor(last_name)"),
+            None
+        );
+    }
+
     #[test]
     fn test_advancing_autoincrement_primary_column() {
         let db_url = "sqlite://sakila.db";
-        let mut pool = Pool::new();
+        let pool = Pool::new();
         let mut em = pool.em(db_url).unwrap();
         let actor_table = TableName::from("actor");
         let last_value = em
@@ -574,7 +2024,7 @@ mod test {
     #[test]
     fn test_get_all_tables() {
         let db_url = "sqlite://sakila.db";
-        let mut pool = Pool::new();
+        let pool = Pool::new();
         let db = pool.db(db_url);
         assert!(db.is_ok());
         let mut db = db.unwrap();
@@ -584,10 +2034,40 @@ mod test {
         assert_eq!(all_tables.len(), 22);
     }
 
+    #[test]
+    fn unsupported_value_is_a_convert_error() {
+        let pool = Pool::new();
+        let mut db = pool.db("sqlite://sakila.db").expect("must be ok");
+        let composite = Value::Composite(vec![("id".to_string(), Value::Int(1))]);
+        match db.execute_sql_with_return("SELECT ?", &[&composite]) {
+            Err(DbError::ConvertError(ConvertError::UnsupportedDataType(_))) => (),
+            other => panic!("expecting a convert error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn broken_view_is_skipped() {
+        let temp_db = TempDb::new();
+        let db_url = temp_db.url();
+        let pool = Pool::new();
+        let mut db = pool.db(&db_url).expect("must be ok");
+        for sql in &[
+            "CREATE TABLE pet (pet_id INTEGER PRIMARY KEY, owner_id INTEGER)",
+            "CREATE TABLE owner (owner_id INTEGER PRIMARY KEY)",
+            "CREATE VIEW pet_owner AS SELECT pet.pet_id FROM pet JOIN owner USING (owner_id)",
+            "DROP TABLE owner",
+        ] {
+            db.execute_sql_with_return(sql, &[]).expect("must execute");
+        }
+        let all_tables = db.get_all_tables().expect("must be ok");
+        assert_eq!(all_tables.len(), 1);
+        assert_eq!(all_tables[0].name, TableName::from("pet"));
+    }
+
     #[test]
     fn test_get_group_table() {
         let db_url = "sqlite://sakila.db";
-        let mut pool = Pool::new();
+        let pool = Pool::new();
         let db = pool.db(db_url);
         assert!(db.is_ok());
         let mut db = db.unwrap();
@@ -611,7 +2091,7 @@ mod test {
     #[test]
     fn test_get_table() {
         let db_url = "sqlite://sakila.db";
-        let mut pool = Pool::new();
+        let pool = Pool::new();
         let db = pool.db(db_url);
         assert!(db.is_ok());
         let mut db = db.unwrap();
@@ -806,14 +2286,61 @@ mod test {
                         name: None,
                         columns: vec![ColumnName::from("original_language_id"),],
                         foreign_table: TableName::from("language"),
-                        referred_columns: vec![ColumnName::from("language_id"),]
+                        referred_columns: vec![ColumnName::from("language_id"),],
+                        on_delete: ReferentialAction::NoAction,
+                        on_update: ReferentialAction::NoAction,
+                        match_type: MatchType::Simple,
+                        is_deferrable: false,
+                        is_initially_deferred: false
                     }),
                     TableKey::ForeignKey(ForeignKey {
                         name: None,
                         columns: vec![ColumnName::from("language_id"),],
                         foreign_table: TableName::from("language"),
-                        referred_columns: vec![ColumnName::from("language_id"),]
+                        referred_columns: vec![ColumnName::from("language_id"),],
+                        on_delete: ReferentialAction::NoAction,
+                        on_update: ReferentialAction::NoAction,
+                        match_type: MatchType::Simple,
+                        is_deferrable: false,
+                        is_initially_deferred: false
+                    }),
+                    TableKey::Check(Check {
+                        name: Some("CHECK_special_features".into()),
+                        expression: "special_features is null or \
+                                     special_features like '%Trailers%' or \
+                                     special_features like '%Commentaries%' or \
+                                     special_features like '%Deleted Scenes%' or \
+                                     special_features like '%Behind the Scenes%'"
+                            .into(),
+                        columns: vec![ColumnName::from("special_features")]
+                    }),
+                    TableKey::Check(Check {
+                        name: Some("CHECK_special_rating".into()),
+                        expression: "rating in ('G','PG','PG-13','R','NC-17')".into(),
+                        columns: vec![ColumnName::from("rating")]
                     })
+                ],
+                indexes: vec![
+                    Index {
+                        name: "idx_fk_language_id".into(),
+                        columns: vec![IndexColumn::Column(ColumnName::from("language_id"))],
+                        is_unique: false,
+                        is_primary: false,
+                        method: None,
+                        predicate: None,
+                        included_columns: vec![],
+                    },
+                    Index {
+                        name: "idx_fk_original_language_id".into(),
+                        columns: vec![IndexColumn::Column(ColumnName::from(
+                            "original_language_id"
+                        ))],
+                        is_unique: false,
+                        is_primary: false,
+                        method: None,
+                        predicate: None,
+                        included_columns: vec![],
+                    }
                 ]
             }
         );
@@ -822,7 +2349,7 @@ mod test {
     #[test]
     fn test_get_table2() {
         let db_url = "sqlite://sakila.db";
-        let mut pool = Pool::new();
+        let pool = Pool::new();
         let db = pool.db(db_url);
         assert!(db.is_ok());
         let mut db = db.unwrap();
@@ -839,6 +2366,7 @@ mod test {
                 name: TableName {
                     name: "actor".into(),
                     schema: None,
+                    catalog: None,
                     alias: None
                 },
                 comment: None,
@@ -847,6 +2375,7 @@ mod test {
                         table: TableName {
                             name: "actor".into(),
                             schema: None,
+                            catalog: None,
                             alias: None
                         },
                         name: ColumnName {
@@ -866,6 +2395,7 @@ mod test {
                         table: TableName {
                             name: "actor".into(),
                             schema: None,
+                            catalog: None,
                             alias: None
                         },
                         name: ColumnName {
@@ -885,6 +2415,7 @@ mod test {
                         table: TableName {
                             name: "actor".into(),
                             schema: None,
+                            catalog: None,
                             alias: None
                         },
                         name: ColumnName {
@@ -904,6 +2435,7 @@ mod test {
                         table: TableName {
                             name: "actor".into(),
                             schema: None,
+                            catalog: None,
                             alias: None
                         },
                         name: ColumnName {
@@ -928,7 +2460,16 @@ mod test {
                         table: None,
                         alias: None
                     }]
-                })]
+                })],
+                indexes: vec![Index {
+                    name: "idx_actor_last_name".into(),
+                    columns: vec![IndexColumn::Column(ColumnName::from("last_name"))],
+                    is_unique: false,
+                    is_primary: false,
+                    method: None,
+                    predicate: None,
+                    included_columns: vec![],
+                }]
             }
         );
     }
@@ -936,7 +2477,7 @@ mod test {
     #[test]
     fn test_get_table3() {
         let db_url = "sqlite://sakila.db";
-        let mut pool = Pool::new();
+        let pool = Pool::new();
         let db = pool.db(db_url);
         assert!(db.is_ok());
         let mut db = db.unwrap();
@@ -953,6 +2494,7 @@ mod test {
                 name: TableName {
                     name: "film_actor".into(),
                     schema: None,
+                    catalog: None,
                     alias: None
                 },
                 comment: None,
@@ -961,6 +2503,7 @@ mod test {
                         table: TableName {
                             name: "film_actor".into(),
                             schema: None,
+                            catalog: None,
                             alias: None
                         },
                         name: ColumnName {
@@ -980,6 +2523,7 @@ mod test {
                         table: TableName {
                             name: "film_actor".into(),
                             schema: None,
+                            catalog: None,
                             alias: None
                         },
                         name: ColumnName {
@@ -999,6 +2543,7 @@ mod test {
                         table: TableName {
                             name: "film_actor".into(),
                             schema: None,
+                            catalog: None,
                             alias: None
                         },
                         name: ColumnName {
@@ -1042,13 +2587,19 @@ mod test {
                         foreign_table: TableName {
                             name: "film".into(),
                             schema: None,
+                            catalog: None,
                             alias: None
                         },
                         referred_columns: vec![ColumnName {
                             name: "film_id".into(),
                             table: None,
                             alias: None
-                        }]
+                        }],
+                        on_delete: ReferentialAction::NoAction,
+                        on_update: ReferentialAction::Cascade,
+                        match_type: MatchType::Simple,
+                        is_deferrable: false,
+                        is_initially_deferred: false
                     }),
                     TableKey::ForeignKey(ForeignKey {
                         name: None,
@@ -1060,14 +2611,52 @@ mod test {
                         foreign_table: TableName {
                             name: "actor".into(),
                             schema: None,
+                            catalog: None,
                             alias: None
                         },
                         referred_columns: vec![ColumnName {
                             name: "actor_id".into(),
                             table: None,
                             alias: None
-                        }]
+                        }],
+                        on_delete: ReferentialAction::NoAction,
+                        on_update: ReferentialAction::Cascade,
+                        match_type: MatchType::Simple,
+                        is_deferrable: false,
+                        is_initially_deferred: false
                     })
+                ],
+                indexes: vec![
+                    Index {
+                        name: "idx_fk_film_actor_actor".into(),
+                        columns: vec![IndexColumn::Column(ColumnName::from("actor_id"))],
+                        is_unique: false,
+                        is_primary: false,
+                        method: None,
+                        predicate: None,
+                        included_columns: vec![],
+                    },
+                    Index {
+                        name: "idx_fk_film_actor_film".into(),
+                        columns: vec![IndexColumn::Column(ColumnName::from("film_id"))],
+                        is_unique: false,
+                        is_primary: false,
+                        method: None,
+                        predicate: None,
+                        included_columns: vec![],
+                    },
+                    Index {
+                        name: "sqlite_autoindex_film_actor_1".into(),
+                        columns: vec![
+                            IndexColumn::Column(ColumnName::from("actor_id")),
+                            IndexColumn::Column(ColumnName::from("film_id"))
+                        ],
+                        is_unique: true,
+                        is_primary: true,
+                        method: None,
+                        predicate: None,
+                        included_columns: vec![],
+                    }
                 ]
             }
         );
@@ -1076,7 +2665,7 @@ mod test {
     #[test]
     fn test_get_foreign() {
         let db_url = "sqlite://sakila.db";
-        let mut pool = Pool::new();
+        let pool = Pool::new();
         let db = pool.db(db_url);
         assert!(db.is_ok());
         let mut db = db.unwrap();
@@ -1091,13 +2680,23 @@ mod test {
                     name: None,
                     columns: vec![ColumnName::from("film_id"),],
                     foreign_table: TableName::from("film"),
-                    referred_columns: vec![ColumnName::from("film_id")]
+                    referred_columns: vec![ColumnName::from("film_id")],
+                    on_delete: ReferentialAction::NoAction,
+                    on_update: ReferentialAction::Cascade,
+                    match_type: MatchType::Simple,
+                    is_deferrable: false,
+                    is_initially_deferred: false
                 },
                 ForeignKey {
                     name: None,
                     columns: vec![ColumnName::from("actor_id"),],
                     foreign_table: TableName::from("actor"),
-                    referred_columns: vec![ColumnName::from("actor_id")]
+                    referred_columns: vec![ColumnName::from("actor_id")],
+                    on_delete: ReferentialAction::NoAction,
+                    on_update: ReferentialAction::Cascade,
+                    match_type: MatchType::Simple,
+                    is_deferrable: false,
+                    is_initially_deferred: false
                 }
             ]
         );
@@ -1106,7 +2705,7 @@ mod test {
     #[test]
     fn test_get_foreign2() {
         let db_url = "sqlite://sakila.db";
-        let mut pool = Pool::new();
+        let pool = Pool::new();
         let db = pool.db(db_url);
         assert!(db.is_ok());
         let mut db = db.unwrap();
@@ -1121,13 +2720,23 @@ mod test {
                     name: None,
                     columns: vec![ColumnName::from("original_language_id"),],
                     foreign_table: TableName::from("language"),
-                    referred_columns: vec![ColumnName::from("language_id"),]
+                    referred_columns: vec![ColumnName::from("language_id"),],
+                    on_delete: ReferentialAction::NoAction,
+                    on_update: ReferentialAction::NoAction,
+                    match_type: MatchType::Simple,
+                    is_deferrable: false,
+                    is_initially_deferred: false
                 },
                 ForeignKey {
                     name: None,
                     columns: vec![ColumnName::from("language_id"),],
                     foreign_table: TableName::from("language"),
-                    referred_columns: vec![ColumnName::from("language_id"),]
+                    referred_columns: vec![ColumnName::from("language_id"),],
+                    on_delete: ReferentialAction::NoAction,
+                    on_update: ReferentialAction::NoAction,
+                    match_type: MatchType::Simple,
+                    is_deferrable: false,
+                    is_initially_deferred: false
                 },
             ]
         );
@@ -0,0 +1,32 @@
+use crate::TableName;
+
+/// A trigger on a table or view
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trigger {
+    pub name: String,
+    pub table: TableName,
+    pub timing: TriggerTiming,
+    pub events: Vec<TriggerEvent>,
+    /// false when the trigger is executed once for each statement, postgresql only
+    pub for_each_row: bool,
+    /// the function executed by the trigger in postgresql,
+    /// or the statements of the trigger body in sqlite and mysql
+    pub action: String,
+    /// the complete `CREATE TRIGGER` statement, when available
+    pub definition: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriggerTiming {
+    Before,
+    After,
+    InsteadOf,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TriggerEvent {
+    Insert,
+    Update,
+    Delete,
+    Truncate,
+}
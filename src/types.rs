@@ -44,6 +44,10 @@ pub enum SqlType {
     // enum list with the choices value
     Enum(String, Vec<String>),
     Array(Box<SqlType>),
+    // a user defined type based on another type, with the name of the domain
+    Domain(String, Box<SqlType>),
+    // a user defined record type with the name and type of its fields
+    Composite(String, Vec<(String, SqlType)>),
 }
 
 impl SqlType {
@@ -74,6 +78,14 @@ impl SqlType {
         }
     }
 
+    /// the type the values of this type are stored as, the base type of a domain
+    pub fn base_type(&self) -> &SqlType {
+        match *self {
+            SqlType::Domain(_, ref base) => base.base_type(),
+            _ => self,
+        }
+    }
+
     pub fn cast_as(&self) -> Option<SqlType> {
         match *self {
             SqlType::TsVector => Some(SqlType::Text),
@@ -151,12 +163,19 @@ impl HasType for Value {
             Value::Array(Array::Int(_)) => Some(SqlType::Array(Box::new(SqlType::Int))),
             Value::Array(Array::Float(_)) => Some(SqlType::Array(Box::new(SqlType::Float))),
             Value::Array(Array::Text(_)) => Some(SqlType::Array(Box::new(SqlType::Text))),
+            // the name of the composite type is not known from its value
+            Value::Composite(_) => None,
         }
     }
 }
 
 impl SqlType {
     pub fn same_type(&self, value: &Value) -> bool {
+        match (self, value) {
+            (SqlType::Domain(_, base), _) => return base.same_type(value),
+            (SqlType::Composite(_, _), Value::Composite(_)) => return true,
+            (_, _) => (),
+        }
         if let Some(simple_type) = value.get_type() {
             if simple_type == *self {
                 return true;
@@ -171,3 +190,103 @@ impl SqlType {
         }
     }
 }
+
+impl SqlType {
+    /// the type of a data type name as the database reports it for the routine arguments,
+    /// ie: `integer`, `character varying(255)`, `int4`, `text[]`, None for the other types
+    pub(crate) fn from_type_name(type_name: &str) -> Option<SqlType> {
+        let type_name = type_name.trim().to_lowercase();
+        if let Some(element) = type_name.strip_suffix("[]") {
+            return SqlType::from_type_name(element).map(|ty| SqlType::Array(Box::new(ty)));
+        }
+        let type_name = match type_name.find('(') {
+            Some(paren) => {
+                let after = type_name[paren..].find(')').map(|end| paren + end + 1);
+                let rest = after.map(|after| &type_name[after..]).unwrap_or("");
+                format!("{}{}", type_name[..paren].trim_end(), rest)
+            }
+            None => type_name,
+        };
+        let sql_type = match type_name.as_str() {
+            "bool" | "boolean" => SqlType::Bool,
+            "tinyint" => SqlType::Tinyint,
+            "smallint" | "int2" => SqlType::Smallint,
+            "int" | "integer" | "int4" | "mediumint" => SqlType::Int,
+            "bigint" | "int8" => SqlType::Bigint,
+            "real" | "float4" => SqlType::Real,
+            "float" => SqlType::Float,
+            "double" | "double precision" | "float8" => SqlType::Double,
+            "numeric" | "decimal" => SqlType::Numeric,
+            "tinyblob" => SqlType::Tinyblob,
+            "mediumblob" => SqlType::Mediumblob,
+            "blob" | "bytea" => SqlType::Blob,
+            "longblob" => SqlType::Longblob,
+            "binary" | "varbinary" => SqlType::Varbinary,
+            "char" | "character" | "bpchar" => SqlType::Char,
+            "varchar" | "character varying" => SqlType::Varchar,
+            "tinytext" => SqlType::Tinytext,
+            "mediumtext" => SqlType::Mediumtext,
+            "text" | "longtext" => SqlType::Text,
+            "json" | "jsonb" => SqlType::Json,
+            "uuid" => SqlType::Uuid,
+            "date" => SqlType::Date,
+            "timestamp" | "timestamp without time zone" | "datetime" => SqlType::Timestamp,
+            "timestamptz" | "timestamp with time zone" => SqlType::TimestampTz,
+            "time" | "time without time zone" => SqlType::Time,
+            "timetz" | "time with time zone" => SqlType::TimeTz,
+            "interval" => SqlType::Interval,
+            "point" => SqlType::Point,
+            _ => return None,
+        };
+        Some(sql_type)
+    }
+
+    fn is_text_type(&self) -> bool {
+        matches!(
+            *self,
+            SqlType::Char
+                | SqlType::Varchar
+                | SqlType::Tinytext
+                | SqlType::Mediumtext
+                | SqlType::Text
+        )
+    }
+
+    fn is_blob_type(&self) -> bool {
+        matches!(
+            *self,
+            SqlType::Tinyblob
+                | SqlType::Mediumblob
+                | SqlType::Blob
+                | SqlType::Longblob
+                | SqlType::Varbinary
+        )
+    }
+
+    /// the value can be passed for an argument of this type without an explicit cast,
+    /// `widen` also lets integer values be passed for decimal types
+    pub(crate) fn accepts(&self, value: &Value, widen: bool) -> bool {
+        if let Value::Nil = value {
+            return true;
+        }
+        match value.get_type() {
+            Some(value_type) => self.accepts_type(&value_type, widen),
+            None => self.same_type(value),
+        }
+    }
+
+    fn accepts_type(&self, value_type: &SqlType, widen: bool) -> bool {
+        match (self.base_type(), value_type) {
+            (SqlType::Array(ty), SqlType::Array(value_type)) => ty.accepts_type(value_type, widen),
+            (ty, value_type) => {
+                ty == value_type
+                    || (ty.is_integer_type() && value_type.is_integer_type())
+                    || (ty.is_decimal_type() && value_type.is_decimal_type())
+                    || (ty.is_decimal_type() && value_type.is_integer_type() && widen)
+                    || (ty.is_text_type() && value_type.is_text_type())
+                    || (ty.is_blob_type() && value_type.is_blob_type())
+                    || (*ty == SqlType::TimestampTz && *value_type == SqlType::Timestamp)
+            }
+        }
+    }
+}
@@ -15,3 +15,230 @@ pub fn maybe_trim_parenthesis(arg: &str) -> &str {
 pub fn eval_f64(expr: &str) -> Result<f64, meval::Error> {
     meval::eval_str(expr)
 }
+
+/// remove one pair of parenthesis only when it encloses the whole expression,
+/// `(a > 0) AND (b > 0)` is left as is
+pub fn trim_enclosing_parenthesis(arg: &str) -> &str {
+    let arg = arg.trim();
+    if !arg.starts_with('(') || !arg.ends_with(')') {
+        return arg;
+    }
+    let mut depth = 0;
+    for (i, c) in arg.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 && i < arg.len() - 1 {
+                    return arg;
+                }
+            }
+            _ => (),
+        }
+    }
+    arg[1..arg.len() - 1].trim()
+}
+
+/// the words and the "double quoted" or `backquoted` identifiers in the sql, quotes are stripped.
+/// The doubled quotes in a quoted identifier are not unescaped.
+pub fn identifiers(sql: &str) -> Vec<&str> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut identifiers = vec![];
+    let mut rest = sql;
+    while let Some(c) = rest.chars().next() {
+        if c == '"' || c == '`' {
+            let end = rest[1..].find(c).map(|i| i + 1).unwrap_or(rest.len());
+            if end > 1 {
+                identifiers.push(&rest[1..end]);
+            }
+            rest = &rest[(end + 1).min(rest.len())..];
+        } else if is_word(c) {
+            let end = rest.find(|c| !is_word(c)).unwrap_or(rest.len());
+            identifiers.push(&rest[..end]);
+            rest = &rest[end..];
+        } else {
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    identifiers
+}
+
+/// extract the select statement from `CREATE VIEW name AS SELECT ..`
+pub fn extract_view_select(create_sql: &str) -> &str {
+    let words: Vec<(usize, &str)> = create_sql
+        .split_whitespace()
+        .map(|word| {
+            let offset = word.as_ptr() as usize - create_sql.as_ptr() as usize;
+            (offset, word)
+        })
+        .collect();
+    for pair in words.windows(2) {
+        if pair[0].1.eq_ignore_ascii_case("AS") {
+            return create_sql[pair[1].0..].trim();
+        }
+    }
+    create_sql
+}
+
+/// the byte offsets of the keyword in the sql where it is a whole word, ignoring the ascii case
+/// and the quoted identifiers and literals, ie: `ON` is not found in `CONSTRAINT` nor in `"on"`.
+/// Only the ascii case is ignored, so the offsets are valid in the sql itself
+fn keyword_offsets(sql: &str, keyword: &str) -> Vec<usize> {
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || !b.is_ascii();
+    let bytes = sql.as_bytes();
+    let keyword = keyword.as_bytes();
+    let mut offsets = vec![];
+    let mut quote: Option<u8> = None;
+    for (i, b) in bytes.iter().enumerate() {
+        match (quote, *b) {
+            (Some(q), b) if b == q => quote = None,
+            (Some(_), _) => (),
+            (None, b'\'') | (None, b'"') | (None, b'`') => quote = Some(*b),
+            (None, b'[') => quote = Some(b']'),
+            (None, _) => {
+                let starts_word = i == 0 || !is_word(bytes[i - 1]);
+                let matches = bytes
+                    .get(i..i + keyword.len())
+                    .map_or(false, |word| word.eq_ignore_ascii_case(keyword));
+                let ends_word = bytes
+                    .get(i + keyword.len())
+                    .map_or(true, |next| !is_word(*next));
+                if starts_word && matches && ends_word {
+                    offsets.push(i);
+                }
+            }
+        }
+    }
+    offsets
+}
+
+/// the byte offset of the first keyword in the sql, see `keyword_offsets`
+pub fn find_keyword(sql: &str, keyword: &str) -> Option<usize> {
+    keyword_offsets(sql, keyword).first().copied()
+}
+
+/// the byte offset of the last keyword in the sql, see `keyword_offsets`
+pub fn rfind_keyword(sql: &str, keyword: &str) -> Option<usize> {
+    keyword_offsets(sql, keyword).last().copied()
+}
+
+/// find the byte range of the column list in the parenthesis of `CREATE INDEX .. ON table(..)`
+fn index_column_list(sql: &str) -> Option<(usize, usize)> {
+    let on = find_keyword(sql, "ON")?;
+    let start = on + sql[on..].find('(')? + 1;
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    for (i, c) in sql[start..].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '\'') | (None, '"') | (None, '`') => quote = Some(c),
+            (None, '[') => quote = Some(']'),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => return Some((start, start + i)),
+            (None, ')') => depth -= 1,
+            (None, ',') | (None, _) => (),
+        }
+    }
+    None
+}
+
+/// extract the indexed expressions of each key part in the create index sql
+pub fn extract_index_expressions(sql: &str) -> Vec<String> {
+    let (start, end) = match index_column_list(sql) {
+        Some(range) => range,
+        None => return vec![],
+    };
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut part_start = start;
+    for (i, c) in sql[start..end].char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '\'') | (None, '"') | (None, '`') => quote = Some(c),
+            (None, '[') => quote = Some(']'),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(&sql[part_start..start + i]);
+                part_start = start + i + 1;
+            }
+            (None, _) => (),
+        }
+    }
+    parts.push(&sql[part_start..end]);
+    parts
+        .iter()
+        .map(|part| {
+            let part = part.trim();
+            let part = match part.rfind(char::is_whitespace) {
+                Some(space)
+                    if part[space + 1..].eq_ignore_ascii_case("ASC")
+                        || part[space + 1..].eq_ignore_ascii_case("DESC") =>
+                {
+                    part[..space].trim_end()
+                }
+                _ => part,
+            };
+            match rfind_keyword(part, "COLLATE") {
+                Some(collate) => part[..collate].trim_end().to_string(),
+                None => part.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// extract the where clause of a partial index in the create index sql
+pub fn extract_index_predicate(sql: &str) -> Option<String> {
+    let (_, end) = index_column_list(sql)?;
+    let rest = &sql[end + 1..];
+    let position = find_keyword(rest, "WHERE")?;
+    Some(rest[position + "WHERE".len()..].trim().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quoted_identifiers() {
+        assert_eq!(
+            identifiers(r#"("first name" <> '') AND `rank`>=0"#),
+            vec!["first name", "AND", "rank", "0"]
+        );
+        assert_eq!(
+            identifiers(r#"len("unterminated"#),
+            vec!["len", "unterminated"]
+        );
+    }
+
+    #[test]
+    fn keywords_are_whole_words() {
+        assert_eq!(find_keyword("CONSTRAINT c ON t", "on"), Some(13));
+        assert_eq!(find_keyword(r#"CREATE INDEX "on" ON t"#, "ON"), Some(18));
+        assert_eq!(find_keyword("SELECT 'where' FROM t", "WHERE"), None);
+        assert_eq!(
+            rfind_keyword("BEGIN SELECT 1 AS backend; END", "END"),
+            Some(27)
+        );
+    }
+
+    #[test]
+    fn index_with_non_ascii_names() {
+        // the uppercase of `ſ` is `S`, which has a different length in utf-8
+        assert_eq!(
+            extract_index_expressions("CREATE INDEX ſé ON t(a)"),
+            vec!["a"]
+        );
+        assert_eq!(
+            extract_index_expressions("create index ſé on t(ſé collate nocase desc, b asc)"),
+            vec!["ſé", "b"]
+        );
+        assert_eq!(
+            extract_index_predicate("CREATE INDEX ſé ON t(a) where ſé > 0"),
+            Some("ſé > 0".to_string())
+        );
+    }
+}
@@ -0,0 +1,15 @@
+use crate::TableName;
+
+/// The definition of a view or a materialized view
+#[derive(Debug, PartialEq, Clone)]
+pub struct ViewDef {
+    pub name: TableName,
+    /// the select statement of the view
+    pub definition: String,
+    /// records can be inserted, updated and deleted on the view directly
+    pub is_updatable: bool,
+    /// a materialized view stores the result and needs to be refreshed, postgresql only
+    pub is_materialized: bool,
+    /// the tables and views used in the definition of this view
+    pub dependencies: Vec<TableName>,
+}